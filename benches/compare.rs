@@ -0,0 +1,129 @@
+//! Compares Mlsp against `Rc` and `Arc` for the sharing patterns of `tests/random_actors.rs`.
+//!
+//! Every group reports throughput in handle operations, so a change that adds atomic operations
+//! to a path that should not perform any shows up as a drop in elements per second.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use mlsp::{Mlsp, MlspPackage};
+
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// The number of clones made of each handle in one iteration
+const CLONES: usize = 64;
+
+/// The number of threads sharing a value in the fan-out group
+const THREADS: usize = 4;
+
+/// Clones and drops a handle `CLONES` times, the way an actor shares a value with its neighbours
+fn churn<H: Clone>(handle: &H) {
+    let clones: Vec<H> = (0..CLONES).map(|_| black_box(handle).clone()).collect();
+    drop(black_box(clones));
+}
+
+/// Clone/drop churn on a single thread, where Mlsp should match Rc and beat Arc
+fn local(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compare/local_churn");
+    group.throughput(Throughput::Elements(CLONES as u64));
+
+    let mlsp = Mlsp::new(7u64);
+    let rc = Rc::new(7u64);
+    let arc = Arc::new(7u64);
+
+    group.bench_function("mlsp", |b| b.iter(|| churn(&mlsp)));
+    group.bench_function("rc", |b| b.iter(|| churn(&rc)));
+    group.bench_function("arc", |b| b.iter(|| churn(&arc)));
+
+    group.finish();
+}
+
+/// Hands a value to a long-lived worker thread which churns it before dropping it.
+///
+/// The Mlsp pays one atomic increment and decrement per handoff however much the worker clones,
+/// while every clone of the Arc on the worker is atomic.
+fn handoff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compare/handoff");
+    group.throughput(Throughput::Elements(CLONES as u64 + 1));
+
+    let mlsp = Mlsp::new(7u64);
+    let (send, recv) = mpsc::channel::<MlspPackage<u64>>();
+    let (done, finished) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        for package in recv {
+            churn(&package.unpackage());
+            done.send(()).unwrap();
+        }
+    });
+    group.bench_function("mlsp", |b| {
+        b.iter(|| {
+            send.send(mlsp.package()).unwrap();
+            finished.recv().unwrap();
+        })
+    });
+    drop(send);
+    worker.join().unwrap();
+
+    let arc = Arc::new(7u64);
+    let (send, recv) = mpsc::channel::<Arc<u64>>();
+    let (done, finished) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        for arc in recv {
+            churn(&arc);
+            done.send(()).unwrap();
+        }
+    });
+    group.bench_function("arc", |b| {
+        b.iter(|| {
+            send.send(arc.clone()).unwrap();
+            finished.recv().unwrap();
+        })
+    });
+    drop(send);
+    worker.join().unwrap();
+
+    group.finish();
+}
+
+/// Several threads churning the same value at once, where every Arc clone contends on one counter
+fn fan_out(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compare/fan_out");
+    group.throughput(Throughput::Elements((THREADS * CLONES * 16) as u64));
+
+    let mlsp = Mlsp::new(7u64);
+    group.bench_function("mlsp", |b| {
+        b.iter(|| {
+            thread::scope(|s| {
+                for package in mlsp.package_n(THREADS) {
+                    s.spawn(move || {
+                        let handle = package.unpackage();
+                        for _ in 0..16 {
+                            churn(&handle);
+                        }
+                    });
+                }
+            })
+        })
+    });
+
+    let arc = Arc::new(7u64);
+    group.bench_function("arc", |b| {
+        b.iter(|| {
+            thread::scope(|s| {
+                for _ in 0..THREADS {
+                    let handle = arc.clone();
+                    s.spawn(move || {
+                        for _ in 0..16 {
+                            churn(&handle);
+                        }
+                    });
+                }
+            })
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, local, handoff, fan_out);
+criterion_main!(benches);
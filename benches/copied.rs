@@ -0,0 +1,47 @@
+//! Compares sharing a small `Copy` value through handles against copying it out.
+//!
+//! For tiny values the copy should win, since it touches neither counter.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mlsp::Mlsp;
+
+use std::thread;
+
+fn local(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_copy/local");
+    let value = Mlsp::new(7u64);
+
+    group.bench_function("clone", |b| {
+        b.iter(|| {
+            let handle = black_box(&value).clone();
+            black_box(*handle.as_ref())
+        })
+    });
+    group.bench_function("copied", |b| b.iter(|| black_box(&value).copied()));
+
+    group.finish();
+}
+
+fn cross_thread(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_copy/cross_thread");
+    let value = Mlsp::new(7u64);
+
+    group.bench_function("package", |b| {
+        b.iter(|| {
+            let package = value.package();
+            thread::spawn(move || *package.unpackage().as_ref())
+                .join()
+                .unwrap()
+        })
+    });
+    group.bench_function("copied", |b| {
+        b.iter(|| {
+            let copy = value.copied();
+            thread::spawn(move || copy).join().unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, local, cross_thread);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Measures the atomic decrement when packages of one allocation are dropped on many threads at once.
+//!
+//! Every thread unpackages and drops its share of the packages while the others do the same,
+//! so almost every decrement is contended and only the last one drops the contents.
+use std::sync::Barrier;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use mlsp::{Mlsp, MlspPackage};
+
+const THREADS: usize = 4;
+const PER_THREAD: usize = 4096;
+
+fn contended_drops(c: &mut Criterion) {
+    let barrier = Barrier::new(THREADS + 1);
+
+    c.bench_function("contended_drops", |b| {
+        b.iter_batched(
+            || {
+                let a = Mlsp::new(vec![0u8; 64]);
+                let packages: Vec<Vec<MlspPackage<Vec<u8>>>> =
+                    (0..THREADS).map(|_| a.package_n(PER_THREAD)).collect();
+                packages
+            },
+            |packages| {
+                thread::scope(|scope| {
+                    for share in packages {
+                        let barrier = &barrier;
+                        scope.spawn(move || {
+                            barrier.wait();
+                            for package in share {
+                                drop(package);
+                            }
+                        });
+                    }
+                    barrier.wait();
+                });
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, contended_drops);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! Compares reading small shared values through `Mlsp` against the copies kept by `InlineMlsp`.
+//!
+//! The handles are spread over many allocations and read in a shuffled order,
+//! so that reading through the pointer of an `Mlsp` misses the cache where the copy doesn't.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mlsp::{InlineMlsp, Mlsp};
+
+const HANDLES: usize = 1 << 16;
+const VALUES: usize = 1 << 14;
+
+/// A fixed permutation of the value indices, so both handle kinds are read in one order
+fn order() -> Vec<usize> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    (0..HANDLES)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % VALUES
+        })
+        .collect()
+}
+
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    let order = order();
+
+    let values: Vec<_> = (0..VALUES as u32).map(Mlsp::new).collect();
+    let mlsp: Vec<_> = order.iter().map(|&i| values[i].clone()).collect();
+    group.bench_function("mlsp", |b| {
+        b.iter(|| black_box(&mlsp).iter().map(|a| *a.as_ref()).sum::<u32>())
+    });
+
+    let values: Vec<_> = (0..VALUES as u32).map(InlineMlsp::new).collect();
+    let inline: Vec<_> = order.iter().map(|&i| values[i].clone()).collect();
+    group.bench_function("inline", |b| {
+        b.iter(|| black_box(&inline).iter().map(InlineMlsp::get).sum::<u32>())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, read);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Compares `Mlsp::new` against `Mlsp::new_pooled` when values are constantly created and dropped.
+//!
+//! Each iteration builds a batch of handles, shares each within the thread and drops them all,
+//! the same construct/share/drop cycle as the actors in `tests/random_actors.rs`.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use mlsp::Mlsp;
+
+const BATCH: u64 = 32;
+
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+
+    group.bench_function("new", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(BATCH as usize),
+            |handles| {
+                handles.extend((0..BATCH).map(|i| Mlsp::new(black_box(i))));
+                handles.extend(handles.clone());
+                handles.clear();
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("new_pooled", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(BATCH as usize),
+            |handles| {
+                handles.extend((0..BATCH).map(|i| Mlsp::new_pooled(black_box(i))));
+                handles.extend(handles.clone());
+                handles.clear();
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, churn);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! Compares `Mlsp` against the single-pointer `ThinMlsp` when many handles are stored together.
+//!
+//! Each iteration clones a column of handles, as a struct of arrays would to share a column,
+//! sums the values through the clones and drops them.
+//! `ThinMlsp` halves the memory of the column but looks up its local count on every clone and drop.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mlsp::{Mlsp, ThinMlsp};
+
+const COLUMN: u64 = 4096;
+const VALUES: u64 = 64;
+
+fn column(c: &mut Criterion) {
+    let mut group = c.benchmark_group("column");
+
+    // The column repeats a few shared values, as a column of interned values would
+    let values: Vec<_> = (0..VALUES).map(Mlsp::new).collect();
+    let mlsp: Vec<_> = (0..COLUMN)
+        .map(|i| values[(i % VALUES) as usize].clone())
+        .collect();
+    group.bench_function("mlsp", |b| {
+        b.iter(|| {
+            let copies = black_box(&mlsp).clone();
+            copies.iter().map(|a| *a.as_ref()).sum::<u64>()
+        })
+    });
+
+    let values: Vec<_> = (0..VALUES).map(ThinMlsp::new).collect();
+    let thin: Vec<_> = (0..COLUMN)
+        .map(|i| values[(i % VALUES) as usize].clone())
+        .collect();
+    group.bench_function("thin", |b| {
+        b.iter(|| {
+            let copies = black_box(&thin).clone();
+            copies.iter().map(|a| *a.as_ref()).sum::<u64>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, column);
+criterion_main!(benches);
@@ -0,0 +1,157 @@
+//! Byte slices placed in memory provided by the caller, such as a shared-memory region.
+//!
+//! `Mlsp::new_slice_in` writes the inner of an `Mlsp<[u8]>` into memory taken from an `Arena`
+//! instead of the global allocator. Such an inner is never freed by this crate:
+//! once the last reference is gone its memory is left to the arena to reclaim.
+//!
+//! The inner is `repr(C)`, so its contents start `data_offset()` bytes after the start of
+//! the memory it was given, with the atomic count as the first `usize` of the header.
+//! A second process mapping the same region at another address can find the contents from
+//! their offset within the region and rebuild a package for them with `MlspPackage::from_raw`,
+//! as long as both run the same build of this crate with the same features.
+//! The counters are plain atomics, so the processes count their references together,
+//! while local counters and the handles that use them stay private to each process.
+
+use std::alloc::Layout;
+use std::ptr::{self, NonNull};
+
+use crate::{AllocError, Mlsp, MlspInner};
+
+/// A source of memory for `Mlsp::new_slice_in`.
+///
+/// # Safety
+/// `allocate` must return memory that is valid for reads and writes of `layout`,
+/// is aligned to `layout.align()` and is not handed out again while the arena lives.
+pub unsafe trait Arena {
+    /// Returns memory for `layout`, or `None` if the arena is out of space.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+}
+
+/// The offset of the contents of an `Mlsp<[u8]>` from the start of the memory holding its inner.
+pub fn data_offset() -> usize {
+    MlspInner::<[u8]>::data_offset(1)
+}
+
+impl Mlsp<[u8]> {
+    /// Creates a zeroed slice of `len` bytes in memory taken from `arena`.
+    ///
+    /// The memory is not returned to the arena when the last reference is dropped,
+    /// the arena reclaims it on its own terms, for example by unmapping its whole region.
+    ///
+    /// # Safety
+    /// The memory given out by `arena` must stay valid until every handle, package
+    /// and weak reference to the slice has been dropped, in every process sharing it.
+    pub unsafe fn new_slice_in(len: usize, arena: &impl Arena) -> Result<Self, AllocError> {
+        let layout = MlspInner::<[u8]>::slice_layout(len).map_err(|_| AllocError)?;
+        let mem = arena.allocate(layout).ok_or(AllocError)?;
+
+        let inner = MlspInner::<[u8]>::init_for_slice(mem.as_ptr(), len);
+        ptr::write(ptr::addr_of_mut!((*inner.as_ptr()).in_arena), true);
+        let data = ptr::addr_of_mut!((*inner.as_ptr()).data) as *mut u8;
+        ptr::write_bytes(data, 0, len);
+
+        Ok(Mlsp::from_inner(inner, false))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::MlspPackage;
+
+    /// A bump allocator over an anonymous shared mapping, unmapped when it is dropped
+    struct Region {
+        base: NonNull<u8>,
+        len: usize,
+        next: Cell<usize>,
+    }
+
+    impl Region {
+        fn map(len: usize) -> Self {
+            // SAFETY: A new anonymous mapping does not alias any existing memory
+            let base = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            assert!(base != libc::MAP_FAILED);
+            Region {
+                base: NonNull::new(base as *mut u8).unwrap(),
+                len,
+                next: Cell::new(0),
+            }
+        }
+
+        fn offset_of(&self, ptr: *const u8) -> usize {
+            ptr as usize - self.base.as_ptr() as usize
+        }
+    }
+
+    unsafe impl Arena for Region {
+        fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+            let start = self.next.get().next_multiple_of(layout.align());
+            let end = start.checked_add(layout.size())?;
+            if end > self.len {
+                return None;
+            }
+            self.next.set(end);
+            // SAFETY: The range is inside the mapping and was not handed out before
+            NonNull::new(unsafe { self.base.as_ptr().add(start) })
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            // SAFETY: The mapping was created by `map` with this length
+            unsafe { libc::munmap(self.base.as_ptr() as *mut _, self.len) };
+        }
+    }
+
+    #[test]
+    fn shared_region_layout() {
+        let region = Region::map(4096);
+
+        // SAFETY: The region outlives every reference made below
+        let mut a = unsafe { Mlsp::new_slice_in(8, &region) }.unwrap();
+        assert_eq!([0; 8], a.as_ref());
+        a.get_mut().unwrap().copy_from_slice(b"mlsp ipc");
+
+        // The offset and length are all another process would need
+        let offset = region.offset_of(a.as_ptr() as *const u8);
+        assert_eq!(data_offset(), offset);
+        let package = a.package();
+
+        // Simulate the other process by finding the header and contents from the offset alone
+        let base = region.base.as_ptr();
+        // SAFETY: The atomic count is the first word of the inner, at the start of the region
+        let count = unsafe { &*(base as *const AtomicUsize) };
+        assert_eq!(2, count.load(Ordering::Acquire));
+
+        // The package's reference travels as the offset
+        let _ = package.into_raw();
+        let ptr = ptr::slice_from_raw_parts(unsafe { base.add(offset) }, 8);
+        let received = unsafe { MlspPackage::from_raw(ptr) }.unpackage();
+        assert_eq!(b"mlsp ipc", received.as_ref());
+
+        // Releasing every reference leaves the memory to the region instead of freeing it
+        drop(received);
+        assert_eq!(1, count.load(Ordering::Acquire));
+        drop(a);
+        assert_eq!(0, count.load(Ordering::Acquire));
+
+        // Allocations follow each other in the region until it runs out
+        let b = unsafe { Mlsp::new_slice_in(16, &region) }.unwrap();
+        assert!(region.offset_of(b.as_ptr() as *const u8) > offset);
+        assert!(unsafe { Mlsp::new_slice_in(4096, &region) }.is_err());
+        drop(b);
+    }
+}
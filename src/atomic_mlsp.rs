@@ -0,0 +1,370 @@
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+use crate::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+use crate::{Mlsp, MlspPackage};
+
+/// A slot holding a shared value that any thread can load or replace,
+/// for configuration that is read often and updated rarely.
+///
+/// The slot holds one package's worth of reference to its current value.
+/// Loading takes a new handle to it, and replacing the value waits for
+/// loads already in progress before releasing the old one,
+/// so a loader never increments the count of a value that has been freed.
+/// Loads announce themselves in one of two generations, and a writer moves new loads
+/// to the other generation before waiting, so it only waits for the loads that began
+/// before it and a steady stream of loads can't hold it back.
+/// Writers release their old values one at a time.
+/// ```
+/// let config = std::sync::Arc::new(mlsp::AtomicMlsp::new(mlsp::Mlsp::new(1u32)));
+///
+/// let current = config.load();
+/// assert!(config.compare_and_set(&current, mlsp::Mlsp::new(2)).is_ok());
+///
+/// // An update based on an old version is refused
+/// assert!(config.compare_and_set(&current, mlsp::Mlsp::new(3)).is_err());
+/// assert_eq!(2, *config.load().as_ref());
+/// ```
+pub struct AtomicMlsp<T> {
+    current: AtomicPtr<T>,
+    /// The number of loads between reading `current` and counting their reference to it,
+    /// for each generation
+    loading: [AtomicUsize; 2],
+    /// The generation new loads announce themselves in
+    generation: AtomicUsize,
+    /// Held by the writer waiting for a generation of loads
+    retiring: AtomicBool,
+}
+
+impl<T> AtomicMlsp<T> {
+    /// Creates a slot holding `value`.
+    pub fn new(value: Mlsp<T>) -> Self {
+        AtomicMlsp {
+            current: AtomicPtr::new(value.into_package().into_raw() as *mut T),
+            loading: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            generation: AtomicUsize::new(0),
+            retiring: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns a handle to the current value.
+    ///
+    /// This performs an atomic increment of the value's count,
+    /// and two more on the slot to announce the load to writers,
+    /// or four if a writer changes the generation while the load announces itself.
+    pub fn load(&self) -> Mlsp<T> {
+        self.load_package().unpackage()
+    }
+
+    /// Returns a guard that reads the current value for as long as it lives,
+    /// in the style of an RCU read-side critical section.
+    ///
+    /// The guard holds its own reference to the version it loaded, so writers can replace
+    /// the value without waiting for it, and that version is freed once the last guard
+    /// or handle reading it is dropped. It costs the same atomic operations as `load`,
+    /// but holds a package rather than a handle, so no local counter is created for it.
+    /// ```
+    /// let config = mlsp::AtomicMlsp::new(mlsp::Mlsp::new(String::from("v1")));
+    /// let guard = config.read();
+    /// config.store(mlsp::Mlsp::new(String::from("v2")));
+    ///
+    /// // The guard keeps reading the version it loaded
+    /// assert_eq!("v1", guard.as_str());
+    /// assert_eq!("v2", config.read().as_str());
+    /// ```
+    pub fn read(&self) -> MlspReadGuard<T> {
+        MlspReadGuard {
+            version: self.load_package(),
+        }
+    }
+
+    /// Takes a package of the current value.
+    fn load_package(&self) -> MlspPackage<T> {
+        // Announced before reading the pointer, so a writer that replaces the value
+        // afterwards waits until the reference has been counted
+        let generation = loop {
+            let generation = self.generation.load(Ordering::Acquire);
+            self.loading[generation].fetch_add(1, Ordering::Acquire);
+            // A writer that has moved on from the generation may not be waiting for it,
+            // so the load announces itself again in the current one
+            if self.generation.load(Ordering::Acquire) == generation {
+                break generation;
+            }
+            self.loading[generation].fetch_sub(1, Ordering::Release);
+        };
+        let current = self.current.load(Ordering::Acquire);
+        // SAFETY: The slot's own reference can't be released until this load is done
+        let package = ManuallyDrop::new(unsafe { MlspPackage::from_raw(current) });
+        let loaded = (*package).clone();
+        self.loading[generation].fetch_sub(1, Ordering::Release);
+
+        loaded
+    }
+
+    /// Replaces the current value with `new`, releasing the slot's reference to the old one.
+    pub fn store(&self, new: Mlsp<T>) {
+        drop(self.swap(new));
+    }
+
+    /// Replaces the current value with `new` and returns the old one.
+    pub fn swap(&self, new: Mlsp<T>) -> Mlsp<T> {
+        let new = new.into_package().into_raw() as *mut T;
+        let old = self.current.swap(new, Ordering::AcqRel);
+        // SAFETY: The slot's reference to the old value was taken out of it by the swap
+        unsafe { self.retire(old) }.unpackage()
+    }
+
+    /// Replaces the current value with `new` only if it is still the value `expected` refers to,
+    /// otherwise returns `new` unchanged.
+    ///
+    /// Values are compared by allocation, so this succeeds only for a caller that observed
+    /// the latest version, and of several racing updaters based on it exactly one succeeds.
+    /// `expected` keeps its allocation alive, so its address can't be reused by a newer value.
+    pub fn compare_and_set(&self, expected: &Mlsp<T>, new: Mlsp<T>) -> Result<(), Mlsp<T>> {
+        let new = new.into_package().into_raw() as *mut T;
+        match self.current.compare_exchange(
+            expected.as_ptr() as *mut T,
+            new,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        ) {
+            Ok(old) => {
+                // SAFETY: The exchange took the slot's reference to the old value out of it
+                drop(unsafe { self.retire(old) });
+                Ok(())
+            }
+            // SAFETY: The new value was never published, so its package is still ours
+            Err(_) => Err(unsafe { MlspPackage::from_raw(new) }.unpackage()),
+        }
+    }
+
+    /// Consumes the slot and returns its current value.
+    pub fn into_inner(self) -> Mlsp<T> {
+        let this = ManuallyDrop::new(self);
+        let current = this.current.load(Ordering::Relaxed);
+        // SAFETY: No loads can be in progress on a slot taken by value
+        unsafe { MlspPackage::from_raw(current) }.unpackage()
+    }
+
+    /// Waits for the loads in progress to count their references,
+    /// then returns the slot's reference to a value that has been replaced.
+    ///
+    /// # Safety
+    /// `old` must have been the current value, and have been replaced by this thread.
+    unsafe fn retire(&self, old: *mut T) -> MlspPackage<T> {
+        while self
+            .retiring
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            yield_now();
+        }
+
+        // Loads that read the new generation synchronize with the flip, so they read the new value.
+        // Those announced in the old one before it are waited for, and a load that announces
+        // itself there afterwards sees the flip when it checks the generation, and moves.
+        let old_generation = self.generation.fetch_xor(1, Ordering::AcqRel);
+
+        // The check is a read-modify-write, so every announcement is ordered
+        // either before it, and the check waits for that load to count its reference,
+        // or after it, and that load synchronizes with the check and reads the new value
+        while self.loading[old_generation].fetch_add(0, Ordering::AcqRel) != 0 {
+            yield_now();
+        }
+
+        // The next writer starts after these loads are done, which it relies on
+        // for loads that announced in its old generation before this writer's flip
+        self.retiring.store(false, Ordering::Release);
+        MlspPackage::from_raw(old)
+    }
+}
+
+fn yield_now() {
+    #[cfg(not(loom))]
+    std::thread::yield_now();
+    #[cfg(loom)]
+    loom::thread::yield_now();
+}
+
+impl<T> Drop for AtomicMlsp<T> {
+    fn drop(&mut self) {
+        let current = self.current.load(Ordering::Relaxed);
+        // SAFETY: No loads can be in progress on a slot being dropped
+        drop(unsafe { MlspPackage::from_raw(current) });
+    }
+}
+
+// SAFETY: The slot only holds a package, and hands out handles made from it
+unsafe impl<T: Send + Sync> Send for AtomicMlsp<T> {}
+unsafe impl<T: Send + Sync> Sync for AtomicMlsp<T> {}
+
+/// A version of the value of an `AtomicMlsp`, held by a reader, see `AtomicMlsp::read`.
+pub struct MlspReadGuard<T> {
+    version: MlspPackage<T>,
+}
+
+impl<T> MlspReadGuard<T> {
+    /// Turns the guard into a handle to the version it read,
+    /// to be shared within this thread.
+    pub fn into_mlsp(self) -> Mlsp<T> {
+        self.version.unpackage()
+    }
+}
+
+impl<T> Deref for MlspReadGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard's package keeps the version alive for as long as the guard,
+        // and the slot only hands versions of `T: Send + Sync` to other threads
+        unsafe { &*self.version.as_ptr() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{mpsc, Arc, Barrier, Mutex};
+    use std::thread;
+
+    #[test]
+    fn one_racing_update_succeeds() {
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(0usize)));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let updaters: Vec<_> = (1..=8)
+            .map(|id| {
+                let slot = slot.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    let observed = slot.load();
+                    // Every updater has observed the first version before any of them update
+                    barrier.wait();
+                    match slot.compare_and_set(&observed, Mlsp::new(id)) {
+                        Ok(()) => true,
+                        Err(refused) => {
+                            assert_eq!(id, *refused.as_ref());
+                            false
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let winners: Vec<_> = updaters
+            .into_iter()
+            .map(|updater| updater.join().unwrap())
+            .enumerate()
+            .filter(|&(_, won)| won)
+            .map(|(i, _)| i + 1)
+            .collect();
+        assert_eq!(1, winners.len());
+        assert_eq!(winners[0], *slot.load().as_ref());
+    }
+
+    #[test]
+    fn readers_keep_their_versions() {
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(vec![0usize; 8])));
+        let first = slot.load().downgrade();
+        let (reading, started) = mpsc::channel();
+        let (swapped, resume) = mpsc::channel::<()>();
+        let resume = Arc::new(Mutex::new(resume));
+
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let slot = slot.clone();
+                let reading = reading.clone();
+                let resume = resume.clone();
+                thread::spawn(move || {
+                    let guard = slot.read();
+                    reading.send(()).unwrap();
+                    let _ = resume.lock().unwrap().recv();
+
+                    // The version read before the swaps is unchanged, later reads see the last
+                    assert_eq!(vec![0; 8], *guard);
+                    assert_eq!(vec![3; 8], *slot.read());
+                })
+            })
+            .collect();
+        for _ in 0..3 {
+            started.recv().unwrap();
+        }
+
+        // Versions that no reader holds are freed as soon as they are replaced
+        let mut replaced = Vec::new();
+        for version in 1..=3 {
+            let next = Mlsp::new(vec![version; 8]);
+            replaced.push(next.downgrade());
+            slot.store(next);
+        }
+        assert_eq!(3, first.strong_count());
+        assert_eq!(0, replaced[0].strong_count());
+        assert_eq!(0, replaced[1].strong_count());
+
+        drop(swapped);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(0, first.strong_count());
+        assert_eq!(1, replaced[2].strong_count());
+    }
+
+    #[test]
+    fn replaced_values_outlive_loads() {
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(vec![0usize; 4])));
+        let first = slot.load().downgrade();
+
+        let loaders: Vec<_> = (0..2)
+            .map(|_| {
+                let slot = slot.clone();
+                thread::spawn(move || {
+                    for _ in 0..200 {
+                        let value = slot.load();
+                        let first = value.as_ref()[0];
+                        assert!(value.as_ref().iter().all(|&v| v == first));
+                    }
+                })
+            })
+            .collect();
+        for i in 1..200 {
+            slot.store(Mlsp::new(vec![i; 4]));
+        }
+        for loader in loaders {
+            loader.join().unwrap();
+        }
+
+        // The slot released each value it replaced
+        assert_eq!(0, first.strong_count());
+        let last = Arc::try_unwrap(slot).ok().unwrap().into_inner();
+        assert_eq!(vec![199; 4], last.try_unwrap().ok().unwrap());
+    }
+
+    #[test]
+    fn writers_finish_under_steady_loads() {
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(0usize)));
+        let done = Arc::new(AtomicBool::new(false));
+
+        // Four loaders keep loads overlapping until every store has gone through
+        let loaders: Vec<_> = (0..4)
+            .map(|_| {
+                let slot = slot.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    while !done.load(Ordering::Acquire) {
+                        drop(slot.load());
+                    }
+                })
+            })
+            .collect();
+        for i in 1..=100 {
+            slot.store(Mlsp::new(i));
+        }
+        done.store(true, Ordering::Release);
+        for loader in loaders {
+            loader.join().unwrap();
+        }
+        assert_eq!(100, *slot.load().as_ref());
+    }
+}
@@ -0,0 +1,157 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{Mlsp, MlspWeak};
+
+/// A cache that only holds weak references to its values,
+/// so a value is evicted once every `Mlsp` and `MlspPackage` for it has been dropped.
+///
+/// The cache can be shared between threads,
+/// values are handed out as `Mlsp`s local to the calling thread.
+/// ```
+/// let cache = mlsp::MlspCache::new();
+/// let a = cache.get_or_insert_with("a", || 1u8);
+/// assert_eq!(Some(1u8), cache.get("a").map(|a| *a.as_ref()));
+///
+/// drop(a);
+/// assert!(cache.get("a").is_none());
+/// ```
+pub struct MlspCache<K, V> {
+    entries: Mutex<HashMap<K, MlspWeak<V>>>,
+}
+
+impl<K: Eq + Hash, V> MlspCache<K, V> {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        MlspCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value for `key` if it is still alive,
+    /// removing the entry if it is not.
+    pub fn get<Q>(&self, key: &Q) -> Option<Mlsp<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut entries = self.entries();
+        let value = entries.get(key)?.upgrade();
+        if value.is_none() {
+            entries.remove(key);
+        }
+        value
+    }
+
+    /// Returns the value for `key`, creating it with `f` if it is missing or no longer alive.
+    ///
+    /// The cache is locked while `f` runs.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> Mlsp<V> {
+        let mut entries = self.entries();
+        if let Some(value) = entries.get(&key).and_then(MlspWeak::upgrade) {
+            return value;
+        }
+
+        let value = Mlsp::new(f());
+        entries.insert(key, value.downgrade());
+        value
+    }
+
+    /// Removes every entry whose value is no longer alive,
+    /// returning how many were removed.
+    pub fn purge_dead(&self) -> usize {
+        let mut entries = self.entries();
+        let before = entries.len();
+        entries.retain(|_, value| value.strong_count() > 0);
+        before - entries.len()
+    }
+
+    /// The number of entries, including any dead entries that have not been purged yet.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns true if the cache has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<K, MlspWeak<V>>> {
+        // The map is never left half-updated, so a poisoned lock is still safe to use
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K: Eq + Hash, V> Default for MlspCache<K, V> {
+    fn default() -> Self {
+        MlspCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_entries_are_purged() {
+        let cache = MlspCache::new();
+        let a = cache.get_or_insert_with(1, || String::from("a"));
+        let b = cache.get_or_insert_with(2, || String::from("b"));
+        let c = cache.get_or_insert_with(3, || String::from("c"));
+        assert_eq!(3, cache.len());
+
+        // Accessing a dead entry removes it
+        drop(a);
+        assert!(cache.get(&1).is_none());
+        assert_eq!(2, cache.len());
+
+        // A package keeps the entry alive after its Mlsp drops
+        let b_package = b.package();
+        drop(b);
+        drop(c);
+        assert_eq!(1, cache.purge_dead());
+        assert_eq!(Some("b"), cache.get(&2).as_ref().map(AsRef::<str>::as_ref));
+
+        drop(b_package);
+        assert_eq!(1, cache.purge_dead());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn concurrent_access() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::thread;
+
+        let cache = Mlsp::new(MlspCache::new());
+        let created = Mlsp::new(AtomicUsize::new(0));
+
+        // Keep the value alive so every thread sees the same entry
+        let keep_alive = cache.as_ref().get_or_insert_with("key", || 7u32);
+
+        let mut children = vec![];
+        for _ in 0..10 {
+            let cache = cache.package();
+            let created = created.package();
+
+            children.push(thread::spawn(move || {
+                let cache = cache.unpackage();
+                let created = created.unpackage();
+                let value = cache.as_ref().get_or_insert_with("key", || {
+                    created.as_ref().fetch_add(1, Ordering::Relaxed);
+                    0
+                });
+                *value.as_ref()
+            }));
+        }
+
+        for child in children {
+            assert_eq!(7, child.join().unwrap());
+        }
+        assert_eq!(0, created.as_ref().load(Ordering::Relaxed));
+        drop(keep_alive);
+    }
+}
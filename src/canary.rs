@@ -0,0 +1,162 @@
+//! Detects `Mlsp`s leaked with `mem::forget` in debug builds.
+//!
+//! Every thread counts the local counters it has live.
+//! Since `Mlsp` cannot leave its thread, any counter still live when the thread exits
+//! belongs to handles that were never dropped, which permanently inflates the atomic count.
+//! The thread reports them on exit, to stderr unless a hook is set with `set_leak_hook`,
+//! and adds them to `leaked_local_counters`.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::Arc;
+use std::sync::Mutex;
+
+static LEAKED: AtomicUsize = AtomicUsize::new(0);
+
+static LEAK_HOOK: Mutex<Option<fn(usize)>> = Mutex::new(None);
+
+thread_local! {
+    static OUTSTANDING: Outstanding = const {
+        Outstanding {
+            live: Cell::new(0),
+            #[cfg(test)]
+            report: Cell::new(None),
+        }
+    };
+}
+
+struct Outstanding {
+    live: Cell<usize>,
+    /// Also receives this thread's leaks on exit, see `report_exit_to`
+    #[cfg(test)]
+    report: Cell<Option<Arc<AtomicUsize>>>,
+}
+
+impl Drop for Outstanding {
+    fn drop(&mut self) {
+        let leaked = self.live.get();
+
+        #[cfg(test)]
+        if let Some(report) = self.report.take() {
+            report.fetch_add(leaked, Ordering::Relaxed);
+        }
+
+        if leaked == 0 {
+            return;
+        }
+        LEAKED.fetch_add(leaked, Ordering::Relaxed);
+
+        let hook = *LEAK_HOOK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match hook {
+            Some(hook) => hook(leaked),
+            None => eprintln!(
+                "mlsp: a thread exited with {} local counters that were never dropped, \
+                 an Mlsp may have been leaked with mem::forget",
+                leaked
+            ),
+        }
+    }
+}
+
+/// The number of local counters, across all threads that have exited,
+/// that were still live when their thread exited.
+///
+/// Only available in builds with `debug_assertions`.
+/// Counters for `Mlsp`s stored in other thread-locals may be reported
+/// if those thread-locals are destroyed after the tracking count.
+pub fn leaked_local_counters() -> usize {
+    LEAKED.load(Ordering::Relaxed)
+}
+
+/// Sets a function to call, on the exiting thread, with the number of local counters it leaked,
+/// in place of the line printed to stderr. `None` restores the line.
+///
+/// The hook runs while the thread's locals are being destroyed, so it must not panic,
+/// which would abort the process.
+///
+/// Only available in builds with `debug_assertions`.
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static LEAKS: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn count_leaks(leaked: usize) {
+///     LEAKS.fetch_add(leaked, Ordering::Relaxed);
+/// }
+///
+/// mlsp::set_leak_hook(Some(count_leaks));
+/// let package = mlsp::Mlsp::new(1u8).package();
+/// std::thread::spawn(move || std::mem::forget(package.unpackage()))
+///     .join()
+///     .unwrap();
+/// assert_eq!(1, LEAKS.load(Ordering::Relaxed));
+/// mlsp::set_leak_hook(None);
+/// ```
+pub fn set_leak_hook(hook: Option<fn(usize)>) {
+    *LEAK_HOOK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = hook;
+}
+
+pub(crate) fn register() {
+    // The count may already be destroyed if this runs during thread exit
+    let _ = OUTSTANDING.try_with(|outstanding| outstanding.live.set(outstanding.live.get() + 1));
+}
+
+pub(crate) fn unregister() {
+    let _ = OUTSTANDING.try_with(|outstanding| {
+        outstanding
+            .live
+            .set(outstanding.live.get().saturating_sub(1))
+    });
+}
+
+/// The number of local counters currently live on this thread
+#[cfg(test)]
+pub(crate) fn outstanding() -> usize {
+    OUTSTANDING.with(|outstanding| outstanding.live.get())
+}
+
+/// Also adds the counters this thread leaks to `report` when it exits,
+/// which unlike `leaked_local_counters` is not affected by other threads
+#[cfg(test)]
+pub(crate) fn report_exit_to(report: Arc<AtomicUsize>) {
+    OUTSTANDING.with(|outstanding| outstanding.report.set(Some(report)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mlsp;
+
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn forgotten_handle_is_reported() {
+        let a = Mlsp::new(1u8);
+        let package = a.package();
+
+        let before = leaked_local_counters();
+        let report = Arc::new(AtomicUsize::new(0));
+        let thread_report = report.clone();
+        thread::spawn(move || {
+            report_exit_to(thread_report);
+            let b = package.unpackage();
+            drop(b.clone());
+            mem::forget(b);
+
+            // Dropped handles are not reported
+            drop(Mlsp::new(2u8));
+        })
+        .join()
+        .unwrap();
+
+        // Other test threads may leak too, so only this thread's report is exact
+        assert_eq!(1, report.load(Ordering::Relaxed));
+        assert!(leaked_local_counters() > before);
+    }
+}
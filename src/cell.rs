@@ -0,0 +1,92 @@
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+
+use crate::{Mlsp, MlspPackage};
+
+/// An Mlsp whose contents can be mutated through any handle,
+/// for building custom synchronization on top of Mlsp.
+///
+/// Nothing prevents two handles from accessing the contents at the same time,
+/// it is up to the caller of `get` to rule out data races.
+/// ```
+/// let cell = mlsp::MlspCell::new(1u32);
+/// let other = cell.clone();
+///
+/// // SAFETY: No other reference to the contents exists while this one is used
+/// unsafe { *other.get() += 1 };
+///
+/// assert_eq!(2, unsafe { *cell.get() });
+/// ```
+pub struct MlspCell<T> {
+    inner: Mlsp<UnsafeCell<T>>,
+}
+
+impl<T> MlspCell<T> {
+    /// Creates a new MlspCell wrapping the given value.
+    pub fn new(data: T) -> Self {
+        MlspCell {
+            inner: Mlsp::new(UnsafeCell::new(data)),
+        }
+    }
+
+    /// A pointer to the contents, shared by every handle and package of this cell.
+    ///
+    /// # Safety
+    /// The caller must guarantee that no data race occurs through the returned pointer:
+    /// while a `&mut T` made from it is alive no other reference to the contents may be used,
+    /// including those made through other handles.
+    ///
+    /// This is not checked in any way. Once the cell has been packaged to another thread,
+    /// every access on every thread must be ordered by synchronization of the caller's own,
+    /// such as a lock or an atomic flag with acquire/release ordering.
+    pub unsafe fn get(&self) -> *mut T {
+        let cell: &UnsafeCell<T> = self.inner.borrow();
+        cell.get()
+    }
+
+    /// Create a Send-able package from the MlspCell
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> MlspCellPackage<T> {
+        MlspCellPackage {
+            inner: self.inner.package(),
+        }
+    }
+}
+
+impl<T> Clone for MlspCell<T> {
+    fn clone(&self) -> Self {
+        MlspCell {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A reference to the contents of an MlspCell
+/// that can be sent across threads.
+pub struct MlspCellPackage<T> {
+    inner: MlspPackage<UnsafeCell<T>>,
+}
+
+impl<T> MlspCellPackage<T> {
+    /// Turns this package into a normal MlspCell that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> MlspCell<T> {
+        MlspCell {
+            inner: self.inner.unpackage(),
+        }
+    }
+}
+
+impl<T> Clone for MlspCellPackage<T> {
+    fn clone(&self) -> Self {
+        MlspCellPackage {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// SAFETY: Every access to the contents goes through the unsafe `MlspCell::get`,
+// whose caller is responsible for synchronizing accesses across threads.
+unsafe impl<T: Send + Sync> Send for MlspCellPackage<T> {}
+unsafe impl<T: Send + Sync> Sync for MlspCellPackage<T> {}
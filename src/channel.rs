@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvError, SendError, Sender, TryRecvError};
+
+use crate::{Mlsp, MlspPackage};
+
+/// Creates a channel that carries `Mlsp`s between threads as packages,
+/// packaging them on send and unpackaging them on receipt.
+/// ```
+/// let (send, recv) = mlsp::channel();
+/// send.send(&mlsp::Mlsp::new(1u8)).unwrap();
+///
+/// std::thread::spawn(move || assert_eq!(1, *recv.recv().unwrap().as_ref()))
+///     .join()
+///     .unwrap();
+/// ```
+pub fn channel<T: ?Sized>() -> (MlspSender<T>, MlspReceiver<T>) {
+    let (sender, receiver) = mpsc::channel();
+    (MlspSender { sender }, MlspReceiver { receiver })
+}
+
+/// Creates a channel like `channel` whose sender refuses to package a value
+/// while `max_outstanding` references to it already exist elsewhere,
+/// giving each shared value its own backpressure.
+///
+/// A value's references are counted by its atomic count,
+/// which counts each package and each thread holding `Mlsp`s to it.
+/// The sending thread's own handles take up one more on top of `max_outstanding`.
+/// ```
+/// let (send, recv) = mlsp::bounded_channel(1);
+/// let a = mlsp::Mlsp::new(1u8);
+/// send.send(&a).unwrap();
+/// assert_eq!(Err(mlsp::BoundedSendError::Full), send.send(&a));
+///
+/// drop(recv.recv());
+/// send.send(&a).unwrap();
+/// ```
+pub fn bounded_channel<T: ?Sized>(
+    max_outstanding: usize,
+) -> (MlspBoundedSender<T>, MlspReceiver<T>) {
+    let (sender, receiver) = channel();
+    (
+        MlspBoundedSender {
+            sender,
+            max_outstanding,
+        },
+        receiver,
+    )
+}
+
+/// The sending half of a channel made by `channel`.
+pub struct MlspSender<T: ?Sized> {
+    sender: Sender<MlspPackage<T>>,
+}
+
+impl<T: ?Sized> MlspSender<T> {
+    /// Packages `a` and sends the package, returning it if the receiver is gone.
+    pub fn send(&self, a: &Mlsp<T>) -> Result<(), SendError<MlspPackage<T>>> {
+        self.send_package(a.package())
+    }
+
+    /// Sends a package that has already been made.
+    pub fn send_package(&self, package: MlspPackage<T>) -> Result<(), SendError<MlspPackage<T>>> {
+        self.sender.send(package)
+    }
+}
+
+impl<T: ?Sized> Clone for MlspSender<T> {
+    fn clone(&self) -> Self {
+        MlspSender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+/// The sending half of a channel made by `bounded_channel`.
+pub struct MlspBoundedSender<T: ?Sized> {
+    sender: MlspSender<T>,
+    max_outstanding: usize,
+}
+
+impl<T: ?Sized> MlspBoundedSender<T> {
+    /// Packages `a` and sends the package, unless the value already has `max_outstanding`
+    /// references outside the sending thread, in which case the caller keeps `a` to retry later.
+    ///
+    /// The check and the increment are a single compare-and-swap, see `Mlsp::package_n_bounded`.
+    pub fn send(&self, a: &Mlsp<T>) -> Result<(), BoundedSendError> {
+        let mut packages = a
+            .package_n_bounded(1, self.max_outstanding.saturating_add(1))
+            .map_err(|_| BoundedSendError::Full)?;
+        let package = packages.pop().unwrap();
+        // The package is dropped along with the error, returning its count
+        self.sender
+            .send_package(package)
+            .map_err(|_| BoundedSendError::Disconnected)
+    }
+
+    /// The most references to a value that may exist outside the sending thread.
+    pub fn max_outstanding(&self) -> usize {
+        self.max_outstanding
+    }
+}
+
+impl<T: ?Sized> Clone for MlspBoundedSender<T> {
+    fn clone(&self) -> Self {
+        MlspBoundedSender {
+            sender: self.sender.clone(),
+            max_outstanding: self.max_outstanding,
+        }
+    }
+}
+
+/// The error returned when an `MlspBoundedSender` can't send a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundedSendError {
+    /// The value already has the most outstanding references allowed.
+    Full,
+    /// The receiver has been dropped.
+    Disconnected,
+}
+
+impl fmt::Display for BoundedSendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundedSendError::Full => f.write_str("too many outstanding references"),
+            BoundedSendError::Disconnected => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl Error for BoundedSendError {}
+
+/// The receiving half of a channel made by `channel`.
+pub struct MlspReceiver<T: ?Sized> {
+    receiver: Receiver<MlspPackage<T>>,
+}
+
+impl<T: ?Sized> MlspReceiver<T> {
+    /// Blocks until a package arrives and unpackages it, see `mpsc::Receiver::recv`.
+    pub fn recv(&self) -> Result<Mlsp<T>, RecvError> {
+        self.receiver.recv().map(MlspPackage::unpackage)
+    }
+
+    /// Unpackages the next package if one is queued, without blocking.
+    pub fn try_recv(&self) -> Result<Mlsp<T>, TryRecvError> {
+        self.receiver.try_recv().map(MlspPackage::unpackage)
+    }
+
+    /// Unpackages every package queued right now, without blocking,
+    /// so that a worker can process its mailbox in batches.
+    ///
+    /// Handles in the batch that refer to the same allocation share one local counter,
+    /// so the batch holds one share of each allocation's atomic_count however often it was sent.
+    pub fn drain(&self) -> impl Iterator<Item = Mlsp<T>> {
+        let mut batch: Vec<Mlsp<T>> = Vec::new();
+        let mut first_of: HashMap<*const (), usize> = HashMap::new();
+        while let Ok(package) = self.receiver.try_recv() {
+            let a = package.unpackage();
+            let a = match first_of.get(&(a.as_ptr() as *const ())) {
+                // The allocation is the same, so merging always succeeds
+                Some(&first) => match batch[first].merge_local(a) {
+                    Ok(merged) | Err(merged) => merged,
+                },
+                None => {
+                    first_of.insert(a.as_ptr() as *const (), batch.len());
+                    a
+                }
+            };
+            batch.push(a);
+        }
+        batch.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn bounded_sends_wait_for_drops() {
+        let (send, recv) = bounded_channel::<u32>(2);
+        let hot = Mlsp::new(1);
+        let other = Mlsp::new(2);
+        let weak = hot.downgrade();
+
+        send.send(&hot).unwrap();
+        send.send(&hot).unwrap();
+        assert_eq!(Err(BoundedSendError::Full), send.send(&hot));
+        assert_eq!(3, weak.strong_count());
+
+        // The bound is per value
+        send.send(&other).unwrap();
+
+        // Once the receiving side lets go of a package there is room again
+        let received = thread::spawn(move || {
+            let first = recv.recv().unwrap();
+            assert_eq!(1, *first.as_ref());
+            drop(first);
+            recv
+        })
+        .join()
+        .unwrap();
+        assert_eq!(2, weak.strong_count());
+        send.send(&hot).unwrap();
+        assert_eq!(Err(BoundedSendError::Full), send.send(&hot));
+
+        drop(received);
+        assert_eq!(Err(BoundedSendError::Disconnected), send.send(&other));
+        assert_eq!(1, other.downgrade().strong_count());
+    }
+
+    #[test]
+    fn drain_shares_counters() {
+        let (send, recv) = channel::<String>();
+        let repeated = Mlsp::new(String::from("repeated"));
+        let once = Mlsp::new(String::from("once"));
+        let (repeated_weak, once_weak) = (repeated.downgrade(), once.downgrade());
+
+        for a in [&repeated, &once, &repeated, &repeated] {
+            send.send(a).unwrap();
+        }
+        drop((repeated, once, send));
+        assert_eq!(3, repeated_weak.strong_count());
+
+        thread::spawn(move || {
+            let batch: Vec<_> = recv.drain().collect();
+            assert_eq!(
+                vec!["repeated", "once", "repeated", "repeated"],
+                batch.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>()
+            );
+
+            // The three copies of one allocation hold a single share of its count
+            assert_eq!(1, repeated_weak.strong_count());
+            assert_eq!(1, once_weak.strong_count());
+            assert!(recv.drain().next().is_none());
+
+            drop(batch);
+            assert_eq!(0, repeated_weak.strong_count());
+            assert!(recv.recv().is_err());
+        })
+        .join()
+        .unwrap();
+    }
+}
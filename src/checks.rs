@@ -0,0 +1,68 @@
+//! Checks of local counters made in builds with `debug_assertions`.
+//!
+//! Every live counter carries a sentinel, checked when it is freed to catch double frees
+//! and corruption, and the thread it is confined to by `Mlsp::confined_scope`,
+//! checked whenever its count changes.
+
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::LocalCounter;
+
+/// Marks a live local counter, checked when it is freed to catch double frees and corruption
+pub(crate) const SENTINEL: usize = 0x6d6c_7370;
+
+/// Confines a local counter to the current thread until the guard is dropped.
+///
+/// Handles without a local counter are not confined.
+pub(crate) fn confine(counter: NonNull<LocalCounter>) -> Option<Confinement> {
+    if counter == crate::ATOMIC_ONLY {
+        return None;
+    }
+
+    // SAFETY: The handle confining the counter keeps it alive for as long as the guard
+    let previous = unsafe { counter.as_ref() }
+        .confined
+        .swap(thread_key(), Ordering::Relaxed);
+    Some(Confinement { counter, previous })
+}
+
+/// Restores the confinement a counter had before `confine`, which is kept by nested scopes
+pub(crate) struct Confinement {
+    counter: NonNull<LocalCounter>,
+    previous: u64,
+}
+
+impl Drop for Confinement {
+    fn drop(&mut self) {
+        // SAFETY: See `confine`
+        unsafe { self.counter.as_ref() }
+            .confined
+            .store(self.previous, Ordering::Relaxed);
+    }
+}
+
+/// Panics if a counter is being used outside the thread it is confined to.
+///
+/// The owner is read atomically, since the point is to run on a thread that isn't the owner,
+/// possibly while the owner enters or leaves a scope.
+pub(crate) fn check_confined(counter: NonNull<LocalCounter>) {
+    // SAFETY: The caller's handle keeps the counter alive
+    let confined = unsafe { counter.as_ref() }.confined.load(Ordering::Relaxed);
+    assert!(
+        confined == UNCONFINED || confined == thread_key(),
+        "a confined Mlsp was used on another thread"
+    );
+}
+
+/// The owner recorded for a counter outside any `confined_scope`
+pub(crate) const UNCONFINED: u64 = 0;
+
+/// A key for the current thread, unique among all threads and never `UNCONFINED`
+fn thread_key() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(UNCONFINED + 1);
+    thread_local! {
+        static KEY: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    KEY.with(|key| *key)
+}
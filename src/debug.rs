@@ -0,0 +1,585 @@
+//! Diagnostics for tracking down why a value is not being freed.
+//!
+//! When the `debug` feature is enabled every `Mlsp` and `MlspPackage`
+//! registers itself in a global registry keyed by the allocation it refers to.
+//! This has a considerable cost, since every clone and drop takes a global lock.
+//!
+//! Packages also record the thread they were created on, see `MlspPackage::source_thread`
+//! and `set_cross_thread_check` for reporting packages unpackaged somewhere else.
+//!
+//! Allocations whose contents implement `MlspEdges` can be registered with `Mlsp::track_edges`,
+//! after which `detect_cycles` finds groups of them that only keep each other alive.
+//!
+//! Each local counter holds one share of the atomic count for all the handles using it,
+//! so the registry also counts the live local counters of each allocation,
+//! and every decrement of the atomic count checks that it stays at least that many.
+//! An unbalanced release, which would otherwise free contents that handles are still using,
+//! panics at the decrement instead.
+
+use std::collections::{HashMap, HashSet};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread::{self, ThreadId};
+
+use crate::{Mlsp, MlspInner, MlspPackage};
+
+/// The live handles and packages of one allocation at the time it was inspected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandleReport {
+    /// The number of live `Mlsp` handles on each thread that holds at least one.
+    pub threads: HashMap<ThreadId, usize>,
+    /// The number of live `MlspPackage`s, which do not belong to any thread.
+    pub packages: usize,
+}
+
+impl HandleReport {
+    /// The total number of live `Mlsp` handles across all threads.
+    pub fn handles(&self) -> usize {
+        self.threads.values().sum()
+    }
+
+    /// Iterates over the threads holding handles along with how many handles each holds.
+    pub fn iter(&self) -> impl Iterator<Item = (ThreadId, usize)> + '_ {
+        self.threads.iter().map(|(id, count)| (*id, *count))
+    }
+}
+
+/// Reports the live handles and packages for the allocation containing `ptr`.
+///
+/// `ptr` must point to the contents of an Mlsp, as returned by `Mlsp::as_ptr`.
+/// Allocations that have been freed, or were never tracked, report no handles.
+pub fn inspect<T: ?Sized>(ptr: *const T) -> HandleReport {
+    registry()
+        .get(&(ptr as *const () as usize))
+        .map(|entry| entry.report.clone())
+        .unwrap_or_default()
+}
+
+/// Lists the shared values a node holds, so that `detect_cycles` can follow them.
+///
+/// `edges` must call `edge` once with `as_ptr()` for each `Mlsp` and `MlspPackage` the node
+/// holds, cast to `*const ()`, and must not create or drop any handles while doing so.
+pub trait MlspEdges {
+    /// Reports each shared value this node holds.
+    fn edges(&self, edge: &mut dyn FnMut(*const ()));
+}
+
+impl<T: MlspEdges> Mlsp<T> {
+    /// Registers these contents for `detect_cycles`, for as long as they are referenced.
+    pub fn track_edges(&self) {
+        /// Reports the edges of the contents at `key`, which must be a live `T`
+        unsafe fn edges_of<T: MlspEdges>(key: usize, edge: &mut dyn FnMut(*const ())) {
+            (*(key as *const T)).edges(edge)
+        }
+
+        if let Some(entry) = registry().get_mut(&(self.as_ptr() as usize)) {
+            entry.edges = Some(edges_of::<T>);
+        }
+    }
+}
+
+/// Finds the groups of tracked allocations that are kept alive only by each other,
+/// and so will never be freed, returning the addresses of their contents.
+///
+/// Only allocations registered with `track_edges` whose handles are all on the calling thread
+/// are followed, since contents can't be read safely while another thread may be using them.
+/// A group is reported when it forms a cycle and every handle and package to its members
+/// is one of the edges between them.
+/// ```
+/// use std::cell::RefCell;
+/// use mlsp::debug::{detect_cycles, MlspEdges};
+/// use mlsp::Mlsp;
+///
+/// struct Node(RefCell<Option<Mlsp<Node>>>);
+///
+/// impl MlspEdges for Node {
+///     fn edges(&self, edge: &mut dyn FnMut(*const ())) {
+///         if let Some(next) = &*self.0.borrow() {
+///             edge(next.as_ptr() as *const ());
+///         }
+///     }
+/// }
+///
+/// let a = Mlsp::new(Node(RefCell::new(None)));
+/// a.track_edges();
+/// *a.as_ref().0.borrow_mut() = Some(a.clone());
+/// let ptr = a.as_ptr() as *const ();
+/// drop(a);
+/// assert!(detect_cycles().contains(&vec![ptr]));
+/// ```
+pub fn detect_cycles() -> Vec<Vec<*const ()>> {
+    let registry = registry();
+    let current = thread::current().id();
+
+    // The tracked allocations that only the calling thread can be using, and their edges
+    let mut graph: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (&key, entry) in registry.iter() {
+        let Some(edges_of) = entry.edges else {
+            continue;
+        };
+        if entry.report.threads.keys().any(|id| *id != current) {
+            continue;
+        }
+        let mut edges = Vec::new();
+        // SAFETY: Each live handle or package in the registry keeps its contents alive,
+        // and every handle is on this thread, which is busy here
+        unsafe { edges_of(key, &mut |ptr| edges.push(ptr as usize)) };
+        graph.insert(key, edges);
+    }
+    for edges in graph.values_mut() {
+        edges.retain(|target| registry.get(target).is_some_and(|e| e.edges.is_some()));
+    }
+    let followed: HashSet<usize> = graph.keys().copied().collect();
+    for edges in graph.values_mut() {
+        edges.retain(|target| followed.contains(target));
+    }
+
+    strongly_connected(&graph)
+        .into_iter()
+        .filter(|component| {
+            let members: HashSet<usize> = component.iter().copied().collect();
+            let cyclic = component.len() > 1 || graph[&component[0]].contains(&component[0]);
+
+            // References to each member that come from inside the group
+            let mut inside: HashMap<usize, usize> = HashMap::new();
+            for member in component {
+                for target in graph[member].iter().filter(|t| members.contains(t)) {
+                    *inside.entry(*target).or_default() += 1;
+                }
+            }
+            cyclic
+                && component.iter().all(|member| {
+                    let report = &registry[member].report;
+                    inside.get(member).copied().unwrap_or(0) == report.handles() + report.packages
+                })
+        })
+        .map(|component| component.into_iter().map(|key| key as *const ()).collect())
+        .collect()
+}
+
+/// Splits a graph into its strongly connected components with Kosaraju's algorithm,
+/// each component sorted by address.
+fn strongly_connected(graph: &HashMap<usize, Vec<usize>>) -> Vec<Vec<usize>> {
+    let mut nodes: Vec<usize> = graph.keys().copied().collect();
+    nodes.sort_unstable();
+
+    // Order the nodes by when a depth-first search finishes with them,
+    // keeping an explicit stack so that long chains can't overflow the real one
+    let mut finished = Vec::with_capacity(nodes.len());
+    let mut visited = HashSet::new();
+    for &root in &nodes {
+        if !visited.insert(root) {
+            continue;
+        }
+        let mut stack = vec![(root, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            match graph[node].get(*next) {
+                Some(&target) => {
+                    *next += 1;
+                    if visited.insert(target) {
+                        stack.push((target, 0));
+                    }
+                }
+                None => {
+                    finished.push(*node);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    let mut reversed: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (&node, edges) in graph {
+        for &target in edges {
+            reversed.entry(target).or_default().push(node);
+        }
+    }
+
+    // Each search of the reversed graph, latest finished first, collects one component
+    let mut assigned = HashSet::new();
+    let mut components = Vec::new();
+    for &root in finished.iter().rev() {
+        if !assigned.insert(root) {
+            continue;
+        }
+        let mut component = vec![root];
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for &source in reversed.get(&node).into_iter().flatten() {
+                if assigned.insert(source) {
+                    component.push(source);
+                    stack.push(source);
+                }
+            }
+        }
+        component.sort_unstable();
+        components.push(component);
+    }
+    components
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// The number of threads currently holding at least one `Mlsp` for these contents.
+    ///
+    /// Packages in flight between threads do not belong to any thread and are not counted,
+    /// see `inspect` for the number of them.
+    pub fn live_thread_count(&self) -> usize {
+        inspect(self.as_ptr()).threads.len()
+    }
+}
+
+impl<T: ?Sized> MlspPackage<T> {
+    /// The thread this package was created on.
+    ///
+    /// A clone of a package comes from the same thread as the original.
+    /// Packages rebuilt with `from_raw` or `from_token` have no known source and return `None`.
+    pub fn source_thread(&self) -> Option<ThreadId> {
+        self.source
+    }
+}
+
+/// What `MlspPackage::unpackage` does with a package created on another thread.
+///
+/// Unpackaging on another thread is what packages are for, so this is only useful
+/// while tracking down a package that was expected to come back to where it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrossThreadCheck {
+    /// Unpackage it silently.
+    #[default]
+    Ignore,
+    /// Print a line to stderr naming both threads.
+    Log,
+    /// Panic, leaving the package to be dropped normally.
+    Panic,
+}
+
+static CROSS_THREAD_CHECK: AtomicU8 = AtomicU8::new(CrossThreadCheck::Ignore as u8);
+
+/// Sets what every thread does when unpackaging a package created on another thread.
+/// ```
+/// use mlsp::debug::{set_cross_thread_check, CrossThreadCheck};
+///
+/// set_cross_thread_check(CrossThreadCheck::Panic);
+/// let package = mlsp::Mlsp::new(1u8).package();
+/// let result = std::thread::spawn(move || drop(package.unpackage())).join();
+/// assert!(result.is_err());
+/// set_cross_thread_check(CrossThreadCheck::Ignore);
+/// ```
+pub fn set_cross_thread_check(check: CrossThreadCheck) {
+    CROSS_THREAD_CHECK.store(check as u8, Ordering::Relaxed);
+}
+
+/// Applies the cross-thread check to a package with the given source being unpackaged.
+pub(crate) fn check_unpackage(source: Option<ThreadId>) {
+    let check = CROSS_THREAD_CHECK.load(Ordering::Relaxed);
+    if check == CrossThreadCheck::Ignore as u8 {
+        return;
+    }
+    let Some(source) = source else {
+        return;
+    };
+    let current = thread::current().id();
+    if source == current {
+        return;
+    }
+
+    if check == CrossThreadCheck::Log as u8 {
+        eprintln!("mlsp: package created on {source:?} unpackaged on {current:?}");
+    } else {
+        panic!("package created on {source:?} unpackaged on {current:?}");
+    }
+}
+
+/// Reports the edges of the contents at a registry key, see `Mlsp::track_edges`
+type EdgesFn = unsafe fn(usize, &mut dyn FnMut(*const ()));
+
+/// What the registry knows about one allocation
+#[derive(Default)]
+struct Entry {
+    report: HandleReport,
+    /// Set by `track_edges` for contents that implement `MlspEdges`
+    edges: Option<EdgesFn>,
+    /// The live local counters, and thin handle threads, each holding one share of the count
+    local_counters: usize,
+}
+
+fn registry() -> MutexGuard<'static, HashMap<usize, Entry>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<usize, Entry>>> = OnceLock::new();
+
+    // A panic while the registry is locked cannot leave it half-updated,
+    // so a poisoned lock is still safe to use.
+    REGISTRY
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The registry key for an allocation, the address of its data.
+fn key<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) -> usize {
+    // SAFETY: Only computes an address within the allocation, nothing is read.
+    unsafe { ptr::addr_of!((*inner_ptr.as_ptr()).data) as *const () as usize }
+}
+
+/// Updates the entry for an allocation, removing it once nothing references it.
+fn update<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>, f: impl FnOnce(&mut Entry)) {
+    let key = key(inner_ptr);
+    let mut registry = registry();
+    let entry = registry.entry(key).or_default();
+    f(entry);
+    if entry.report.packages == 0 && entry.report.threads.is_empty() && entry.local_counters == 0 {
+        registry.remove(&key);
+    }
+}
+
+pub(crate) fn handle_created<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| {
+        *entry
+            .report
+            .threads
+            .entry(thread::current().id())
+            .or_default() += 1;
+    });
+}
+
+pub(crate) fn handle_dropped<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| {
+        let id = thread::current().id();
+        if let Some(count) = entry.report.threads.get_mut(&id) {
+            *count -= 1;
+            if *count == 0 {
+                entry.report.threads.remove(&id);
+            }
+        }
+    });
+}
+
+pub(crate) fn package_created<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| entry.report.packages += 1);
+}
+
+pub(crate) fn package_dropped<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| entry.report.packages -= 1);
+}
+
+pub(crate) fn counter_created<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| entry.local_counters += 1);
+}
+
+/// Called before the counter's share of the atomic count is released or handed on
+pub(crate) fn counter_released<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) {
+    update(inner_ptr, |entry| entry.local_counters -= 1);
+}
+
+/// Holds the registry locked across a decrement of the atomic count,
+/// so no local counter can be created or released between the decrement and its check.
+pub(crate) struct DecrementCheck {
+    registry: MutexGuard<'static, HashMap<usize, Entry>>,
+    key: usize,
+}
+
+pub(crate) fn lock_decrement<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) -> DecrementCheck {
+    DecrementCheck {
+        registry: registry(),
+        key: key(inner_ptr),
+    }
+}
+
+impl DecrementCheck {
+    /// Checks a decrement by `n` from `old` left a share for every live local counter.
+    ///
+    /// Every counter in the registry was created after its share was counted
+    /// and is released before its share is, so they are all included in `old`,
+    /// and the share being released is not one of theirs.
+    pub(crate) fn check(self, old: usize, n: usize) {
+        let local_counters = self
+            .registry
+            .get(&self.key)
+            .map_or(0, |entry| entry.local_counters);
+        // Unlocked before panicking, or before the contents' drop can take handles of its own
+        drop(self.registry);
+        assert!(
+            old >= n && old - n >= local_counters,
+            "atomic count fell below the {local_counters} local counters sharing it"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Barrier;
+
+    #[test]
+    fn handles_on_two_threads() {
+        let a = Mlsp::new(1u8);
+        let _b = a.clone();
+        let ptr = a.as_ptr();
+
+        let package = a.package();
+        let report = inspect(ptr);
+        assert_eq!(1, report.packages);
+        assert_eq!(2, report.handles());
+
+        let barrier = Barrier::new(2);
+        thread::scope(|s| {
+            let child = s.spawn(|| {
+                let c = package.unpackage();
+                let _d = c.clone();
+                barrier.wait();
+                // Hold the handles until the main thread has inspected them
+                barrier.wait();
+            });
+
+            barrier.wait();
+            let report = inspect(ptr);
+            assert_eq!(0, report.packages);
+            assert_eq!(4, report.handles());
+            assert_eq!(Some(&2), report.threads.get(&thread::current().id()));
+            assert_eq!(Some(&2), report.threads.get(&child.thread().id()));
+            barrier.wait();
+        });
+
+        let report = inspect(ptr);
+        assert_eq!(2, report.handles());
+        assert_eq!(
+            vec![(thread::current().id(), 2)],
+            report.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn source_threads() {
+        let a = Mlsp::new(1u8);
+        let here = thread::current().id();
+        let package = a.package();
+        assert_eq!(Some(here), package.source_thread());
+        assert_eq!(Some(here), a.clone().into_package().source_thread());
+
+        // Packages made on another thread name it, including clones sent back
+        let (there, returned) = thread::spawn(move || {
+            let b = package.unpackage();
+            let returned = b.package();
+            (thread::current().id(), returned.clone())
+        })
+        .join()
+        .unwrap();
+        assert_eq!(Some(there), returned.source_thread());
+        assert!(there != here);
+
+        let rebuilt = unsafe { MlspPackage::from_raw(returned.into_raw()) };
+        assert_eq!(None, rebuilt.source_thread());
+    }
+
+    #[test]
+    fn reports_deliberate_cycles() {
+        use std::cell::RefCell;
+
+        struct Node(RefCell<Vec<Mlsp<Node>>>);
+
+        impl MlspEdges for Node {
+            fn edges(&self, edge: &mut dyn FnMut(*const ())) {
+                for next in self.0.borrow().iter() {
+                    edge(next.as_ptr() as *const ());
+                }
+            }
+        }
+
+        let node = || {
+            let a = Mlsp::new(Node(RefCell::new(Vec::new())));
+            a.track_edges();
+            a
+        };
+        let key = |a: &Mlsp<Node>| a.as_ptr() as *const ();
+        let reported = |keys: &[*const ()]| {
+            let mut keys = keys.to_vec();
+            keys.sort();
+            detect_cycles().contains(&keys)
+        };
+
+        // a -> b -> c -> a, with d hanging off the cycle
+        let (a, b, c, d) = (node(), node(), node(), node());
+        a.as_ref().0.borrow_mut().push(b.clone());
+        b.as_ref().0.borrow_mut().push(c.clone());
+        c.as_ref().0.borrow_mut().push(a.clone());
+        c.as_ref().0.borrow_mut().push(d.clone());
+        let cycle = [key(&a), key(&b), key(&c)];
+
+        // Not leaked while the local handles are alive
+        assert!(!reported(&cycle));
+        let weak = a.downgrade();
+        drop((a, b, c, d));
+        assert!(reported(&cycle));
+
+        // Breaking the cycle through a weak reference frees all of it
+        let a = weak.upgrade().unwrap();
+        assert!(!reported(&cycle));
+        let b = a.as_ref().0.borrow_mut().pop().unwrap();
+        drop((a, b));
+        assert!(weak.upgrade().is_none());
+        assert!(!reported(&cycle));
+
+        // A chain without a cycle is never reported
+        let (e, f) = (node(), node());
+        e.as_ref().0.borrow_mut().push(f.clone());
+        drop(f);
+        assert!(!detect_cycles().iter().any(|group| group.contains(&key(&e))));
+    }
+
+    #[test]
+    fn live_threads() {
+        let a = Mlsp::new(1u8);
+        let packages = a.package_n(2);
+        assert_eq!(1, a.live_thread_count());
+
+        let barrier = Barrier::new(3);
+        thread::scope(|s| {
+            for package in packages {
+                let barrier = &barrier;
+                s.spawn(move || {
+                    let _b = package.unpackage();
+                    barrier.wait();
+                    barrier.wait();
+                });
+            }
+
+            barrier.wait();
+            assert_eq!(3, a.live_thread_count());
+            barrier.wait();
+        });
+
+        assert_eq!(1, a.live_thread_count());
+    }
+
+    #[test]
+    fn undercounted_releases_are_caught() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::Ordering;
+
+        let a = Mlsp::new(String::from("in use"));
+        let ptr = a.as_ptr();
+        let package = a.package();
+
+        // Lose the package's share, so releasing it takes the count to zero while `a` is alive
+        unsafe {
+            a.inner_ptr
+                .as_ref()
+                .atomic_count
+                .store(1, Ordering::Relaxed)
+        };
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(package))).is_err());
+        assert_eq!("in use", AsRef::<str>::as_ref(&a));
+
+        // The check panicked before the contents were dropped, so restoring the share of `a`
+        // lets it free them as usual
+        unsafe {
+            a.inner_ptr
+                .as_ref()
+                .atomic_count
+                .store(1, Ordering::Relaxed)
+        };
+        drop(a);
+        assert_eq!(HandleReport::default(), inspect(ptr));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::Mlsp;
+
+/// A type that links to the next node of a list with an `Mlsp`,
+/// so that a long list can be dropped one node at a time with `Mlsp::drop_list`.
+///
+/// As with `Rc` and `Box`, dropping the head of a list of `Mlsp`s drops the next node
+/// from inside the drop of the current one, so a long enough list overflows the stack.
+/// A node type opts out of this by implementing the trait and having its `Drop`
+/// hand its next node to `drop_list`, which unlinks the remaining nodes in a loop.
+/// ```
+/// use mlsp::{Mlsp, MlspDropList};
+///
+/// struct Node {
+///     next: Option<Mlsp<Node>>,
+/// }
+///
+/// impl MlspDropList for Node {
+///     fn take_next(&mut self) -> Option<Mlsp<Node>> {
+///         self.next.take()
+///     }
+/// }
+///
+/// impl Drop for Node {
+///     fn drop(&mut self) {
+///         if let Some(next) = self.next.take() {
+///             next.drop_list();
+///         }
+///     }
+/// }
+///
+/// let mut head = Mlsp::new(Node { next: None });
+/// for _ in 0..100_000 {
+///     head = Mlsp::new(Node { next: Some(head) });
+/// }
+/// drop(head);
+/// ```
+pub trait MlspDropList: Sized {
+    /// Unlinks and returns the next node, if there is one.
+    fn take_next(&mut self) -> Option<Mlsp<Self>>;
+}
+
+impl<T: MlspDropList> Mlsp<T> {
+    /// Drops this handle and then, for as long as each node has no other references,
+    /// unlinks the next node before dropping the current one, so that the stack does not grow.
+    ///
+    /// The walk stops at the first node that is still referenced elsewhere,
+    /// which keeps the rest of the list alive as before.
+    pub fn drop_list(self) {
+        let mut next = Some(self);
+        while let Some(node) = next {
+            next = match node.try_unwrap() {
+                Ok(mut node) => node.take_next(),
+                // Dropping a shared handle only releases a reference, it can't recurse
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node {
+        value: usize,
+        next: Option<Mlsp<Node>>,
+    }
+
+    impl MlspDropList for Node {
+        fn take_next(&mut self) -> Option<Mlsp<Node>> {
+            self.next.take()
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            if let Some(next) = self.next.take() {
+                next.drop_list();
+            }
+        }
+    }
+
+    fn chain(len: usize) -> Mlsp<Node> {
+        let mut head = Mlsp::new(Node {
+            value: 0,
+            next: None,
+        });
+        for value in 1..len {
+            head = Mlsp::new(Node {
+                value,
+                next: Some(head),
+            });
+        }
+        head
+    }
+
+    #[test]
+    fn million_node_chain() {
+        let head = chain(1_000_000);
+        assert_eq!(999_999, head.as_ref().value);
+        drop(head);
+    }
+
+    #[test]
+    fn shared_tail_survives() {
+        let head = chain(10);
+        let mut tail = head.clone();
+        for _ in 0..5 {
+            let next = tail.as_ref().next.clone().unwrap();
+            tail = next;
+        }
+        let weak = head.downgrade();
+
+        drop(head);
+        assert_eq!(0, weak.strong_count());
+        assert_eq!(4, tail.as_ref().value);
+        let rest = std::iter::successors(Some(tail.clone()), |node| node.as_ref().next.clone());
+        assert_eq!(
+            vec![4, 3, 2, 1, 0],
+            rest.map(|node| node.as_ref().value).collect::<Vec<_>>()
+        );
+    }
+}
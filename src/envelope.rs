@@ -0,0 +1,103 @@
+use crate::{Mlsp, MlspPackage};
+
+/// A routing tag traveling together with a shared payload.
+///
+/// Sealing packages the payload, so an envelope is `Send` when the tag is `Send`
+/// and the payload is `Send + Sync`, and opening it on the receiving thread unpackages it.
+/// ```
+/// let (send, recv) = std::sync::mpsc::channel();
+/// let payload = mlsp::Mlsp::new(String::from("hello"));
+/// send.send(mlsp::MlspEnvelope::seal("greet", &payload)).unwrap();
+///
+/// std::thread::spawn(move || {
+///     let (tag, payload) = recv.recv().unwrap().open();
+///     assert_eq!(("greet", "hello"), (tag, AsRef::<str>::as_ref(&payload)));
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct MlspEnvelope<Tag, T: ?Sized> {
+    /// The tag the payload is routed by.
+    pub tag: Tag,
+    payload: MlspPackage<T>,
+}
+
+impl<Tag, T: ?Sized> MlspEnvelope<Tag, T> {
+    /// Packages `payload` into an envelope with `tag`.
+    ///
+    /// This increments the atomic_count
+    pub fn seal(tag: Tag, payload: &Mlsp<T>) -> Self {
+        MlspEnvelope {
+            tag,
+            payload: payload.package(),
+        }
+    }
+
+    /// Puts an existing package into an envelope with `tag`.
+    pub fn from_package(tag: Tag, payload: MlspPackage<T>) -> Self {
+        MlspEnvelope { tag, payload }
+    }
+
+    /// Takes the tag and unpackages the payload for the current thread.
+    pub fn open(self) -> (Tag, Mlsp<T>) {
+        (self.tag, self.payload.unpackage())
+    }
+
+    /// Takes the tag and the payload, still packaged to be forwarded elsewhere.
+    pub fn into_parts(self) -> (Tag, MlspPackage<T>) {
+        (self.tag, self.payload)
+    }
+}
+
+impl<Tag: Clone, T: ?Sized> Clone for MlspEnvelope<Tag, T> {
+    fn clone(&self) -> Self {
+        MlspEnvelope {
+            tag: self.tag.clone(),
+            payload: self.payload.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[derive(Debug, PartialEq)]
+    enum Route {
+        Upper,
+        Count,
+    }
+
+    #[test]
+    fn tagged_across_threads() {
+        let text = Mlsp::new(String::from("envelope"));
+        let weak = text.downgrade();
+        let (send, recv) = mpsc::channel();
+        send.send(MlspEnvelope::seal(Route::Upper, &text)).unwrap();
+        send.send(MlspEnvelope::seal(Route::Count, &text)).unwrap();
+        drop(send);
+        assert_eq!(3, weak.strong_count());
+
+        let results = thread::spawn(move || {
+            recv.into_iter()
+                .map(|envelope| match envelope.open() {
+                    (Route::Upper, text) => AsRef::<str>::as_ref(&text).to_uppercase(),
+                    (Route::Count, text) => text.len().to_string(),
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(vec!["ENVELOPE", "8"], results.join().unwrap());
+        assert_eq!(1, weak.strong_count());
+
+        // Forwarding keeps the payload packaged
+        let envelope = MlspEnvelope::seal(Route::Count, &text);
+        assert_eq!(Route::Count, envelope.tag);
+        let (_, package) = envelope.into_parts();
+        let (tag, copy) = MlspEnvelope::from_package(Route::Upper, package).open();
+        assert_eq!(Route::Upper, tag);
+        assert!(copy.was_unpackaged());
+    }
+}
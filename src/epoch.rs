@@ -0,0 +1,83 @@
+//! Versioning the fan-out of an allocation, enabled by the `epoch` feature.
+//!
+//! Every inner carries a counter of the packages made from its handles,
+//! which `package` and `package_n` advance with one more atomic operation each.
+
+use std::sync::atomic::Ordering;
+
+use crate::atomic::AtomicUsize;
+use crate::{Mlsp, MlspInner};
+
+/// Advances the epoch of `inner` for `n` packages made from one of its handles.
+pub(crate) fn advance<T: ?Sized>(inner: &MlspInner<T>, n: usize) {
+    if n > 0 {
+        inner.package_epoch.fetch_add(n, Ordering::Release);
+    }
+}
+
+/// The initial epoch of a new inner.
+pub(crate) fn new() -> AtomicUsize {
+    AtomicUsize::new(0)
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// The number of packages made from handles to this allocation by `package`, `package_n`
+    /// and `package_n_bounded`, on any thread.
+    ///
+    /// A consumer can poll this to notice that another reference was handed out,
+    /// as a version of the value's fan-out, without a separate synchronization structure.
+    /// Moving the last handle on a thread into a package with `into_package`
+    /// and cloning packages don't count, since they don't hand out a reference from a handle.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let seen = a.package_epoch();
+    /// let package = a.package();
+    /// assert_eq!(seen + 1, a.package_epoch());
+    /// ```
+    pub fn package_epoch(&self) -> usize {
+        // SAFETY: The existence of this Mlsp keeps the inner alive
+        unsafe {
+            self.inner_ptr
+                .as_ref()
+                .package_epoch
+                .load(Ordering::Acquire)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn advances_with_each_package() {
+        let a = Mlsp::new(String::from("fan-out"));
+        assert_eq!(0, a.package_epoch());
+
+        let first = a.package();
+        assert_eq!(1, a.package_epoch());
+        let batch = a.package_n(3);
+        assert_eq!(4, a.package_epoch());
+        assert_eq!(0, a.package_n(0).len());
+        assert!(a.package_n_bounded(2, 16).is_ok());
+        assert_eq!(6, a.package_epoch());
+
+        // Only packages made from handles count
+        let moved = first.clone().unpackage().into_package();
+        assert_eq!(6, a.package_epoch());
+
+        // Every thread sees the same epoch, and advances it for all of them
+        let epoch = thread::spawn(move || {
+            let b = moved.unpackage();
+            let _package = b.package();
+            b.package_epoch()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(7, epoch);
+        assert_eq!(7, a.package_epoch());
+        drop((first, batch));
+    }
+}
@@ -0,0 +1,201 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::{Mlsp, MlspPackage};
+
+/// A handle to contents that will never be mutated again, made by `Mlsp::freeze`.
+///
+/// It shares, clones and packages like the `Mlsp` it was made from,
+/// but has no `get_mut`, `make_mut` or other way to reach the contents mutably,
+/// even once it is the only handle left, and there is no way back to an `Mlsp`.
+/// ```compile_fail
+/// let mut config = mlsp::Mlsp::new(vec![1, 2]).freeze();
+/// config.make_mut().push(3);
+/// ```
+///
+/// Since its contents can't change, it compares and hashes by its contents and can be used as
+/// the key of a `HashMap` or `HashSet`, looked up by a reference to the contents.
+/// `Mlsp` deliberately does neither, since mutating a key through `get_mut` or `make_mut`
+/// would change its hash while it is in the map.
+/// ```compile_fail
+/// let mut set = std::collections::HashSet::new();
+/// set.insert(mlsp::Mlsp::new(String::from("key")));
+/// ```
+pub struct FrozenMlsp<T: ?Sized> {
+    inner: Mlsp<T>,
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Turns this handle into one that can only read the contents,
+    /// as a marker that a value such as shared configuration is done being built.
+    ///
+    /// Other handles to the same contents are unaffected.
+    /// ```
+    /// let config = mlsp::Mlsp::new(String::from("fast")).freeze();
+    /// let package = config.package();
+    /// std::thread::spawn(move || assert_eq!("fast", &*package.unpackage()))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    pub fn freeze(self) -> FrozenMlsp<T> {
+        FrozenMlsp { inner: self }
+    }
+}
+
+impl<T: ?Sized> FrozenMlsp<T> {
+    /// Create a Send-able package from the handle
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> FrozenMlspPackage<T> {
+        FrozenMlspPackage {
+            package: self.inner.package(),
+        }
+    }
+
+    /// A pointer to the contents, which stays valid as long as any reference to them exists.
+    pub fn as_ptr(&self) -> *const T {
+        self.inner.as_ptr()
+    }
+}
+
+impl<T: ?Sized> Deref for FrozenMlsp<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner.as_ref()
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for FrozenMlsp<T> {
+    fn as_ref(&self) -> &T {
+        self.inner.as_ref()
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for FrozenMlsp<T> {
+    fn borrow(&self) -> &T {
+        self.inner.as_ref()
+    }
+}
+
+/// Shows the handle it was frozen from, counts included, rather than the contents it derefs to.
+impl<T: ?Sized + fmt::Debug> fmt::Debug for FrozenMlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FrozenMlsp").field(&self.inner).finish()
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for FrozenMlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for FrozenMlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.inner, f)
+    }
+}
+
+/// Compares the contents, as `Borrow` requires for map lookups by `&T`.
+impl<T: ?Sized + PartialEq> PartialEq for FrozenMlsp<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for FrozenMlsp<T> {}
+
+/// Hashes the contents, the same way as `T` so that maps can be searched by `&T`.
+impl<T: ?Sized + Hash> Hash for FrozenMlsp<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
+impl<T: ?Sized> Clone for FrozenMlsp<T> {
+    fn clone(&self) -> Self {
+        FrozenMlsp {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A reference to frozen contents that can be sent across threads.
+pub struct FrozenMlspPackage<T: ?Sized> {
+    package: MlspPackage<T>,
+}
+
+impl<T: ?Sized> FrozenMlspPackage<T> {
+    /// Turns this package into a frozen handle that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> FrozenMlsp<T> {
+        FrozenMlsp {
+            inner: self.package.unpackage(),
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for FrozenMlspPackage<T> {
+    fn clone(&self) -> Self {
+        FrozenMlspPackage {
+            package: self.package.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn frozen_handles_share() {
+        let a = Mlsp::new(vec![1u32, 2, 3]);
+        let weak = a.downgrade();
+        let frozen = a.freeze();
+        assert_eq!(3, frozen.len());
+        assert_eq!([1, 2, 3], frozen.as_ref()[..]);
+
+        // Clones share the local counter, packages add to the atomic count
+        let copy = frozen.clone();
+        assert!(std::ptr::eq(copy.as_ptr(), frozen.as_ptr()));
+        assert_eq!(1, weak.strong_count());
+        let package = frozen.package();
+        assert_eq!(2, weak.strong_count());
+        let sum = thread::spawn(move || package.unpackage().iter().sum::<u32>());
+        assert_eq!(6, sum.join().unwrap());
+
+        drop(copy);
+        drop(frozen);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn frozen_map_keys() {
+        use std::collections::HashMap;
+
+        let key = Mlsp::new(String::from("threads")).freeze();
+        let mut settings = HashMap::new();
+        settings.insert(key.clone(), 4);
+        settings.insert(Mlsp::new(String::from("retries")).freeze(), 2);
+
+        // Equal contents are the same key, whichever allocation holds them
+        assert_eq!(
+            Some(&4),
+            settings.get(&Mlsp::new(String::from("threads")).freeze())
+        );
+        assert_eq!(Some(&2), settings.get(&String::from("retries")));
+        assert!(key == Mlsp::new(String::from("threads")).freeze());
+
+        // The key can't be changed in place while the map holds it, so it is still found
+        let package = key.package();
+        assert_eq!(Some(2), settings.remove(&String::from("retries")));
+        let thread_key = thread::spawn(move || package.unpackage().len());
+        assert_eq!(7, thread_key.join().unwrap());
+        assert_eq!(Some(&4), settings.get(key.as_ref()));
+    }
+}
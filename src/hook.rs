@@ -0,0 +1,157 @@
+//! Running code when an allocation is first shared across threads, enabled by the `hooks` feature.
+//!
+//! Every inner carries a flag recording whether it has ever been packaged and a slot
+//! for a hook registered with `Mlsp::on_first_package`. Creating a package checks the flag
+//! with a relaxed load, so only the first package of an allocation takes the hook's lock.
+
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use crate::atomic::AtomicBool;
+use crate::Mlsp;
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// Whether an allocation has been packaged, and the hook to run when it first is.
+pub(crate) struct FirstPackage {
+    packaged: AtomicBool,
+    hook: Mutex<Option<Hook>>,
+}
+
+impl FirstPackage {
+    pub(crate) fn new() -> Self {
+        FirstPackage {
+            packaged: AtomicBool::new(false),
+            hook: Mutex::new(None),
+        }
+    }
+
+    /// Marks the allocation as packaged, running the hook if this is the first package.
+    ///
+    /// The flag is set under the lock, so a hook registered concurrently
+    /// is either run here or refused by `on_first_package`.
+    pub(crate) fn packaged(&self) {
+        if self.packaged.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut slot = self
+            .hook
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.packaged.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let hook = slot.take();
+        // Run outside the lock, since the hook may package the allocation itself
+        drop(slot);
+
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Registers `hook` to run once, when the first package of this allocation is created.
+    ///
+    /// The hook runs inside the call that creates that package, on whichever thread makes it,
+    /// so it suits lazy setup that is only needed once the value is shared across threads,
+    /// such as registering it with a cross-thread tracker.
+    /// Returns the hook back if the allocation has already been packaged
+    /// or already has a hook registered.
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let shared = Arc::new(AtomicBool::new(false));
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let seen = shared.clone();
+    /// assert!(a.on_first_package(move || seen.store(true, Ordering::Relaxed)).is_ok());
+    ///
+    /// assert!(!shared.load(Ordering::Relaxed));
+    /// let package = a.package();
+    /// assert!(shared.load(Ordering::Relaxed));
+    /// assert!(a.on_first_package(|| ()).is_err());
+    /// # drop(package);
+    /// ```
+    pub fn on_first_package<F: FnOnce() + Send + 'static>(&self, hook: F) -> Result<(), F> {
+        // SAFETY: The existence of this Mlsp keeps the inner alive
+        let first = unsafe { &self.inner_ptr.as_ref().first_package };
+        let mut slot = first
+            .hook
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if first.packaged.load(Ordering::Relaxed) || slot.is_some() {
+            return Err(hook);
+        }
+        *slot = Some(Box::new(hook));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn runs_once_across_threads() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let a = Mlsp::new(String::from("shared"));
+        let counted = runs.clone();
+        a.on_first_package(move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+        })
+        .ok()
+        .unwrap();
+
+        // Local clones don't count as sharing
+        let b = a.clone();
+        assert_eq!(0, runs.load(Ordering::Relaxed));
+
+        // Several threads race to make the first package through weak references
+        let barrier = Arc::new(Barrier::new(4));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let weak = a.downgrade();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    let packages = weak.upgrade_to_package().unwrap().clone_n(3);
+                    assert_eq!(
+                        "shared",
+                        AsRef::<str>::as_ref(&packages[0].clone().unpackage())
+                    );
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        drop(b.package_n(2));
+        drop(a.package());
+        assert_eq!(1, runs.load(Ordering::Relaxed));
+
+        // Once packaged, a new hook is refused
+        assert!(a.on_first_package(|| unreachable!()).is_err());
+    }
+
+    #[test]
+    fn one_hook_per_allocation() {
+        let a = Mlsp::new(1u8);
+        assert!(a.on_first_package(|| ()).is_ok());
+        assert!(a.on_first_package(|| ()).is_err());
+
+        // An allocation that is never packaged drops its hook without running it
+        let dropped = Arc::new(());
+        let b = Mlsp::new(2u8);
+        let held = dropped.clone();
+        assert!(b.on_first_package(move || drop(held)).is_ok());
+        assert_eq!(2, Arc::strong_count(&dropped));
+        drop(b);
+        assert_eq!(1, Arc::strong_count(&dropped));
+    }
+}
@@ -0,0 +1,192 @@
+use std::borrow::Borrow;
+use std::mem;
+
+use crate::{Mlsp, MlspPackage};
+
+/// A handle to a small `Copy` value that keeps a copy of the value next to its counters,
+/// enabled by the `inline` feature.
+///
+/// Reading the value through an `Mlsp` follows its pointer to the allocation,
+/// which for a small shared scalar read from many places can cost a cache miss on every read.
+/// An `InlineMlsp` reads its own copy instead, and only reaches the allocation
+/// to count its references, which are shared with every `Mlsp` of the same allocation.
+/// The contents of a `Copy` value can't change while they are shared, so the copies agree.
+///
+/// Values may be at most `MAX_SIZE` bytes, as larger ones would make every handle larger
+/// than the load they save.
+/// ```
+/// let a = mlsp::InlineMlsp::new(7u32);
+/// let package = a.package();
+/// let b = std::thread::spawn(move || package.unpackage().get())
+///     .join()
+///     .unwrap();
+/// assert_eq!(a.get(), b);
+/// ```
+/// ```compile_fail
+/// let a = mlsp::InlineMlsp::new([0u64; 4]);
+/// ```
+pub struct InlineMlsp<T: Copy> {
+    value: T,
+    handle: Mlsp<T>,
+}
+
+impl<T: Copy> InlineMlsp<T> {
+    /// The largest value, in bytes, that can be stored inline
+    pub const MAX_SIZE: usize = mem::size_of::<usize>();
+
+    /// Creates a new shared value with a copy kept inline.
+    pub fn new(value: T) -> Self {
+        const {
+            assert!(
+                mem::size_of::<T>() <= Self::MAX_SIZE,
+                "value too large to inline"
+            )
+        };
+        InlineMlsp {
+            value,
+            handle: Mlsp::new(value),
+        }
+    }
+
+    /// Returns a copy of the value, without reading the allocation.
+    pub fn get(&self) -> T {
+        self.value
+    }
+
+    /// Create a Send-able package from the handle, which carries its own copy of the value
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> InlineMlspPackage<T> {
+        InlineMlspPackage {
+            value: self.value,
+            package: self.handle.package(),
+        }
+    }
+
+    /// The handle whose references this one shares.
+    pub fn as_mlsp(&self) -> &Mlsp<T> {
+        &self.handle
+    }
+
+    /// Returns the handle whose references this one shares, dropping the inline copy.
+    pub fn into_mlsp(self) -> Mlsp<T> {
+        self.handle
+    }
+}
+
+/// Copies the value out of the allocation once, after which reads use the copy.
+impl<T: Copy> From<Mlsp<T>> for InlineMlsp<T> {
+    fn from(handle: Mlsp<T>) -> Self {
+        const {
+            assert!(
+                mem::size_of::<T>() <= Self::MAX_SIZE,
+                "value too large to inline"
+            )
+        };
+        InlineMlsp {
+            value: *handle.as_ref(),
+            handle,
+        }
+    }
+}
+
+impl<T: Copy> AsRef<T> for InlineMlsp<T> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Copy> Borrow<T> for InlineMlsp<T> {
+    fn borrow(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Copy> Clone for InlineMlsp<T> {
+    fn clone(&self) -> Self {
+        InlineMlsp {
+            value: self.value,
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+/// A reference to a small value, carrying a copy of it, that can be sent across threads.
+pub struct InlineMlspPackage<T: Copy> {
+    value: T,
+    package: MlspPackage<T>,
+}
+
+impl<T: Copy> InlineMlspPackage<T> {
+    /// Turns this package into a handle that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> InlineMlsp<T> {
+        InlineMlsp {
+            value: self.value,
+            handle: self.package.unpackage(),
+        }
+    }
+
+    /// Returns a copy of the value, without reading the allocation.
+    pub fn get(&self) -> T {
+        self.value
+    }
+}
+
+impl<T: Copy> Clone for InlineMlspPackage<T> {
+    fn clone(&self) -> Self {
+        InlineMlspPackage {
+            value: self.value,
+            package: self.package.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::num::Wrapping;
+    use std::thread;
+
+    #[test]
+    fn copies_follow_the_handle() {
+        let a = InlineMlsp::new(Wrapping(5u32));
+        let weak = a.as_mlsp().downgrade();
+        let b = a.clone();
+        assert_eq!(Wrapping(5), b.get());
+        assert_eq!(2, a.as_mlsp().local_handles());
+
+        // Packages carry the copy across, and count like those of the handle
+        let packages: Vec<_> = (0..3).map(|_| b.package()).collect();
+        assert_eq!(4, weak.strong_count());
+        let sums: Vec<_> = packages
+            .into_iter()
+            .map(|package| {
+                let copy = package.clone();
+                thread::spawn(move || {
+                    let c = package.unpackage();
+                    assert_eq!(copy.get(), c.get());
+                    (c.get() + *c.as_ref()).0
+                })
+            })
+            .collect();
+        for sum in sums {
+            assert_eq!(10, sum.join().unwrap());
+        }
+        assert_eq!(1, weak.strong_count());
+
+        drop(a);
+        assert_eq!(Wrapping(5), b.into_mlsp().try_unwrap().ok().unwrap());
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn from_an_existing_handle() {
+        let a = Mlsp::new(-1i16);
+        let inline = InlineMlsp::from(a.clone());
+        assert_eq!(-1, inline.get());
+        assert!(std::ptr::eq(a.as_ptr(), inline.as_mlsp().as_ptr()));
+        assert_eq!(2, a.local_handles());
+    }
+}
@@ -1,19 +1,161 @@
 use core::cell::Cell;
+use core::mem::{self, ManuallyDrop};
 use core::ptr;
+#[cfg(not(loom))]
 use core::sync::atomic;
+#[cfg(loom)]
+use loom::sync::atomic;
 
+use std::alloc::{self, Layout, LayoutError};
 use std::borrow::Borrow;
 use std::boxed::Box;
+use std::cmp;
+use std::collections::{hash_map, HashMap};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::ops::Index;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::slice::SliceIndex;
 
 use std::ptr::NonNull;
+#[cfg(debug_assertions)]
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+#[cfg(feature = "arena")]
+pub mod arena;
+mod atomic_mlsp;
+mod cache;
+#[cfg(debug_assertions)]
+mod canary;
+mod cell;
+mod channel;
+#[cfg(debug_assertions)]
+mod checks;
+#[cfg(feature = "debug")]
+pub mod debug;
+mod drop_list;
+mod envelope;
+#[cfg(feature = "epoch")]
+mod epoch;
+mod frozen;
+#[cfg(feature = "hooks")]
+mod hook;
+#[cfg(feature = "inline")]
+mod inline;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod mutex;
+mod observer;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "bytemuck")]
+mod pod;
+#[cfg(feature = "pool")]
+pub mod pool;
+mod reservation;
+#[cfg(feature = "serde")]
+mod serialize;
+mod share;
+mod slice;
+mod stack;
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "testing")]
+mod testing;
+#[cfg(feature = "thin")]
+mod thin;
+#[cfg(feature = "tracing")]
+mod trace;
+mod typed_arena;
+#[cfg(feature = "unsize")]
+mod unsize;
+#[cfg(feature = "wait")]
+mod wait;
+mod weak;
+
+pub use atomic_mlsp::{AtomicMlsp, MlspReadGuard};
+pub use cache::MlspCache;
+#[cfg(debug_assertions)]
+pub use canary::{leaked_local_counters, set_leak_hook};
+pub use cell::{MlspCell, MlspCellPackage};
+pub use channel::{
+    bounded_channel, channel, BoundedSendError, MlspBoundedSender, MlspReceiver, MlspSender,
+};
+pub use drop_list::MlspDropList;
+pub use envelope::MlspEnvelope;
+pub use frozen::{FrozenMlsp, FrozenMlspPackage};
+#[cfg(feature = "inline")]
+pub use inline::{InlineMlsp, InlineMlspPackage};
+pub use mutex::{MlspMutex, MlspMutexPackage};
+pub use observer::MlspObserver;
+pub use reservation::PackageReservation;
+#[cfg(feature = "serde")]
+pub use serialize::MlspWeakSeed;
+pub use share::{Destination, Handle};
+pub use slice::{MlspSlice, MlspSlicePackage};
+pub use stack::MlspStack;
+#[cfg(feature = "futures")]
+pub use stream::MlspStream;
+#[cfg(feature = "thin")]
+pub use thin::ThinMlsp;
+pub use typed_arena::{MlspArena, MlspArenaHandle, MlspArenaPackage};
+#[cfg(feature = "unsize")]
+pub use unsize::MlspDyn;
+pub use weak::MlspWeak;
+
+/// The highest value the atomic and weak counts may reach,
+/// the process is aborted rather than exceed it.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// The atomic count at which an allocation becomes hot, see `Mlsp::is_hot`.
+const HOT_THRESHOLD: usize = 16;
+
+/// Stands in for the local counter of handles to a hot allocation,
+/// which are counted in the atomic count directly.
+/// It is never the address of a real counter, since no allocation is placed at it.
+const ATOMIC_ONLY: NonNull<LocalCounter> = NonNull::dangling();
 
 /// The inner Arc-like portion of the Mlsp
 /// It is a wrapper tha bundles an atomic usize reference counter
 /// with an arbitrary value
-struct MlspInner<T> {
+///
+/// The `weak_count` counts the `MlspWeak`s plus one shared by all strong references,
+/// the data is dropped when `atomic_count` reaches zero
+/// and the allocation is freed when `weak_count` reaches zero.
+///
+/// The layout is `repr(C)`, so it is guaranteed to be the two counters, the `hot` flag,
+/// the arena flag with the `arena` feature, the package epoch with the `epoch` feature,
+/// the first-package hook with the `hooks` feature, the peak count with the `metrics` feature
+/// and the waiters with the `wait` feature,
+/// followed by `data` at the first offset after them that is aligned for `T`.
+/// This is relied on to compute the position of `data` when allocating inners
+/// for unsized values like slices, and to find the inner from a data pointer in `from_raw`.
+#[repr(C)]
+struct MlspInner<T: ?Sized> {
     atomic_count: atomic::AtomicUsize,
-    data: T,
+    weak_count: atomic::AtomicUsize,
+    /// Set once the allocation is shared widely enough that new handles skip local counting
+    hot: atomic::AtomicBool,
+    /// Set if the allocation belongs to an arena, which reclaims it instead of `decrement_weak`
+    #[cfg(feature = "arena")]
+    in_arena: bool,
+    /// The number of packages made from handles, see `Mlsp::package_epoch`
+    #[cfg(feature = "epoch")]
+    package_epoch: atomic::AtomicUsize,
+    /// Whether the allocation has been packaged, and the hook to run when it first is
+    #[cfg(feature = "hooks")]
+    first_package: hook::FirstPackage,
+    /// The highest value `atomic_count` has reached, see `Mlsp::peak_count`
+    #[cfg(feature = "metrics")]
+    peak_count: atomic::AtomicUsize,
+    #[cfg(feature = "wait")]
+    waiters: wait::Waiters,
+    data: ManuallyDrop<T>,
 }
 
 impl<T> MlspInner<T> {
@@ -21,32 +163,356 @@ impl<T> MlspInner<T> {
     fn new(data: T) -> Self {
         MlspInner {
             atomic_count: atomic::AtomicUsize::new(1),
-            data,
+            weak_count: atomic::AtomicUsize::new(1),
+            hot: atomic::AtomicBool::new(false),
+            #[cfg(feature = "arena")]
+            in_arena: false,
+            #[cfg(feature = "epoch")]
+            package_epoch: epoch::new(),
+            #[cfg(feature = "hooks")]
+            first_package: hook::FirstPackage::new(),
+            #[cfg(feature = "metrics")]
+            peak_count: atomic::AtomicUsize::new(1),
+            #[cfg(feature = "wait")]
+            waiters: wait::Waiters::new(),
+            data: ManuallyDrop::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> MlspInner<T> {
+    /// The layout of the fields before `data`
+    fn header_layout() -> Layout {
+        let counter = Layout::new::<atomic::AtomicUsize>();
+        let (header, _) = counter.extend(counter).unwrap();
+        let (header, _) = header.extend(Layout::new::<atomic::AtomicBool>()).unwrap();
+        #[cfg(feature = "arena")]
+        let (header, _) = header.extend(Layout::new::<bool>()).unwrap();
+        #[cfg(feature = "epoch")]
+        let (header, _) = header.extend(counter).unwrap();
+        #[cfg(feature = "hooks")]
+        let (header, _) = header.extend(Layout::new::<hook::FirstPackage>()).unwrap();
+        #[cfg(feature = "metrics")]
+        let (header, _) = header.extend(counter).unwrap();
+        #[cfg(feature = "wait")]
+        let (header, _) = header.extend(Layout::new::<wait::Waiters>()).unwrap();
+        header
+    }
+
+    /// The offset of `data` for a `T` with the given alignment
+    fn data_offset(align: usize) -> usize {
+        let (_, offset) = Self::header_layout()
+            .extend(Layout::from_size_align(0, align).unwrap())
+            .unwrap();
+        offset
+    }
+}
+
+impl<T> MlspInner<[T]> {
+    /// The layout of an MlspInner for a slice of `len` elements
+    fn slice_layout(len: usize) -> Result<Layout, LayoutError> {
+        let (layout, _) = Self::header_layout().extend(Layout::array::<T>(len)?)?;
+        Ok(layout.pad_to_align())
+    }
+
+    /// Allocates an MlspInner for a slice of `len` elements
+    /// with an atomic counter with value 1.
+    ///
+    /// Aborts if the allocation fails.
+    ///
+    /// # Safety
+    /// The elements of `data` are left uninitialized,
+    /// the caller must write all `len` of them before the inner is used.
+    unsafe fn allocate_for_slice(len: usize) -> NonNull<MlspInner<[T]>> {
+        let layout = Self::slice_layout(len).expect("capacity overflow");
+        Self::try_allocate_for_slice(len).unwrap_or_else(|_| alloc::handle_alloc_error(layout))
+    }
+
+    /// Allocates an MlspInner for a slice of `len` elements
+    /// with an atomic counter with value 1.
+    ///
+    /// # Safety
+    /// The elements of `data` are left uninitialized,
+    /// the caller must write all `len` of them before the inner is used.
+    unsafe fn try_allocate_for_slice(len: usize) -> Result<NonNull<MlspInner<[T]>>, AllocError> {
+        let layout = Self::slice_layout(len).map_err(|_| AllocError)?;
+
+        let mem = alloc::alloc(layout);
+        if mem.is_null() {
+            return Err(AllocError);
         }
+        Ok(Self::init_for_slice(mem, len))
+    }
+
+    /// Writes the header of an MlspInner for a slice of `len` elements
+    /// with an atomic counter with value 1 to `mem`.
+    ///
+    /// # Safety
+    /// `mem` must be valid for writes of `slice_layout(len)`,
+    /// and the elements of `data` are left uninitialized as with `allocate_for_slice`.
+    unsafe fn init_for_slice(mem: *mut u8, len: usize) -> NonNull<MlspInner<[T]>> {
+        // Give the allocation the slice metadata so that it describes an MlspInner<[T]>
+        let inner = ptr::slice_from_raw_parts_mut(mem as *mut T, len) as *mut MlspInner<[T]>;
+        ptr::write(
+            ptr::addr_of_mut!((*inner).atomic_count),
+            atomic::AtomicUsize::new(1),
+        );
+        ptr::write(
+            ptr::addr_of_mut!((*inner).weak_count),
+            atomic::AtomicUsize::new(1),
+        );
+        ptr::write(
+            ptr::addr_of_mut!((*inner).hot),
+            atomic::AtomicBool::new(false),
+        );
+        #[cfg(feature = "metrics")]
+        ptr::write(
+            ptr::addr_of_mut!((*inner).peak_count),
+            atomic::AtomicUsize::new(1),
+        );
+        #[cfg(feature = "arena")]
+        ptr::write(ptr::addr_of_mut!((*inner).in_arena), false);
+        #[cfg(feature = "epoch")]
+        ptr::write(ptr::addr_of_mut!((*inner).package_epoch), epoch::new());
+        #[cfg(feature = "hooks")]
+        ptr::write(
+            ptr::addr_of_mut!((*inner).first_package),
+            hook::FirstPackage::new(),
+        );
+        #[cfg(feature = "wait")]
+        ptr::write(ptr::addr_of_mut!((*inner).waiters), wait::Waiters::new());
+
+        NonNull::new_unchecked(inner)
+    }
+
+    /// Frees an allocation made by `allocate_for_slice` without dropping any elements.
+    ///
+    /// # Safety
+    /// `inner` must have been allocated for `len` elements and must not be used afterwards.
+    unsafe fn deallocate_slice(inner: NonNull<MlspInner<[T]>>, len: usize) {
+        alloc::dealloc(inner.as_ptr() as *mut u8, Self::slice_layout(len).unwrap());
     }
+}
 
+impl<T: ?Sized> MlspInner<T> {
     /// Increment the atomic counter for a given MlspInner pointer
     ///
     /// # Safety
     /// A caller to increment is obligated to later call decrement exactly once,
     /// in order to ensure that the memory it contains is not leaked.
     unsafe fn increment(&self) {
-        self.atomic_count.fetch_add(1, Ordering::Release);
+        self.increment_by(1);
+    }
+
+    /// Increment the atomic counter by `n` with a single atomic operation
+    ///
+    /// # Safety
+    /// A caller to increment_by is obligated to later call decrement exactly `n` times,
+    /// in order to ensure that the memory it contains is not leaked.
+    unsafe fn increment_by(&self, n: usize) {
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_op();
+
+        let old = self.atomic_count.fetch_add(n, Ordering::Release);
+
+        // Continuing past the limit could wrap the counter and free the data while it is in use
+        if old.checked_add(n).is_none_or(|new| new > MAX_REFCOUNT) {
+            process::abort();
+        }
+        self.note_count(old + n);
+
+        #[cfg(feature = "tracing")]
+        trace::increment(self, old + n);
+    }
+
+    /// Marks the allocation hot once the atomic count reaches `HOT_THRESHOLD`,
+    /// and records the peak count with the `metrics` feature
+    fn note_count(&self, count: usize) {
+        // Not recorded as an atomic operation, since it only happens to collect metrics
+        #[cfg(feature = "metrics")]
+        self.peak_count.fetch_max(count, Ordering::Relaxed);
+
+        // Either kind of handle is correct in either mode, so the flag needs no ordering
+        if count >= HOT_THRESHOLD && !self.hot.load(Ordering::Relaxed) {
+            self.hot.store(true, Ordering::Relaxed);
+        }
     }
 
     /// Decrement the atomic counter for a given MlspInner pointer
     ///
+    /// Takes a pointer rather than a reference,
+    /// since the last caller drops the data and possibly frees the allocation.
+    ///
     /// # Safety
     /// For each call to decrement there must have been exactly one
     /// prior call to increment to prevent premature freeing.
-    unsafe fn decrement(&mut self) {
-        let old = self.atomic_count.fetch_sub(1, Ordering::Release);
-        atomic::fence(Ordering::Acquire);
+    unsafe fn decrement(this: NonNull<Self>) {
+        Self::decrement_by(this, 1);
+    }
+
+    /// Decrement the atomic counter by `n` with a single atomic operation
+    ///
+    /// # Safety
+    /// The counter must have been incremented `n` times for this caller,
+    /// as if `decrement` was called `n` times.
+    unsafe fn decrement_by(this: NonNull<Self>, n: usize) {
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_op();
+
+        #[cfg(feature = "debug")]
+        let check = debug::lock_decrement(this);
+
+        #[cfg(not(feature = "wait"))]
+        let old = this.as_ref().atomic_count.fetch_sub(n, Ordering::Release);
+        #[cfg(feature = "wait")]
+        let old = this
+            .as_ref()
+            .waiters
+            .decrement(&this.as_ref().atomic_count, n);
 
-        // If the value before decrementing was one,
+        #[cfg(feature = "debug")]
+        check.check(old, n);
+
+        #[cfg(feature = "tracing")]
+        trace::decrement(this.as_ptr(), old - n);
+
+        // If the value before decrementing was `n`,
         // this caller is the last reference holder and the inner data must be dropped.
-        if old == 1 {
-            ptr::drop_in_place(self);
+        if old != n {
+            return;
+        }
+
+        // Every other holder released the count with a `Release` decrement before this one,
+        // and those decrements form a release sequence ending in the store of zero above.
+        // An acquire load of the count reads that zero, since nothing can change it afterwards,
+        // so it synchronizes with all of them and their uses of the contents happen before the drop.
+        // Only the last holder pays for the acquire, and only on this allocation's count.
+        this.as_ref().atomic_count.load(Ordering::Acquire);
+
+        ManuallyDrop::drop(&mut (*this.as_ptr()).data);
+
+        // Release the weak reference held collectively by the strong references
+        Self::decrement_weak(this);
+    }
+
+    /// Increment the weak counter for a given MlspInner pointer
+    ///
+    /// # Safety
+    /// A caller to increment_weak is obligated to later call decrement_weak exactly once,
+    /// in order to ensure that the allocation is not leaked.
+    unsafe fn increment_weak(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_op();
+
+        if self.weak_count.fetch_add(1, Ordering::Relaxed) >= MAX_REFCOUNT {
+            process::abort();
+        }
+    }
+
+    /// Decrement the weak counter for a given MlspInner pointer,
+    /// freeing the allocation if this was the last weak reference.
+    ///
+    /// # Safety
+    /// For each call to decrement_weak there must have been exactly one
+    /// prior call to increment_weak, or the inner must have just been created.
+    unsafe fn decrement_weak(this: NonNull<Self>) {
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_op();
+
+        if this.as_ref().weak_count.fetch_sub(1, Ordering::Release) == 1 {
+            atomic::fence(Ordering::Acquire);
+
+            // The memory of an arena allocation is reclaimed along with the arena
+            #[cfg(feature = "arena")]
+            if this.as_ref().in_arena {
+                ptr::drop_in_place(this.as_ptr());
+                return;
+            }
+
+            // The data is already dropped and `ManuallyDrop` keeps the box from dropping it again
+            #[cfg(not(feature = "pool"))]
+            drop(Box::from_raw(this.as_ptr()));
+
+            // The same as dropping the box, unless this thread keeps the slot for reuse
+            #[cfg(feature = "pool")]
+            {
+                let layout = Layout::for_value(this.as_ref());
+                ptr::drop_in_place(this.as_ptr());
+                if !pool::give(this.cast(), layout) {
+                    alloc::dealloc(this.as_ptr() as *mut u8, layout);
+                }
+            }
+        }
+    }
+
+    /// Increment the atomic counter only if it has not already reached zero
+    ///
+    /// # Safety
+    /// The allocation must still be alive, as guaranteed by holding a weak reference.
+    unsafe fn try_increment(&self) -> bool {
+        let mut count = self.atomic_count.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return false;
+            }
+            if count >= MAX_REFCOUNT {
+                process::abort();
+            }
+
+            #[cfg(feature = "metrics")]
+            metrics::record_atomic_op();
+
+            match self.atomic_count.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.note_count(count + 1);
+
+                    #[cfg(feature = "tracing")]
+                    trace::increment(self, count + 1);
+
+                    return true;
+                }
+                Err(current) => count = current,
+            }
+        }
+    }
+
+    /// Increment the atomic counter by `n` only if that would not take it above `max`
+    ///
+    /// # Safety
+    /// A caller to increment_by_bounded is obligated to later call decrement exactly `n` times
+    /// if it returns true, in order to ensure that the memory it contains is not leaked.
+    unsafe fn increment_by_bounded(&self, n: usize, max: usize) -> bool {
+        let mut count = self.atomic_count.load(Ordering::Relaxed);
+        loop {
+            match count.checked_add(n) {
+                Some(new) if new <= max && new <= MAX_REFCOUNT => {}
+                _ => return false,
+            }
+
+            #[cfg(feature = "metrics")]
+            metrics::record_atomic_op();
+
+            match self.atomic_count.compare_exchange_weak(
+                count,
+                count + n,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.note_count(count + n);
+
+                    #[cfg(feature = "tracing")]
+                    trace::increment(self, count + n);
+
+                    return true;
+                }
+                Err(current) => count = current,
+            }
         }
     }
 }
@@ -74,8 +540,11 @@ impl<T> MlspInner<T> {
 ///     let a2 = a_pkg.unpackage();
 /// });
 /// ```
-pub struct Mlsp<T> {
-    local_count: NonNull<Cell<usize>>,
+///
+/// Like `Rc`, dropping the last handle to a long chain of values that each own the next
+/// recurses once per value and can overflow the stack, see `MlspDropList` for the way out.
+pub struct Mlsp<T: ?Sized> {
+    local_count: NonNull<LocalCounter>,
     inner_ptr: NonNull<MlspInner<T>>,
 }
 
@@ -88,183 +557,3015 @@ impl<T> Mlsp<T> {
         let atomic_counter = Box::into_raw(atomic_counter);
         let atomic_counter = NonNull::new(atomic_counter).unwrap();
 
-        Mlsp {
-            local_count: new_local_counter(),
-            inner_ptr: atomic_counter,
-        }
+        Mlsp::from_inner(atomic_counter, false)
     }
 
-    /// Create a Send-able package from the Mlsp
+    /// Returns the contents if this is the only `Mlsp` or `MlspPackage` referencing them,
+    /// otherwise returns this handle unchanged.
     ///
-    /// This increments the atomic_count
-    pub fn package(&self) -> MlspPackage<T> {
-        // Adding a new referent to the inner requires incrementing its counter
+    /// Outstanding `MlspWeak`s do not prevent this, they will fail to upgrade afterwards.
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        // SAFETY: The existence of this Mlsp keeps the local counter and the inner alive
         unsafe {
-            self.inner_ptr.as_ref().increment();
-        }
+            if self.local_handles() != 1 {
+                return Err(self);
+            }
 
-        MlspPackage {
-            inner_ptr: self.inner_ptr,
+            #[cfg(feature = "metrics")]
+            metrics::record_atomic_op();
+
+            // Claim the contents by taking the atomic count from one to zero,
+            // after which weak references can no longer upgrade
+            let inner = self.inner_ptr.as_ref();
+            if inner
+                .atomic_count
+                .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                return Err(self);
+            }
+
+            #[cfg(feature = "tracing")]
+            trace::decrement(inner, 0);
+
+            let this = ManuallyDrop::new(self);
+
+            #[cfg(feature = "debug")]
+            {
+                debug::handle_dropped(this.inner_ptr);
+                if !this.is_atomic_only() {
+                    debug::counter_released(this.inner_ptr);
+                }
+            }
+
+            free_local_counter(this.local_count);
+            let data = ManuallyDrop::take(&mut (*this.inner_ptr.as_ptr()).data);
+
+            // Release the weak reference held collectively by the strong references
+            MlspInner::decrement_weak(this.inner_ptr);
+
+            Ok(data)
         }
     }
-}
 
-impl<T> Borrow<T> for Mlsp<T> {
-    fn borrow(&self) -> &T {
-        unsafe { &self.inner_ptr.as_ref().data }
+    /// Moves the contents into a `Box` if this is the only `Mlsp` or `MlspPackage` referencing them,
+    /// otherwise returns this handle unchanged.
+    ///
+    /// This succeeds exactly when `try_unwrap` does.
+    pub fn try_into_box(self) -> Result<Box<T>, Self> {
+        self.try_unwrap().map(Box::new)
     }
 }
 
-impl<T> AsRef<T> for Mlsp<T> {
-    fn as_ref(&self) -> &T {
-        unsafe { &self.inner_ptr.as_ref().data }
+impl<T: Copy> Mlsp<T> {
+    /// Returns a copy of the contents.
+    ///
+    /// For small `Copy` values a copy is cheaper than another handle,
+    /// since a clone has to update the local counter and
+    /// a package has to update the atomic counter.
+    /// Code that only needs the value rather than shared ownership of it
+    /// should copy it out instead of cloning or packaging the Mlsp.
+    /// ```
+    /// let a = mlsp::Mlsp::new(7u64);
+    /// let values = vec![a.copied(); 3];
+    /// assert_eq!(vec![7, 7, 7], values);
+    /// ```
+    pub fn copied(&self) -> T {
+        *self.as_ref()
     }
 }
 
-impl<T> Clone for Mlsp<T> {
-    fn clone(&self) -> Self {
-        // SAFETY: Requires that local_count has not been freed.
-        // This is guaranteed by the existence of the current Mlsp.
-        let local_count = unsafe { self.local_count.as_ref() };
+impl<T: ?Sized> Mlsp<T> {
+    /// The highest value the atomic count of an allocation may reach.
+    ///
+    /// The atomic count grows by one for each package and for each thread holding `Mlsp`s.
+    /// Any operation that would take it past this limit aborts the process,
+    /// since a wrapped counter would free the contents while they are still in use.
+    /// It can only be reached by leaking references, e.g. with `mem::forget`.
+    pub const MAX_REFCOUNT: usize = MAX_REFCOUNT;
+
+    /// The atomic count at which an allocation becomes hot, see `is_hot`.
+    pub const HOT_THRESHOLD: usize = HOT_THRESHOLD;
+
+    /// Creates the first Mlsp for this thread from an inner
+    /// whose atomic count already accounts for the new Mlsp.
+    ///
+    /// The Mlsp gets its own local counter unless the inner is hot.
+    fn from_inner(inner_ptr: NonNull<MlspInner<T>>, unpackaged: bool) -> Self {
+        #[cfg(feature = "debug")]
+        debug::handle_created(inner_ptr);
+
+        // SAFETY: The caller's share of the atomic count keeps the inner alive
+        let hot = unsafe { inner_ptr.as_ref().hot.load(Ordering::Relaxed) };
+        let local_count = if hot {
+            ATOMIC_ONLY
+        } else {
+            #[cfg(feature = "debug")]
+            debug::counter_created(inner_ptr);
 
-        // Increment the local counter
-        let count = local_count.get();
-        let count = count + 1;
-        local_count.set(count);
+            new_local_counter(unpackaged)
+        };
 
         Mlsp {
-            local_count: self.local_count,
-            inner_ptr: self.inner_ptr,
+            local_count,
+            inner_ptr,
         }
     }
-}
 
-impl<T> Drop for Mlsp<T> {
-    fn drop(&mut self) {
-        // SAFETY: Requires that two `Mlsp`s for the same inner data must never exist in different threads
-        unsafe {
-            let local_count = self.local_count.as_mut();
-            // Decrement the local_count
-            let count = local_count.get();
-            let count = count - 1;
-            local_count.set(count);
+    /// Whether this handle is counted in the atomic count directly instead of a local counter
+    fn is_atomic_only(&self) -> bool {
+        self.local_count == ATOMIC_ONLY
+    }
 
-            // If the new value is greater than zero, there are still local references
-            // and no further operations are needed.
-            if count > 0 {
-                return;
-            }
+    /// The number of handles sharing this handle's share of the atomic count
+    fn local_handles(&self) -> usize {
+        if self.is_atomic_only() {
+            1
+        } else {
+            // SAFETY: The existence of this Mlsp keeps the local counter alive
+            unsafe { self.local_count.as_ref().count.get() }
         }
-        // If the local_count was reduced to zero,
-        // then this thread no longer has any references
+    }
 
-        // SAFETY: Requires that no other `Mlsp`s exist that reference the same local_count
-        unsafe {
-            // Drop the local counter being used by this thread
-            ptr::drop_in_place(self.local_count.as_mut());
-            // Decrement the global pointer on the MlspInner and drop the inner data if necessary
-            self.inner_ptr.as_mut().decrement();
-        }
+    /// Returns true once the atomic count of the allocation has reached `HOT_THRESHOLD`,
+    /// meaning it is shared by enough threads and packages that local counting
+    /// no longer pays for itself.
+    ///
+    /// Handles created from a hot allocation by `unpackage` or `upgrade` skip the local counter
+    /// and increment the atomic count for each clone, like an `Arc`.
+    /// Handles that already have a local counter keep using it.
+    /// An allocation stays hot for the rest of its life.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// assert!(!a.is_hot());
+    ///
+    /// let packages = a.package_n(mlsp::Mlsp::<u8>::HOT_THRESHOLD);
+    /// assert!(a.is_hot());
+    /// ```
+    pub fn is_hot(&self) -> bool {
+        unsafe { self.inner_ptr.as_ref().hot.load(Ordering::Relaxed) }
     }
-}
 
-/// A reference to the contents of an Mlsp
-/// that does not yet have a local counter and can be sent across threads.
-pub struct MlspPackage<T> {
-    inner_ptr: NonNull<MlspInner<T>>,
-}
+    /// Returns true if cloning this handle only touches its local counter.
+    ///
+    /// This holds for every handle with a local counter, including those of a hot allocation
+    /// made before it became hot. Handles made from a hot allocation afterwards return false,
+    /// their clones increment the atomic count, see `is_hot`.
+    /// A scheduler can use this to choose between keeping a clone and sending a package.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// assert!(a.clone_is_free());
+    /// ```
+    pub fn clone_is_free(&self) -> bool {
+        !self.is_atomic_only()
+    }
 
-impl<T> MlspPackage<T> {
-    /// Turns this package into a normal Mlsp that can
-    /// be shared within this thread without atomic operations.
-    pub fn unpackage(self) -> Mlsp<T> {
-        Mlsp {
-            local_count: new_local_counter(),
-            inner_ptr: self.inner_ptr,
-        }
+    /// Returns true if this handle, or the handle it was cloned from,
+    /// was created by unpackaging an `MlspPackage`,
+    /// which means the contents may have arrived from another thread.
+    ///
+    /// Handles created by `new` or by upgrading an `MlspWeak` return false,
+    /// except for handles created after the allocation became hot, which always return true
+    /// since they have no local counter to record this in.
+    pub fn was_unpackaged(&self) -> bool {
+        self.is_atomic_only() || unsafe { self.local_count.as_ref().unpackaged }
     }
-}
 
-impl<T> Drop for MlspPackage<T> {
-    fn drop(&mut self) {
-        unsafe {
-            // Decrement the global pointer on the MlspInner and drop if necessary
-            self.inner_ptr.as_mut().decrement();
+    /// Runs `f` with this handle, checking in debug builds that the handles sharing its
+    /// local counter are only cloned and dropped on the current thread until `f` returns.
+    ///
+    /// `Mlsp` is not `Send`, so safe code can't move a handle to another thread,
+    /// but unsafe code that briefly steps outside the type system can.
+    /// Wrapping such a block marks where confinement is relied upon,
+    /// and a debug build panics at the clone or drop of a handle on another thread,
+    /// before it can race on the non-atomic local count. Release builds only call `f`.
+    /// Handles to hot allocations have no local count to race on and are not checked.
+    /// ```
+    /// let a = mlsp::Mlsp::new(vec![1, 2, 3]);
+    /// let len = a.confined_scope(|a| {
+    ///     let b = a.clone();
+    ///     b.as_ref().len()
+    /// });
+    /// assert_eq!(3, len);
+    /// ```
+    pub fn confined_scope<R>(&self, f: impl FnOnce(&Mlsp<T>) -> R) -> R {
+        #[cfg(debug_assertions)]
+        let _confinement = checks::confine(self.local_count);
+
+        f(self)
+    }
+
+    /// How many more atomic increments can be performed before reaching `MAX_REFCOUNT`.
+    pub fn count_headroom(&self) -> usize {
+        let count = unsafe { self.inner_ptr.as_ref().atomic_count.load(Ordering::Acquire) };
+        MAX_REFCOUNT - count
+    }
+
+    /// Reads the counts of this handle and its allocation together, for logging and diagnostics.
+    ///
+    /// The counts are read one after another, so while other threads share the allocation
+    /// the atomic and weak counts may change between the reads and afterwards.
+    /// The local count can only change on this thread, so it is exact.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let b = a.clone();
+    /// let package = a.package();
+    /// let weak = a.downgrade();
+    ///
+    /// let snapshot = a.count_snapshot();
+    /// assert_eq!((2, 2, 1), (snapshot.local, snapshot.atomic, snapshot.weak));
+    /// ```
+    pub fn count_snapshot(&self) -> CountSnapshot {
+        // SAFETY: The existence of this Mlsp keeps the inner alive
+        let inner = unsafe { self.inner_ptr.as_ref() };
+        CountSnapshot {
+            local: self.local_handles(),
+            atomic: inner.atomic_count.load(Ordering::Acquire),
+            // The strong references hold one weak reference between them, which this handle keeps
+            weak: inner.weak_count.load(Ordering::Acquire) - 1,
         }
     }
-}
 
-unsafe impl<T: Sync + Send> Send for MlspPackage<T> {}
-unsafe impl<T: Sync + Send> Sync for MlspPackage<T> {}
+    /// The fraction of the references to the contents that are handles on this thread,
+    /// for judging whether local counting pays off for this allocation.
+    ///
+    /// The local handles share one atomic reference, so this is the local count divided
+    /// by the local count plus every other atomic reference, from a single `count_snapshot`.
+    /// It is 1.0 while the value is only shared on this thread and falls towards 0.0
+    /// as packages and handles on other threads outnumber the local ones.
+    /// A hot handle counts only itself as local.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let b = a.clone();
+    /// assert_eq!(1.0, a.locality_ratio());
+    ///
+    /// let packages = a.package_n(2);
+    /// assert_eq!(0.5, b.locality_ratio());
+    /// ```
+    pub fn locality_ratio(&self) -> f64 {
+        let snapshot = self.count_snapshot();
+        let others = snapshot.atomic.saturating_sub(1);
+        snapshot.local as f64 / (snapshot.local + others) as f64
+    }
+
+    /// Compares the atomic counts of this handle's allocation and `other`'s, not their contents,
+    /// to find which of two shared values is referenced by more packages and threads.
+    ///
+    /// This is a diagnostic: the two counts are read one after the other,
+    /// so while other threads are packaging or dropping references the result may not
+    /// reflect any single moment, and it can change as soon as it is returned.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let b = mlsp::Mlsp::new(2u8);
+    /// let package = a.package();
+    /// assert_eq!(std::cmp::Ordering::Greater, a.compare_counts(&b));
+    /// ```
+    pub fn compare_counts(&self, other: &Self) -> cmp::Ordering {
+        // SAFETY: Both handles keep their inners alive
+        let (count, other_count) = unsafe {
+            (
+                self.inner_ptr.as_ref().atomic_count.load(Ordering::Acquire),
+                other
+                    .inner_ptr
+                    .as_ref()
+                    .atomic_count
+                    .load(Ordering::Acquire),
+            )
+        };
+        count.cmp(&other_count)
+    }
 
-impl<T> Clone for MlspPackage<T> {
-    fn clone(&self) -> Self {
-        unsafe {
-            self.inner_ptr.as_ref().increment();
+    /// Moves `other` onto this handle's local counter,
+    /// returning it unchanged if it refers to a different allocation.
+    ///
+    /// Handles on the same thread usually share one local counter,
+    /// but unpackaging or upgrading more than once creates a separate counter each time,
+    /// and each counter holds its own share of the atomic_count.
+    /// The returned handle shares this handle's counter instead,
+    /// and if `other` was the last handle on its counter the atomic_count is decremented.
+    pub fn merge_local(&self, other: Mlsp<T>) -> Result<Mlsp<T>, Mlsp<T>> {
+        if self.inner_ptr.as_ptr() as *const () != other.inner_ptr.as_ptr() as *const () {
+            return Err(other);
         }
 
-        MlspPackage {
-            inner_ptr: self.inner_ptr,
+        // Dropping `other` cannot drop the contents, since this handle keeps them alive
+        let merged = self.clone();
+        drop(other);
+        Ok(merged)
+    }
+
+    /// Returns true if this is the only handle to the contents,
+    /// with no other `Mlsp`s, `MlspPackage`s or `MlspWeak`s referencing them.
+    pub fn is_unique(&self) -> bool {
+        // SAFETY: The existence of this Mlsp keeps the local counter and the inner alive
+        unsafe {
+            let inner = self.inner_ptr.as_ref();
+            self.local_handles() == 1
+                && inner.atomic_count.load(Ordering::Acquire) == 1
+                && inner.weak_count.load(Ordering::Acquire) == 1
         }
     }
-}
 
-fn new_local_counter() -> NonNull<Cell<usize>> {
-    // Allocate the counter as a boxed cell
-    let local_counter: Box<Cell<usize>> = Box::new(Cell::new(1));
-    // Create a mutable pointer to the cell and prevent dropping
-    let local_counter: *mut Cell<usize> = Box::into_raw(local_counter);
-    // Turn that pointer into a NonNull
-    let local_counter: NonNull<Cell<usize>> = NonNull::new(local_counter).unwrap();
+    /// Returns true if other `Mlsp`s or `MlspPackage`s reference the contents,
+    /// reading the atomic count only when no other handle on this thread does.
+    ///
+    /// Other handles on this thread share this handle's local counter, so while there are any
+    /// the answer is known without touching the allocation, which lets hot paths rule out
+    /// uniqueness cheaply in the common case of a value cloned within its thread.
+    /// Unlike `is_unique`, `MlspWeak`s are not counted, since they don't share the contents.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// assert!(!a.is_definitely_shared());
+    ///
+    /// let b = a.clone();
+    /// assert!(a.is_definitely_shared());
+    /// drop(b);
+    ///
+    /// let package = a.package();
+    /// assert!(a.is_definitely_shared());
+    /// ```
+    pub fn is_definitely_shared(&self) -> bool {
+        if self.local_handles() > 1 {
+            return true;
+        }
 
-    local_counter
-}
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_load();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // SAFETY: The existence of this Mlsp keeps the inner alive
+        unsafe { self.inner_ptr.as_ref().atomic_count.load(Ordering::Acquire) > 1 }
+    }
 
-    #[test]
-    fn local_sharing() {
-        let a = Mlsp::new(1u8);
-        let b = a.clone();
-        let c = b.clone();
+    /// Returns a mutable reference to the contents if this is the only handle to them.
+    ///
+    /// See `is_unique` for when that is the case.
+    /// Every method that mutates the contents in place checks uniqueness the same way.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.try_get_mut().ok()
+    }
 
-        let d = c.package();
-        let _d2 = d.unpackage();
+    /// Returns a mutable reference to the contents if this is the only handle to them,
+    /// otherwise returns this handle so that the caller can fall back to something else.
+    ///
+    /// This never clones, see `make_mut` for a version that does.
+    pub fn try_get_mut(&mut self) -> Result<&mut T, &mut Self> {
+        if self.is_unique() {
+            // SAFETY: No other handle exists that could access the contents,
+            // and none can be created while this Mlsp is borrowed mutably
+            unsafe { Ok(&mut (*self.inner_ptr.as_ptr()).data) }
+        } else {
+            Err(self)
+        }
+    }
 
-        let e = c.package();
-        let _e2 = e.unpackage();
+    /// A pointer to the contents, which stays valid as long as any reference to them exists.
+    pub fn as_ptr(&self) -> *const T {
+        // Derived from the inner pointer rather than a reference to the data,
+        // so that `from_raw` can use it to reach the counters
+        unsafe { ptr::addr_of!((*self.inner_ptr.as_ptr()).data) as *const T }
+    }
 
-        // Convince clippy that we need these values
-        drop(c);
-        drop(b);
-        drop(a);
+    /// Calls `f` with a reference to the contents and returns its result.
+    ///
+    /// This is the same as `f(self.as_ref())`, for generic code where naming
+    /// the lifetime of the reference is awkward.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(self.as_ref())
     }
 
-    #[test]
-    fn cross_thread_sharing() {
-        use std::thread;
+    /// Computes a new value from the contents and returns it in a new allocation,
+    /// releasing this handle.
+    ///
+    /// The result shares nothing with the original, which is dropped as usual
+    /// once its last handle is gone.
+    pub fn map_value<U>(self, f: impl FnOnce(&T) -> U) -> Mlsp<U> {
+        Mlsp::new(f(self.as_ref()))
+    }
 
-        let mlsp = Mlsp::new(1u8);
+    /// Create a Send-able package from the Mlsp
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> MlspPackage<T> {
+        // Adding a new referent to the inner requires incrementing its counter
+        unsafe {
+            self.inner_ptr.as_ref().increment();
+        }
 
-        let mut children = vec![];
+        #[cfg(feature = "epoch")]
+        epoch::advance(unsafe { self.inner_ptr.as_ref() }, 1);
 
-        for _ in 0..10 {
-            let package = mlsp.package();
+        #[cfg(feature = "debug")]
+        debug::package_created(self.inner_ptr);
 
-            children.push(thread::spawn(move || {
+        #[cfg(feature = "tracing")]
+        trace::package(self.inner_ptr.as_ptr(), 1);
+
+        MlspPackage::from_inner(self.inner_ptr)
+    }
+
+    /// Creates a package from a pointer to contents that another reference keeps alive,
+    /// such as the head of a lock-free structure published through an `AtomicPtr`.
+    ///
+    /// This is the single atomic increment of `package`, made through a pointer
+    /// rather than a handle, so a thread can take its own reference to a published value
+    /// before whichever reference kept it alive is released.
+    ///
+    /// # Safety
+    /// - `ptr` must have been returned by `as_ptr` or `into_raw` of a handle or package
+    ///   for this exact `T`.
+    /// - The atomic count must stay above zero for the whole call:
+    ///   some other reference, such as the one held by the structure that published `ptr`,
+    ///   must be kept alive until this returns. Nothing is checked, a count that reached zero
+    ///   is incremented back up and the contents are used after they were dropped.
+    /// - If the pointer was published by another thread, `T` must be `Send + Sync`.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let ptr = a.as_ptr();
+    /// // SAFETY: `a` keeps the count above zero
+    /// let package = unsafe { mlsp::Mlsp::package_from_ptr(ptr) };
+    /// drop(a);
+    /// assert_eq!(1, *package.unpackage().as_ref());
+    /// ```
+    pub unsafe fn package_from_ptr(ptr: *const T) -> MlspPackage<T> {
+        // The reference this pointer stands for belongs to someone else, so it is only borrowed
+        let borrowed = ManuallyDrop::new(MlspPackage::from_raw(ptr));
+        (*borrowed).clone()
+    }
+
+    /// Reads a value derived from the contents and creates a package to forward them onward,
+    /// for the common pattern of processing a shared value and passing it on.
+    ///
+    /// This is `read` on the contents followed by `package`, with the same single atomic operation.
+    /// ```
+    /// let a = mlsp::Mlsp::new(vec![1, 2, 3]);
+    /// let (len, package) = a.read_and_forward(|v| v.len());
+    /// assert_eq!(3, len);
+    /// std::thread::spawn(move || assert_eq!(3, package.unpackage().len()))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    pub fn read_and_forward<R>(&self, read: impl FnOnce(&T) -> R) -> (R, MlspPackage<T>) {
+        let value = read(self.as_ref());
+        (value, self.package())
+    }
+
+    /// Turns this Mlsp into a Send-able package.
+    ///
+    /// If this is the last handle on its thread, its share of the atomic_count
+    /// is handed over to the package and no atomic operation is performed.
+    /// Otherwise this is the same as calling `package` and dropping the handle.
+    pub fn into_package(self) -> MlspPackage<T> {
+        // SAFETY: The existence of this Mlsp keeps the local counter alive
+        if self.local_handles() != 1 {
+            return self.package();
+        }
+
+        let this = ManuallyDrop::new(self);
+
+        #[cfg(feature = "debug")]
+        {
+            debug::handle_dropped(this.inner_ptr);
+            if !this.is_atomic_only() {
+                debug::counter_released(this.inner_ptr);
+            }
+            debug::package_created(this.inner_ptr);
+        }
+
+        #[cfg(feature = "tracing")]
+        trace::package(this.inner_ptr.as_ptr(), 1);
+
+        // SAFETY: This was the only handle using the local counter
+        unsafe { free_local_counter(this.local_count) };
+
+        MlspPackage::from_inner(this.inner_ptr)
+    }
+
+    /// Detaches this handle from its thread before a task moves to another one,
+    /// such as at a suspension point of a task in a work-stealing pool.
+    ///
+    /// This is `into_package` under a name that reads correctly at that point in the task,
+    /// resume with `MlspPackage::bind` on whichever thread the task continues on.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let suspended = a.rebind();
+    /// let resumed = std::thread::spawn(move || *suspended.bind().as_ref());
+    /// assert_eq!(1, resumed.join().unwrap());
+    /// ```
+    pub fn rebind(self) -> MlspPackage<T> {
+        self.into_package()
+    }
+
+    /// Create `n` Send-able packages from the Mlsp
+    ///
+    /// This increments the atomic_count by `n` with a single atomic operation
+    pub fn package_n(&self, n: usize) -> Vec<MlspPackage<T>> {
+        #[cfg(feature = "tracing")]
+        trace::package(self.inner_ptr.as_ptr(), n);
+
+        #[cfg(feature = "epoch")]
+        epoch::advance(unsafe { self.inner_ptr.as_ref() }, n);
+
+        MlspPackage::new_n(self.inner_ptr, n)
+    }
+
+    /// Appends `n` Send-able packages from the Mlsp to `out`,
+    /// for channels that send a batch of them in one call.
+    ///
+    /// This increments the atomic_count by `n` with a single atomic operation, as `package_n` does,
+    /// but reuses the capacity of `out`, so a fan-out loop that drains the buffer into a channel
+    /// and refills it doesn't allocate a `Vec` for each batch.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let mut batch = Vec::with_capacity(4);
+    /// for _ in 0..3 {
+    ///     a.package_batch_into(&mut batch, 4);
+    ///     // Sent as one batch, and the buffer kept for the next
+    ///     drop(batch.drain(..));
+    /// }
+    /// assert_eq!(4, batch.capacity());
+    /// ```
+    pub fn package_batch_into(&self, out: &mut Vec<MlspPackage<T>>, n: usize) {
+        if n == 0 {
+            return;
+        }
+
+        #[cfg(feature = "tracing")]
+        trace::package(self.inner_ptr.as_ptr(), n);
+
+        // SAFETY: The existence of this Mlsp keeps the inner alive,
+        // and each appended package decrements the counter once when dropped
+        unsafe {
+            let inner = self.inner_ptr.as_ref();
+
+            #[cfg(feature = "epoch")]
+            epoch::advance(inner, n);
+
+            inner.increment_by(n);
+            MlspPackage::counted_into(self.inner_ptr, n, out);
+        }
+    }
+
+    /// Create `n` Send-able packages from the Mlsp,
+    /// unless that would take the atomic_count above `max_total`.
+    ///
+    /// Either all `n` packages are created or none are and the count is left unchanged.
+    /// The atomic_count includes one for each thread holding `Mlsp`s as well as each package.
+    pub fn package_n_bounded(
+        &self,
+        n: usize,
+        max_total: usize,
+    ) -> Result<Vec<MlspPackage<T>>, BackpressureError> {
+        // SAFETY: Each returned package decrements the counter once when dropped
+        unsafe {
+            if self.inner_ptr.as_ref().increment_by_bounded(n, max_total) {
+                #[cfg(feature = "tracing")]
+                trace::package(self.inner_ptr.as_ptr(), n);
+
+                #[cfg(feature = "epoch")]
+                epoch::advance(self.inner_ptr.as_ref(), n);
+
+                Ok(MlspPackage::counted_n(self.inner_ptr, n))
+            } else {
+                Err(BackpressureError)
+            }
+        }
+    }
+
+    /// Create `n` clones of the Mlsp for this thread
+    ///
+    /// This raises the local count by `n` with a single update,
+    /// or the atomic_count with a single atomic operation for a handle without a local counter.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let clones = a.clone_n(3);
+    /// assert!(clones.iter().all(|b| b.as_ptr() == a.as_ptr()));
+    /// ```
+    pub fn clone_n(&self, n: usize) -> Vec<Mlsp<T>> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        if self.is_atomic_only() {
+            // SAFETY: Each clone decrements the atomic counter once when it is dropped
+            unsafe { self.inner_ptr.as_ref().increment_by(n) };
+        } else {
+            #[cfg(debug_assertions)]
+            checks::check_confined(self.local_count);
+
+            // SAFETY: The existence of this Mlsp keeps the local counter alive
+            let local_count = unsafe { &self.local_count.as_ref().count };
+            local_count.set(local_count.get() + n);
+        }
+
+        (0..n)
+            .map(|_| {
+                #[cfg(feature = "debug")]
+                debug::handle_created(self.inner_ptr);
+
+                Mlsp {
+                    local_count: self.local_count,
+                    inner_ptr: self.inner_ptr,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The same reference as `as_ref`, which it is defined in terms of so the two can't diverge.
+impl<T: ?Sized> Borrow<T> for Mlsp<T> {
+    fn borrow(&self) -> &T {
+        self.as_ref()
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for Mlsp<T> {
+    fn as_ref(&self) -> &T {
+        unsafe { &self.inner_ptr.as_ref().data }
+    }
+}
+
+/// Lets a shared path be passed straight to `std::fs` and other APIs taking `AsRef<Path>`.
+///
+/// With this impl `as_ref` on an `Mlsp<PathBuf>` has two possible targets,
+/// so calls that should return the `PathBuf` need the type spelled out, or `borrow`.
+impl AsRef<Path> for Mlsp<PathBuf> {
+    fn as_ref(&self) -> &Path {
+        let path: &PathBuf = self.borrow();
+        path
+    }
+}
+
+/// Lets a shared OS string be passed to APIs taking `AsRef<OsStr>`, such as `Command::arg`.
+///
+/// As with `Mlsp<PathBuf>`, `as_ref` calls that should return the `OsString` need a type.
+impl AsRef<OsStr> for Mlsp<OsString> {
+    fn as_ref(&self) -> &OsStr {
+        let string: &OsString = self.borrow();
+        string
+    }
+}
+
+/// Lets a shared string be passed to APIs taking `AsRef<str>`.
+///
+/// As with `Mlsp<PathBuf>`, `as_ref` calls that should return the `String` need a type.
+impl AsRef<str> for Mlsp<String> {
+    fn as_ref(&self) -> &str {
+        let string: &String = self.borrow();
+        string
+    }
+}
+
+/// Shows the contents along with the counts that keep them alive,
+/// the number of handles sharing this handle's local counter and the atomic count.
+/// ```
+/// let a = mlsp::Mlsp::new(1u8);
+/// let b = a.clone();
+/// assert_eq!("Mlsp { data: 1, local_count: 2, atomic_count: 1 }", format!("{:?}", a));
+/// ```
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = self.count_snapshot();
+        f.debug_struct("Mlsp")
+            .field("data", &self.as_ref())
+            .field("local_count", &counts.local)
+            .field("atomic_count", &counts.atomic)
+            .finish()
+    }
+}
+
+/// Shows the contents, as they would be shown without the handle.
+impl<T: ?Sized + fmt::Display> fmt::Display for Mlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_ref(), f)
+    }
+}
+
+/// Shows the address of the contents, the same as `as_ptr`.
+impl<T: ?Sized> fmt::Pointer for Mlsp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+
+/// Compares the contents with those of an `Arc`, for tests bridging the two.
+///
+/// A handle is deliberately not comparable with other handles,
+/// see `FrozenMlsp` for contents that can be compared and hashed.
+impl<T: ?Sized + PartialEq> PartialEq<Arc<T>> for Mlsp<T> {
+    fn eq(&self, other: &Arc<T>) -> bool {
+        *self.as_ref() == **other
+    }
+}
+
+/// Compares the contents with those of an `Mlsp`, for tests bridging the two.
+impl<T: ?Sized + PartialEq> PartialEq<Mlsp<T>> for Arc<T> {
+    fn eq(&self, other: &Mlsp<T>) -> bool {
+        **self == *other.as_ref()
+    }
+}
+
+/// Compares the contents with those of an `Rc`, for tests bridging the two.
+impl<T: ?Sized + PartialEq> PartialEq<Rc<T>> for Mlsp<T> {
+    fn eq(&self, other: &Rc<T>) -> bool {
+        *self.as_ref() == **other
+    }
+}
+
+/// Compares the contents with those of an `Mlsp`, for tests bridging the two.
+impl<T: ?Sized + PartialEq> PartialEq<Mlsp<T>> for Rc<T> {
+    fn eq(&self, other: &Mlsp<T>) -> bool {
+        **self == *other.as_ref()
+    }
+}
+
+impl<T: ?Sized + PartialEq> Mlsp<T> {
+    /// Tells whether `other` refers to the same allocation, to distinct but equal contents,
+    /// or to different contents, answering the questions of interning and deduplication
+    /// with one call.
+    ///
+    /// Handles to the same allocation are recognized by address without comparing the contents.
+    /// ```
+    /// use mlsp::{Mlsp, Relation};
+    ///
+    /// let a = Mlsp::new(String::from("interned"));
+    /// assert_eq!(Relation::SameAllocation, a.relation(&a.clone()));
+    /// assert_eq!(Relation::EqualContent, a.relation(&Mlsp::new(String::from("interned"))));
+    /// assert_eq!(Relation::Different, a.relation(&Mlsp::new(String::new())));
+    /// ```
+    pub fn relation(&self, other: &Self) -> Relation {
+        if self.inner_ptr.as_ptr() as *const () == other.inner_ptr.as_ptr() as *const () {
+            Relation::SameAllocation
+        } else if self.as_ref() == other.as_ref() {
+            Relation::EqualContent
+        } else {
+            Relation::Different
+        }
+    }
+}
+
+/// A shared error is an error, with the same source.
+impl<T: ?Sized + Error> Error for Mlsp<T> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.as_ref().source()
+    }
+}
+
+/// Indexes into a shared vector, as `Vec` does.
+///
+/// There is no `IndexMut`, since other handles may be reading the vector.
+/// Index into the result of `get_mut` or `make_mut` to modify elements.
+/// ```
+/// let mut a = mlsp::Mlsp::new(vec![1, 2, 3]);
+/// assert_eq!(2, a[1]);
+/// assert_eq!([2, 3], a[1..]);
+///
+/// a.get_mut().unwrap()[0] = 4;
+/// assert_eq!(4, a[0]);
+/// ```
+impl<T, I: SliceIndex<[T]>> Index<I> for Mlsp<Vec<T>> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        let vec: &Vec<T> = self.borrow();
+        &vec[index]
+    }
+}
+
+/// Forwards the size queries of a shared vector, so they can be called on the handle.
+/// ```
+/// let a = mlsp::Mlsp::new(vec![1, 2, 3]);
+/// assert_eq!(3, a.len());
+/// assert!(!a.is_empty());
+/// ```
+impl<T> Mlsp<Vec<T>> {
+    /// The number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    /// Whether the vector has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.as_ref().is_empty()
+    }
+
+    /// The number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.as_ref().capacity()
+    }
+
+    /// Shrinks the capacity of the vector to its length if this is the only reference to it,
+    /// such as before sharing a vector that is done growing.
+    ///
+    /// Returns whether it was unique, a shared vector is left unchanged.
+    pub fn shrink_to_fit(&mut self) -> bool {
+        match self.get_mut() {
+            Some(vec) => {
+                vec.shrink_to_fit();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Forwards the size queries of a shared string, so they can be called on the handle.
+/// ```
+/// let a = mlsp::Mlsp::new(String::from("mlsp"));
+/// assert_eq!(4, a.len());
+/// assert!(!a.is_empty());
+/// ```
+impl Mlsp<String> {
+    /// The length of the string in bytes.
+    pub fn len(&self) -> usize {
+        let string: &String = self.borrow();
+        string.len()
+    }
+
+    /// Whether the string has a length of zero bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes the string can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        let string: &String = self.borrow();
+        string.capacity()
+    }
+
+    /// Shrinks the capacity of the string to its length if this is the only reference to it.
+    ///
+    /// Returns whether it was unique, a shared string is left unchanged.
+    pub fn shrink_to_fit(&mut self) -> bool {
+        match self.get_mut() {
+            Some(string) => {
+                string.shrink_to_fit();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for Mlsp<T> {
+    fn clone(&self) -> Self {
+        if self.is_atomic_only() {
+            // SAFETY: The clone decrements the atomic counter when it is dropped
+            unsafe { self.inner_ptr.as_ref().increment() };
+        } else {
+            #[cfg(debug_assertions)]
+            checks::check_confined(self.local_count);
+
+            // SAFETY: Requires that local_count has not been freed.
+            // This is guaranteed by the existence of the current Mlsp.
+            let local_count = unsafe { &self.local_count.as_ref().count };
+
+            // Increment the local counter
+            let count = local_count.get();
+            let count = count + 1;
+            local_count.set(count);
+        }
+
+        #[cfg(feature = "debug")]
+        debug::handle_created(self.inner_ptr);
+
+        Mlsp {
+            local_count: self.local_count,
+            inner_ptr: self.inner_ptr,
+        }
+    }
+}
+
+impl<T> Mlsp<T> {
+    /// Replaces the contents with `new`, returning the old contents,
+    /// if this is the only handle to them.
+    ///
+    /// Returns `new` back as the error if the contents are shared, leaving them unchanged,
+    /// see `is_unique` for when that is the case.
+    pub fn replace(&mut self, new: T) -> Result<T, T> {
+        match self.get_mut() {
+            Some(data) => Ok(mem::replace(data, new)),
+            None => Err(new),
+        }
+    }
+}
+
+impl<T: Clone> Mlsp<T> {
+    /// Creates `count` independent Mlsps, each with its own allocation holding a clone of `value`.
+    ///
+    /// Unlike `package_n` nothing is shared between the results,
+    /// the last one takes `value` itself so it is cloned `count - 1` times.
+    pub fn new_many(value: T, count: usize) -> Vec<Mlsp<T>> {
+        let mut handles = Vec::with_capacity(count);
+        if count > 0 {
+            handles.extend((1..count).map(|_| Mlsp::new(value.clone())));
+            handles.push(Mlsp::new(value));
+        }
+        handles
+    }
+
+    /// Returns a mutable reference to the contents,
+    /// first cloning them into a new allocation if this is not the only handle to them.
+    ///
+    /// After cloning this handle no longer refers to the old contents,
+    /// which stay unchanged for every other handle, package and weak reference.
+    /// Uniqueness is decided exactly as for `get_mut`.
+    /// ```
+    /// let mut a = mlsp::Mlsp::new(vec![1u8]);
+    /// let b = a.clone();
+    /// a.make_mut().push(2);
+    /// assert_eq!(&[1, 2], &a.as_ref()[..]);
+    /// assert_eq!(&[1], &b.as_ref()[..]);
+    /// ```
+    pub fn make_mut(&mut self) -> &mut T {
+        if let Err(this) = self.try_get_mut() {
+            *this = Mlsp::new(this.as_ref().clone());
+        }
+
+        // SAFETY: The handle is either unique already or was just replaced with a new allocation
+        unsafe { &mut (*self.inner_ptr.as_ptr()).data }
+    }
+
+    /// The same as `make_mut`, named for the fallback it takes.
+    pub fn get_mut_or_clone(&mut self) -> &mut T {
+        self.make_mut()
+    }
+}
+
+impl<T> Mlsp<Vec<T>> {
+    /// Appends the contents of `iter` if this is the only handle to the vector,
+    /// otherwise returns `iter` unconsumed.
+    pub fn try_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), I> {
+        match self.get_mut() {
+            Some(v) => {
+                v.extend(iter);
+                Ok(())
+            }
+            None => Err(iter),
+        }
+    }
+}
+
+impl<T: Clone> Mlsp<Vec<T>> {
+    /// Freezes the vector into a shared slice once it has been built,
+    /// to share it without the vector's spare capacity or its pointer indirection.
+    ///
+    /// If this is the only handle to the vector, its elements are moved into the slice
+    /// and none are cloned. The vector's buffer is freed rather than reused,
+    /// since it has no room in front of the elements for the counters, as with `From<Vec<T>>`.
+    /// Otherwise the other references still need the vector, so the elements are cloned
+    /// into the slice and the vector is left to them.
+    /// ```
+    /// let mut a = mlsp::Mlsp::new(Vec::with_capacity(8));
+    /// a.try_extend([1, 2, 3]).unwrap();
+    /// let slice = a.into_boxed_slice_shared();
+    /// assert_eq!([1, 2, 3], slice.as_ref());
+    /// ```
+    pub fn into_boxed_slice_shared(self) -> Mlsp<[T]> {
+        match self.try_unwrap() {
+            Ok(vec) => Mlsp::from(vec),
+            Err(shared) => Mlsp::from(shared.as_ref().clone()),
+        }
+    }
+}
+
+impl<T> Extend<T> for Mlsp<Vec<T>> {
+    /// Appends the contents of `iter` to the vector.
+    ///
+    /// # Panics
+    /// Panics if this is not the only handle to the vector,
+    /// use `try_extend` to handle that case instead.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        if self.try_extend(iter).is_err() {
+            panic!("cannot extend an Mlsp<Vec<T>> that is not uniquely owned");
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Mlsp<[T]> {
+    /// Freezes a vector into a shared slice.
+    ///
+    /// The elements are moved into a new allocation that also holds the atomic counter,
+    /// the vector's buffer cannot be reused because it has no room for the counter.
+    fn from(mut v: Vec<T>) -> Self {
+        let len = v.len();
+
+        unsafe {
+            let inner_ptr = MlspInner::<[T]>::allocate_for_slice(len);
+            let data = ptr::addr_of_mut!((*inner_ptr.as_ptr()).data) as *mut T;
+            ptr::copy_nonoverlapping(v.as_ptr(), data, len);
+
+            // The elements now belong to the new allocation,
+            // so the vector must only free its buffer when dropped.
+            v.set_len(0);
+
+            Mlsp::from_inner(inner_ptr, false)
+        }
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Mlsp<[T]> {
+    /// Moves the elements of an array into a shared slice.
+    ///
+    /// The allocation is made before any element is moved and moving cannot panic,
+    /// so if allocating fails the array is still whole and is dropped as usual.
+    /// ```
+    /// let a = mlsp::Mlsp::from([1, 2, 3]);
+    /// assert_eq!([1, 2, 3], a.as_ref());
+    /// ```
+    fn from(array: [T; N]) -> Self {
+        unsafe {
+            let inner_ptr = MlspInner::<[T]>::allocate_for_slice(N);
+            let data = ptr::addr_of_mut!((*inner_ptr.as_ptr()).data) as *mut T;
+
+            // The elements now belong to the new allocation, so the array must not drop them
+            let array = ManuallyDrop::new(array);
+            ptr::copy_nonoverlapping(array.as_ptr(), data, N);
+
+            Mlsp::from_inner(inner_ptr, false)
+        }
+    }
+}
+
+impl<T: Clone> Mlsp<[T]> {
+    /// Creates a shared slice holding clones of the contents of each handle, in order,
+    /// to turn many small shared values into one contiguous array for a bulk pass.
+    ///
+    /// The handles and their contents are left untouched.
+    /// ```
+    /// let handles = vec![mlsp::Mlsp::new(1u32), mlsp::Mlsp::new(2)];
+    /// let slice = mlsp::Mlsp::<[u32]>::from_handles(&handles);
+    /// assert_eq!([1, 2], slice.as_ref());
+    /// ```
+    pub fn from_handles(handles: &[Mlsp<T>]) -> Self {
+        let values: Vec<T> = handles.iter().map(|a| a.as_ref().clone()).collect();
+        Mlsp::from(values)
+    }
+
+    /// Creates a shared slice holding clones of the elements of `s`.
+    ///
+    /// Returns an error instead of aborting if the allocation fails.
+    /// If cloning an element panics, the elements cloned so far are dropped
+    /// and the allocation is freed before the panic continues.
+    pub fn try_from_slice(s: &[T]) -> Result<Self, AllocError> {
+        /// Cleans up a partially initialized slice if a clone panics
+        struct Guard<T> {
+            inner_ptr: NonNull<MlspInner<[T]>>,
+            len: usize,
+            initialized: usize,
+        }
+
+        impl<T> Drop for Guard<T> {
+            fn drop(&mut self) {
+                unsafe {
+                    let data = ptr::addr_of_mut!((*self.inner_ptr.as_ptr()).data) as *mut T;
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(data, self.initialized));
+                    MlspInner::deallocate_slice(self.inner_ptr, self.len);
+                }
+            }
+        }
+
+        unsafe {
+            let inner_ptr = MlspInner::<[T]>::try_allocate_for_slice(s.len())?;
+            let data = ptr::addr_of_mut!((*inner_ptr.as_ptr()).data) as *mut T;
+
+            let mut guard = Guard {
+                inner_ptr,
+                len: s.len(),
+                initialized: 0,
+            };
+            for item in s {
+                ptr::write(data.add(guard.initialized), item.clone());
+                guard.initialized += 1;
+            }
+            mem::forget(guard);
+
+            Ok(Mlsp::from_inner(inner_ptr, false))
+        }
+    }
+}
+
+impl<T: Clone> TryFrom<&[T]> for Mlsp<[T]> {
+    type Error = AllocError;
+
+    fn try_from(s: &[T]) -> Result<Self, AllocError> {
+        Mlsp::try_from_slice(s)
+    }
+}
+
+impl<T: ?Sized> From<Mlsp<T>> for MlspPackage<T> {
+    /// See `Mlsp::into_package`
+    fn from(mlsp: Mlsp<T>) -> Self {
+        mlsp.into_package()
+    }
+}
+
+/// Dropping the last handle on a thread releases its bookkeeping in a fixed order:
+/// the debug registry entry, then the local counter, and only then the share of the atomic count,
+/// which drops the contents if it was the last share.
+///
+/// The contents' own `Drop` is arbitrary code that may create and drop handles on this thread,
+/// including other handles in the debug registry and leak canary, or panic.
+/// Finishing with the local counter first means that code never observes
+/// a counter that is half released, and a panic in it cannot leak the counter.
+impl<T: ?Sized> Drop for Mlsp<T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "debug")]
+        debug::handle_dropped(self.inner_ptr);
+
+        if self.is_atomic_only() {
+            // SAFETY: This handle holds its own share of the atomic count
+            unsafe { MlspInner::decrement(self.inner_ptr) };
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        checks::check_confined(self.local_count);
+
+        // SAFETY: Requires that two `Mlsp`s for the same inner data must never exist in different threads
+        unsafe {
+            let local_count = &self.local_count.as_ref().count;
+            // Decrement the local_count
+            let count = local_count.get();
+            let count = count - 1;
+            local_count.set(count);
+
+            // If the new value is greater than zero, there are still local references
+            // and no further operations are needed.
+            if count > 0 {
+                return;
+            }
+        }
+        // If the local_count was reduced to zero,
+        // then this thread no longer has any references
+
+        // SAFETY: Requires that no other `Mlsp`s exist that reference the same local_count
+        unsafe {
+            #[cfg(feature = "debug")]
+            debug::counter_released(self.inner_ptr);
+
+            // Free the local counter being used by this thread,
+            // before any code in the contents' drop can run
+            free_local_counter(self.local_count);
+            // Decrement the global pointer on the MlspInner and drop the inner data if necessary
+            MlspInner::decrement(self.inner_ptr);
+        }
+    }
+}
+
+/// A reference to the contents of an Mlsp
+/// that does not yet have a local counter and can be sent across threads.
+pub struct MlspPackage<T: ?Sized> {
+    inner_ptr: NonNull<MlspInner<T>>,
+    /// The thread the package was created on, unknown for packages rebuilt from a pointer
+    #[cfg(feature = "debug")]
+    source: Option<std::thread::ThreadId>,
+}
+
+impl<T: ?Sized> MlspPackage<T> {
+    /// Wraps a reference to an inner that is already counted, created on the current thread.
+    fn from_inner(inner_ptr: NonNull<MlspInner<T>>) -> Self {
+        // SAFETY: The new package's share of the atomic count keeps the inner alive
+        #[cfg(feature = "hooks")]
+        unsafe {
+            inner_ptr.as_ref().first_package.packaged()
+        };
+
+        MlspPackage {
+            inner_ptr,
+            #[cfg(feature = "debug")]
+            source: Some(std::thread::current().id()),
+        }
+    }
+
+    /// Creates `n` packages for an inner with a single increment of its atomic counter
+    fn new_n(inner_ptr: NonNull<MlspInner<T>>, n: usize) -> Vec<Self> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        // SAFETY: Each returned package decrements the counter once when dropped
+        unsafe {
+            inner_ptr.as_ref().increment_by(n);
+            Self::counted_n(inner_ptr, n)
+        }
+    }
+
+    /// Creates `n` packages for an inner whose counter has already been incremented for them
+    ///
+    /// # Safety
+    /// The atomic counter must have been incremented by `n` on behalf of the packages.
+    unsafe fn counted_n(inner_ptr: NonNull<MlspInner<T>>, n: usize) -> Vec<Self> {
+        let mut packages = Vec::with_capacity(n);
+        Self::counted_into(inner_ptr, n, &mut packages);
+        packages
+    }
+
+    /// Appends `n` packages to `out` for an inner whose counter has already been incremented for them
+    ///
+    /// # Safety
+    /// The atomic counter must have been incremented by `n` on behalf of the packages.
+    unsafe fn counted_into(inner_ptr: NonNull<MlspInner<T>>, n: usize, out: &mut Vec<Self>) {
+        out.extend((0..n).map(|_| {
+            #[cfg(feature = "debug")]
+            debug::package_created(inner_ptr);
+
+            MlspPackage::from_inner(inner_ptr)
+        }));
+    }
+
+    /// Drops every package in `packages`, releasing the references to each allocation
+    /// with a single atomic operation however many of its packages there are.
+    ///
+    /// This is for teardown, such as a worker discarding a mailbox of undelivered packages,
+    /// where dropping them one at a time would decrement each count once per package.
+    /// Allocations whose last reference is released are dropped and freed once each as usual.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let mut mailbox = a.package_n(3);
+    /// mailbox.push(mlsp::MlspPackage::from_value(2u8));
+    ///
+    /// mlsp::MlspPackage::drop_batch(mailbox);
+    /// assert!(a.is_unique());
+    /// ```
+    pub fn drop_batch(packages: Vec<Self>) {
+        let mut counts: HashMap<*const (), (NonNull<MlspInner<T>>, usize)> = HashMap::new();
+        for package in packages {
+            let package = ManuallyDrop::new(package);
+
+            #[cfg(feature = "debug")]
+            debug::package_dropped(package.inner_ptr);
+
+            let key = package.inner_ptr.as_ptr() as *const ();
+            counts.entry(key).or_insert((package.inner_ptr, 0)).1 += 1;
+        }
+
+        // If dropping one allocation's contents panics, the guard releases the rest while unwinding
+        let mut rest = ReleaseRest(counts.into_values());
+        for (inner_ptr, n) in &mut rest.0 {
+            // SAFETY: Each of the `n` packages held one increment of the count, and is forgotten
+            unsafe { MlspInner::decrement_by(inner_ptr, n) };
+        }
+    }
+
+    /// Clones this package so that the clone can be sent to another thread.
+    ///
+    /// This is the same as `clone`, which performs exactly one atomic increment.
+    pub fn clone_for_send(&self) -> Self {
+        self.clone()
+    }
+
+    /// Create `n` clones of this package
+    ///
+    /// This increments the atomic_count by `n` with a single atomic operation
+    pub fn clone_n(&self, n: usize) -> Vec<Self> {
+        MlspPackage::new_n(self.inner_ptr, n)
+    }
+
+    /// Turns this package into a normal Mlsp that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> Mlsp<T> {
+        #[cfg(feature = "debug")]
+        debug::check_unpackage(self.source);
+
+        // The package's reference is handed over to the new Mlsp,
+        // so the package must not decrement the atomic counter when it goes away.
+        let package = ManuallyDrop::new(self);
+
+        #[cfg(feature = "debug")]
+        debug::package_dropped(package.inner_ptr);
+
+        #[cfg(feature = "tracing")]
+        trace::unpackage(package.inner_ptr.as_ptr());
+
+        Mlsp::from_inner(package.inner_ptr, true)
+    }
+
+    /// Attaches a handle detached by `Mlsp::rebind` to the current thread,
+    /// where a migrated task resumes.
+    ///
+    /// This is `unpackage` under the name that pairs with `rebind`.
+    pub fn bind(self) -> Mlsp<T> {
+        self.unpackage()
+    }
+
+    /// Unpackages this package if `pred` accepts its contents,
+    /// otherwise returns it unchanged so that it can be forwarded elsewhere.
+    ///
+    /// Neither outcome performs an atomic operation.
+    /// ```
+    /// let package = mlsp::Mlsp::new(3u8).package();
+    /// let package = package.unpackage_if(|n| n % 2 == 0).err().unwrap();
+    /// assert_eq!(3, *package.unpackage_if(|n| n % 2 == 1).ok().unwrap().as_ref());
+    /// ```
+    pub fn unpackage_if(self, pred: impl FnOnce(&T) -> bool) -> Result<Mlsp<T>, Self> {
+        // SAFETY: The package keeps the contents alive, and they are only shared immutably
+        if pred(unsafe { &*self.as_ptr() }) {
+            Ok(self.unpackage())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// A pointer to the contents, which stays valid as long as any reference to them exists.
+    pub fn as_ptr(&self) -> *const T {
+        // Derived from the inner pointer rather than a reference to the data,
+        // so that `from_raw` can use it to reach the counters
+        unsafe { ptr::addr_of!((*self.inner_ptr.as_ptr()).data) as *const T }
+    }
+
+    /// Turns this package into a pointer to its contents.
+    ///
+    /// The pointer keeps the package's reference alive,
+    /// the value is leaked unless it is turned back into a package with `from_raw`.
+    pub fn into_raw(self) -> *const T {
+        let package = ManuallyDrop::new(self);
+        package.as_ptr()
+    }
+
+    /// Turns a pointer created by `into_raw` back into a package.
+    ///
+    /// # Safety
+    /// - `ptr` must have been returned by `MlspPackage::<T>::into_raw` for this exact `T`.
+    /// - Each pointer must be passed to `from_raw` exactly once,
+    ///   using it again would release the same reference twice.
+    /// - If the pointer was created on another thread, `T` must be `Send + Sync`.
+    /// ```
+    /// let a = mlsp::Mlsp::<[u8]>::from(vec![1, 2]);
+    /// let ptr = a.package().into_raw();
+    /// let package = unsafe { mlsp::MlspPackage::from_raw(ptr) };
+    /// assert_eq!([1, 2], package.unpackage().as_ref());
+    /// ```
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        // The repr(C) layout puts the data at a fixed offset from the start of the inner
+        let offset = MlspInner::<T>::data_offset(mem::align_of_val(&*ptr));
+        let inner = ptr.byte_sub(offset) as *mut MlspInner<T>;
+
+        MlspPackage {
+            inner_ptr: NonNull::new_unchecked(inner),
+            #[cfg(feature = "debug")]
+            source: None,
+        }
+    }
+}
+
+impl<T: Copy> MlspPackage<T> {
+    /// Returns a copy of the contents and frees the allocation
+    /// if this package is the only reference to them, otherwise returns the package unchanged.
+    ///
+    /// For small values this lets the last reference be sent as the value itself,
+    /// so the receiver gets it without unpackaging or touching the allocation.
+    /// ```
+    /// let package = mlsp::Mlsp::new(7u32).into_package();
+    /// assert_eq!(Ok(7), package.try_inline().map_err(|_| ()));
+    /// ```
+    pub fn try_inline(self) -> Result<T, Self> {
+        #[cfg(feature = "metrics")]
+        metrics::record_atomic_op();
+
+        // SAFETY: The existence of this package keeps the inner alive
+        unsafe {
+            // Claim the contents by taking the atomic count from one to zero,
+            // after which weak references can no longer upgrade
+            let inner = self.inner_ptr.as_ref();
+            if inner
+                .atomic_count
+                .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                return Err(self);
+            }
+
+            #[cfg(feature = "tracing")]
+            trace::decrement(inner, 0);
+
+            let package = ManuallyDrop::new(self);
+
+            #[cfg(feature = "debug")]
+            debug::package_dropped(package.inner_ptr);
+
+            let data = *(*package.inner_ptr.as_ptr()).data;
+
+            // Release the weak reference held collectively by the strong references
+            MlspInner::decrement_weak(package.inner_ptr);
+
+            Ok(data)
+        }
+    }
+}
+
+impl<T> MlspPackage<T> {
+    /// Creates a package holding the only reference to `data`,
+    /// for values decoded or received on one thread that are only there to be forwarded.
+    ///
+    /// This is `Mlsp::new(data).into_package()` without the local counter,
+    /// which would be allocated only to be freed again, and performs no atomic operations.
+    /// ```
+    /// let package = mlsp::MlspPackage::from_value(String::from("decoded"));
+    /// std::thread::spawn(move || assert_eq!("decoded", AsRef::<str>::as_ref(&package.unpackage())))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    pub fn from_value(data: T) -> Self {
+        let inner = NonNull::from(Box::leak(Box::new(MlspInner::new(data))));
+
+        #[cfg(feature = "debug")]
+        debug::package_created(inner);
+
+        MlspPackage::from_inner(inner)
+    }
+
+    /// Turns this package into a plain integer token,
+    /// for transports such as C queues that can only carry a `usize`.
+    ///
+    /// The token keeps the package's reference alive,
+    /// the value is leaked unless the token is turned back into a package with `from_token`.
+    ///
+    /// A token carries no type information and no `Send` bound,
+    /// so the checks the type system normally performs on a package
+    /// become obligations of the caller of `from_token`.
+    pub fn into_token(self) -> usize {
+        let package = ManuallyDrop::new(self);
+        package.inner_ptr.as_ptr() as usize
+    }
+
+    /// Turns a token created by `into_token` back into a package.
+    ///
+    /// # Safety
+    /// - `token` must have been returned by `MlspPackage::<T>::into_token` for this exact `T`.
+    /// - Each token must be passed to `from_token` exactly once,
+    ///   using it again would release the same reference twice.
+    /// - If the token was created on another thread, `T` must be `Send + Sync`.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let token = a.package().into_token();
+    /// let package = unsafe { mlsp::MlspPackage::<u8>::from_token(token) };
+    /// assert_eq!(1u8, *package.unpackage().as_ref());
+    /// ```
+    pub unsafe fn from_token(token: usize) -> Self {
+        MlspPackage {
+            inner_ptr: NonNull::new_unchecked(token as *mut MlspInner<T>),
+            #[cfg(feature = "debug")]
+            source: None,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MlspPackage<T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "debug")]
+        debug::package_dropped(self.inner_ptr);
+
+        unsafe {
+            // Decrement the global pointer on the MlspInner and drop if necessary
+            MlspInner::decrement(self.inner_ptr);
+        }
+    }
+}
+
+/// The allocations of a `MlspPackage::drop_batch` not yet released, with the packages of each,
+/// which are released when it is dropped
+struct ReleaseRest<T: ?Sized>(hash_map::IntoValues<*const (), (NonNull<MlspInner<T>>, usize)>);
+
+impl<T: ?Sized> Drop for ReleaseRest<T> {
+    fn drop(&mut self) {
+        for (inner_ptr, n) in &mut self.0 {
+            // SAFETY: As in `drop_batch`, each count is released once, here or there
+            unsafe { MlspInner::decrement_by(inner_ptr, n) };
+        }
+    }
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for MlspPackage<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for MlspPackage<T> {}
+
+/// Shows the contents along with the atomic count.
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MlspPackage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // SAFETY: The package keeps the inner alive, and the contents are only shared immutably
+        let (data, atomic_count) = unsafe {
+            (
+                &*self.as_ptr(),
+                self.inner_ptr.as_ref().atomic_count.load(Ordering::Relaxed),
+            )
+        };
+        f.debug_struct("MlspPackage")
+            .field("data", &data)
+            .field("atomic_count", &atomic_count)
+            .finish()
+    }
+}
+
+/// Shows the address of the contents, the same as `as_ptr`.
+impl<T: ?Sized> fmt::Pointer for MlspPackage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+
+impl<T: ?Sized> Clone for MlspPackage<T> {
+    /// Clones the package with a single atomic increment,
+    /// the minimum needed for the clone to be sent to another thread.
+    fn clone(&self) -> Self {
+        unsafe {
+            self.inner_ptr.as_ref().increment();
+        }
+
+        #[cfg(feature = "debug")]
+        debug::package_created(self.inner_ptr);
+
+        // A clone comes from wherever the original did
+        MlspPackage {
+            inner_ptr: self.inner_ptr,
+            #[cfg(feature = "debug")]
+            source: self.source,
+        }
+    }
+}
+
+/// The counts of an `Mlsp` and its allocation, read by `Mlsp::count_snapshot`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CountSnapshot {
+    /// The number of handles sharing the handle's local counter.
+    pub local: usize,
+    /// The atomic count, one for each package and each thread's share of handles.
+    pub atomic: usize,
+    /// The number of `MlspWeak`s.
+    pub weak: usize,
+}
+
+/// How the contents of two `Mlsp`s relate, see `Mlsp::relation`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    /// Both handles refer to the same allocation.
+    SameAllocation,
+    /// The handles refer to distinct allocations with equal contents.
+    EqualContent,
+    /// The contents are not equal.
+    Different,
+}
+
+/// The error returned when allocating memory for an Mlsp fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
+
+/// The error returned when packaging would exceed the allowed number of references
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackpressureError;
+
+impl fmt::Display for BackpressureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("too many outstanding references")
+    }
+}
+
+impl Error for BackpressureError {}
+
+/// The per-thread portion of the Mlsp,
+/// shared by all of the `Mlsp`s for one inner created from the same `new` or `unpackage`.
+struct LocalCounter {
+    count: Cell<usize>,
+    /// Whether these `Mlsp`s were created by unpackaging a package
+    unpackaged: bool,
+    #[cfg(debug_assertions)]
+    sentinel: usize,
+    /// The thread these `Mlsp`s are confined to by `Mlsp::confined_scope`, if any
+    #[cfg(debug_assertions)]
+    confined: AtomicU64,
+}
+
+fn new_local_counter(unpackaged: bool) -> NonNull<LocalCounter> {
+    let counter = LocalCounter {
+        count: Cell::new(1),
+        unpackaged,
+        #[cfg(debug_assertions)]
+        sentinel: checks::SENTINEL,
+        #[cfg(debug_assertions)]
+        confined: AtomicU64::new(checks::UNCONFINED),
+    };
+
+    // Reuse a counter kept by this thread, see `pool::prewarm`
+    #[cfg(feature = "pool")]
+    if let Some(local_counter) = pool::take_counter() {
+        // SAFETY: A kept counter is an allocation for a LocalCounter that nothing references
+        unsafe { ptr::write(local_counter.as_ptr(), counter) };
+
+        #[cfg(debug_assertions)]
+        canary::register();
+
+        return local_counter;
+    }
+
+    // Allocate the counter as a boxed cell
+    let local_counter: Box<LocalCounter> = Box::new(counter);
+    // Create a mutable pointer to the cell and prevent dropping
+    let local_counter: *mut LocalCounter = Box::into_raw(local_counter);
+    // Turn that pointer into a NonNull
+    let local_counter: NonNull<LocalCounter> = NonNull::new(local_counter).unwrap();
+
+    #[cfg(debug_assertions)]
+    canary::register();
+
+    local_counter
+}
+
+/// Frees a local counter created by `new_local_counter`
+///
+/// # Safety
+/// No `Mlsp` referencing the counter may be used afterwards.
+unsafe fn free_local_counter(local_counter: NonNull<LocalCounter>) {
+    // Handles to hot allocations have no counter to free
+    if local_counter == ATOMIC_ONLY {
+        return;
+    }
+
+    #[cfg(debug_assertions)]
+    assert_eq!(
+        checks::SENTINEL,
+        local_counter.as_ref().sentinel,
+        "freeing a local counter that is not live"
+    );
+
+    #[cfg(debug_assertions)]
+    canary::unregister();
+
+    // The same as dropping the box, unless this thread keeps the counter for reuse
+    #[cfg(feature = "pool")]
+    {
+        // A kept counter no longer reads as live, so freeing it twice is still caught
+        #[cfg(debug_assertions)]
+        {
+            (*local_counter.as_ptr()).sentinel = 0;
+        }
+        if pool::give_counter(local_counter) {
+            return;
+        }
+    }
+
+    drop(Box::from_raw(local_counter.as_ptr()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_sharing() {
+        let a = Mlsp::new(1u8);
+        let b = a.clone();
+        let c = b.clone();
+
+        let d = c.package();
+        let _d2 = d.unpackage();
+
+        let e = c.package();
+        let _e2 = e.unpackage();
+
+        // Convince clippy that we need these values
+        drop(c);
+        drop(b);
+        drop(a);
+    }
+
+    #[test]
+    fn cross_thread_sharing() {
+        use std::thread;
+
+        let mlsp = Mlsp::new(1u8);
+
+        let mut children = vec![];
+
+        for _ in 0..10 {
+            let package = mlsp.package();
+
+            children.push(thread::spawn(move || {
                 let shared_mlsp = package.unpackage();
                 let shared_mlsp_clone = shared_mlsp.clone();
 
-                assert_eq!(1u8, *(shared_mlsp.borrow()));
-                assert_eq!(1u8, *(shared_mlsp_clone.borrow()));
-            }));
+                assert_eq!(1u8, *Borrow::<u8>::borrow(&shared_mlsp));
+                assert_eq!(1u8, *Borrow::<u8>::borrow(&shared_mlsp_clone));
+            }));
+        }
+
+        for child in children {
+            // Wait for the thread to finish. Returns a result.
+            let _ = child.join();
+        }
+    }
+
+    #[test]
+    fn vec_into_shared_slice() {
+        let v: Vec<String> = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        let shared: Mlsp<[String]> = Mlsp::from(v);
+        let shared_clone = shared.clone();
+
+        assert_eq!(3, shared.as_ref().len());
+        assert_eq!(["a", "b", "c"], shared_clone.as_ref());
+
+        let _unpackaged = shared.package().unpackage();
+    }
+
+    #[test]
+    fn vec_into_shared_slice_drops_elements_once() {
+        use std::rc::Rc;
+
+        let marker = Rc::new(());
+        let v: Vec<Rc<()>> = (0..5).map(|_| marker.clone()).collect();
+
+        // Moving the elements out of the vector must not drop any of them
+        let shared: Mlsp<[Rc<()>]> = v.into();
+        assert_eq!(6, Rc::strong_count(&marker));
+
+        // Dropping the last handle drops every element exactly once
+        drop(shared);
+        assert_eq!(1, Rc::strong_count(&marker));
+
+        let empty: Mlsp<[String]> = Vec::new().into();
+        assert!(empty.as_ref().is_empty());
+    }
+
+    #[test]
+    fn copied() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let a = Mlsp::new(u64::MAX);
+        assert_eq!(u64::MAX, a.copied());
+
+        let p = Mlsp::new(Point { x: 1, y: -1 });
+        let mut copy = p.copied();
+        copy.x = 2;
+        assert_eq!(Point { x: 2, y: -1 }, copy);
+        assert_eq!(Point { x: 1, y: -1 }, p.copied());
+    }
+
+    #[test]
+    fn token_round_trip() {
+        use std::thread;
+
+        let a = Mlsp::new(String::from("token"));
+        let token = a.package().into_token();
+
+        let contents = thread::spawn(move || {
+            // SAFETY: The token came from a package of a String and is used once
+            let package = unsafe { MlspPackage::<String>::from_token(token) };
+            AsRef::<String>::as_ref(&package.unpackage()).clone()
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!("token", contents);
+    }
+
+    #[test]
+    fn inner_layout() {
+        use std::mem::offset_of;
+
+        assert_eq!(0, offset_of!(MlspInner<u8>, atomic_count));
+        assert!(offset_of!(MlspInner<u8>, data) > offset_of!(MlspInner<u8>, atomic_count));
+        assert_eq!(
+            offset_of!(MlspInner<u8>, data),
+            MlspInner::<u8>::data_offset(mem::align_of::<u8>())
+        );
+        assert_eq!(
+            offset_of!(MlspInner<u128>, data),
+            MlspInner::<u128>::data_offset(mem::align_of::<u128>())
+        );
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let a = Mlsp::new(5u128);
+        let ptr = a.package().into_raw();
+        assert_eq!(a.as_ptr(), ptr);
+
+        // SAFETY: The pointer came from a package of a u128 and is used once
+        let package = unsafe { MlspPackage::from_raw(ptr) };
+        assert_eq!(5u128, *package.unpackage().as_ref());
+
+        let b = Mlsp::<[String]>::from(vec![String::from("raw")]);
+        let ptr = b.package().into_raw();
+
+        // SAFETY: The pointer came from a package of a [String] and is used once
+        let package = unsafe { MlspPackage::from_raw(ptr) };
+        assert_eq!(b.as_ptr(), package.as_ptr());
+        assert_eq!(["raw"], package.unpackage().as_ref());
+    }
+
+    #[test]
+    fn batched_packages() {
+        let a = Mlsp::new(1u8);
+        let weak = a.downgrade();
+
+        let packages = a.package_n(3);
+        let clones = packages[0].clone_n(2);
+        assert_eq!(6, weak.strong_count());
+
+        drop(packages);
+        drop(a);
+        assert_eq!(2, weak.strong_count());
+        assert_eq!(1u8, *clones[1].clone().unpackage().as_ref());
+
+        drop(clones);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn drop_batch_panicking_drop() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::AtomicUsize;
+
+        struct Dropped(Arc<AtomicUsize>, bool);
+
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                assert!(!self.1, "panicking drop");
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let values: Vec<_> = (0..5)
+            .map(|i| Mlsp::new(Dropped(drops.clone(), i == 2)))
+            .collect();
+        let weaks: Vec<_> = values.iter().map(Mlsp::downgrade).collect();
+        let mut mailbox = Vec::new();
+        for value in values {
+            mailbox.extend(value.package_n(2));
+        }
+
+        // Every allocation is released, whichever order the batch visits them in
+        let result = panic::catch_unwind(AssertUnwindSafe(|| MlspPackage::drop_batch(mailbox)));
+        assert!(result.is_err());
+        assert_eq!(5, drops.load(Ordering::Relaxed));
+        assert!(weaks.iter().all(|weak| weak.strong_count() == 0));
+    }
+
+    #[test]
+    fn unpackaged_provenance() {
+        let a = Mlsp::new(1u8);
+        assert!(!a.was_unpackaged());
+        assert!(!a.clone().was_unpackaged());
+        assert!(!a.downgrade().upgrade().unwrap().was_unpackaged());
+
+        let b = a.package().unpackage();
+        assert!(b.was_unpackaged());
+        assert!(b.clone().was_unpackaged());
+        assert!(!a.was_unpackaged());
+    }
+
+    #[test]
+    fn unique_get_mut() {
+        let mut a = Mlsp::new(1u8);
+        assert!(a.is_unique());
+        *a.get_mut().unwrap() += 1;
+
+        let b = a.clone();
+        assert!(a.get_mut().is_none());
+        drop(b);
+
+        let package = a.package();
+        assert!(a.get_mut().is_none());
+        drop(package);
+
+        let weak = a.downgrade();
+        assert!(a.get_mut().is_none());
+        drop(weak);
+
+        assert_eq!(Some(&mut 2), a.get_mut());
+    }
+
+    #[test]
+    fn extend_unique_vec() {
+        let mut a = Mlsp::new(vec![1u8]);
+        a.extend([2, 3]);
+        assert_eq!(Ok(()), a.try_extend(vec![4]));
+        assert_eq!(&[1, 2, 3, 4], a.as_ref().as_slice());
+
+        // A shared vector cannot be extended and the input is handed back
+        let b = a.clone();
+        assert_eq!(Err(vec![5]), a.try_extend(vec![5]));
+        assert_eq!(4, b.as_ref().len());
+    }
+
+    #[test]
+    #[should_panic(expected = "not uniquely owned")]
+    fn extend_shared_vec_panics() {
+        let mut a = Mlsp::new(vec![1u8]);
+        let _package = a.package();
+        a.extend([2]);
+    }
+
+    #[test]
+    fn unwrap_unique() {
+        let a = Mlsp::new(String::from("unique"));
+        let weak = a.downgrade();
+
+        assert_eq!(Some(String::from("unique")), a.try_unwrap().ok());
+        assert!(weak.upgrade().is_none());
+
+        let b = Mlsp::new(String::from("boxed"));
+        let boxed: Option<Box<String>> = b.try_into_box().ok();
+        assert_eq!(Some(Box::new(String::from("boxed"))), boxed);
+    }
+
+    #[test]
+    fn unwrap_shared() {
+        let a = Mlsp::new(String::from("shared"));
+
+        let clone = a.clone();
+        let a = a.try_into_box().unwrap_err();
+        drop(clone);
+
+        let package = a.package();
+        let a = a.try_unwrap().unwrap_err();
+        let a = a.try_into_box().unwrap_err();
+        assert_eq!("shared", AsRef::<str>::as_ref(&a));
+
+        // Once the package is gone the remaining handle is unique
+        drop(package);
+        assert_eq!(
+            Some(Box::new(String::from("shared"))),
+            a.try_into_box().ok()
+        );
+    }
+
+    #[test]
+    fn bounded_packages() {
+        let a = Mlsp::new(1u8);
+        let weak = a.downgrade();
+
+        let first = a.package_n_bounded(2, 4).unwrap();
+        assert_eq!(3, weak.strong_count());
+
+        // All or nothing, one more would fit but two would not
+        assert_eq!(Some(BackpressureError), a.package_n_bounded(2, 4).err());
+        assert_eq!(3, weak.strong_count());
+
+        let second = a.package_n_bounded(1, 4).unwrap();
+        assert_eq!(Some(BackpressureError), a.package_n_bounded(1, 4).err());
+        assert_eq!(4, weak.strong_count());
+
+        drop(first);
+        assert_eq!(2, a.package_n_bounded(2, 4).unwrap().len());
+        drop(second);
+    }
+
+    #[test]
+    fn count_headroom() {
+        assert_eq!(isize::MAX as usize, Mlsp::<u8>::MAX_REFCOUNT);
+
+        let a = Mlsp::new(1u8);
+        assert_eq!(Mlsp::<u8>::MAX_REFCOUNT - 1, a.count_headroom());
+
+        // Local clones do not use up any headroom
+        let _b = a.clone();
+        let packages = a.package_n(3);
+        assert_eq!(Mlsp::<u8>::MAX_REFCOUNT - 4, a.count_headroom());
+
+        drop(packages);
+        assert_eq!(Mlsp::<u8>::MAX_REFCOUNT - 1, a.count_headroom());
+
+        // Bounded packaging never exceeds the limit
+        assert!(a.package_n_bounded(usize::MAX, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn merge_duplicate_counters() {
+        let a = Mlsp::new(1u8);
+        let weak = a.downgrade();
+        let first = a.package();
+        let second = a.package();
+        drop(a);
+
+        // Each unpackage creates its own local counter
+        let b = first.unpackage();
+        let c = second.unpackage();
+        let c2 = c.clone();
+        assert_eq!(2, weak.strong_count());
+
+        // The other counter is still used by c2
+        let c = b.merge_local(c).ok().unwrap();
+        assert_eq!(2, weak.strong_count());
+
+        let c2 = b.merge_local(c2).ok().unwrap();
+        assert_eq!(1, weak.strong_count());
+
+        // Handles on the same counter are returned as they are
+        let c2 = b.merge_local(c2).ok().unwrap();
+        assert_eq!(1, weak.strong_count());
+
+        assert!(b.merge_local(Mlsp::new(1u8)).is_err());
+        drop((b, c, c2));
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn into_package() {
+        let a = Mlsp::new(String::from("sole"));
+        let weak = a.downgrade();
+
+        // The sole handle's share of the count moves to the package
+        let package: MlspPackage<_> = a.into();
+        assert_eq!(1, weak.strong_count());
+
+        let b = package.unpackage();
+        let c = b.clone();
+        let package: MlspPackage<_> = b.into();
+        assert_eq!(2, weak.strong_count());
+        drop(c);
+        assert_eq!(1, weak.strong_count());
+
+        let len = std::thread::spawn(move || package.unpackage().len())
+            .join()
+            .unwrap();
+        assert_eq!(4, len);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn package_from_value() {
+        let package = MlspPackage::from_value(String::from("received"));
+        let a = package.unpackage();
+        assert!(a.was_unpackaged());
+        let snapshot = a.count_snapshot();
+        assert_eq!((1, 1, 0), (snapshot.local, snapshot.atomic, snapshot.weak));
+
+        // The package held the only reference, so nothing else is left to release
+        assert_eq!("received", a.try_unwrap().ok().unwrap());
+    }
+
+    /// A push-only Treiber stack, whose head holds a reference to the newest node
+    /// and each node a reference to the one below it, so no node is released while it lives
+    struct PushOnly {
+        head: atomic::AtomicPtr<Link>,
+    }
+
+    struct Link {
+        value: usize,
+        next: atomic::AtomicPtr<Link>,
+    }
+
+    // SAFETY: The pointers stand for packages of `Link`, which are `Send + Sync`
+    unsafe impl Send for Link {}
+    unsafe impl Sync for Link {}
+
+    impl PushOnly {
+        fn push(&self, value: usize) {
+            let next = atomic::AtomicPtr::new(ptr::null_mut());
+            let node = MlspPackage::from_value(Link { value, next }).into_raw() as *mut Link;
+            let mut head = self.head.load(Ordering::Acquire);
+            loop {
+                // SAFETY: Nothing else can reach the node until it is published
+                unsafe { (*node).next.store(head, Ordering::Relaxed) };
+                // The node takes over the stack's reference to the old head
+                match self.head.compare_exchange_weak(
+                    head,
+                    node,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => return,
+                    Err(current) => head = current,
+                }
+            }
         }
 
-        for child in children {
-            // Wait for the thread to finish. Returns a result.
-            let _ = child.join();
+        /// The values from the top down, read through a package of the head
+        fn snapshot(&self) -> Vec<usize> {
+            let head = self.head.load(Ordering::Acquire);
+            if head.is_null() {
+                return Vec::new();
+            }
+            // SAFETY: The stack never releases its reference to a node it has published
+            let top = unsafe { Mlsp::package_from_ptr(head) }.unpackage();
+            top.as_ref().values()
+        }
+    }
+
+    impl Link {
+        /// The values of this node and every node below it
+        fn values(&self) -> Vec<usize> {
+            let mut values = vec![self.value];
+            let mut next = self.next.load(Ordering::Acquire);
+            while !next.is_null() {
+                // SAFETY: Each node keeps the one below it alive, and `self` keeps them all
+                let node = unsafe { &*next };
+                values.push(node.value);
+                next = node.next.load(Ordering::Acquire);
+            }
+            values
+        }
+    }
+
+    impl Drop for Link {
+        fn drop(&mut self) {
+            let next = *self.next.get_mut();
+            if !next.is_null() {
+                // SAFETY: The link held the reference to the node below it
+                drop(unsafe { MlspPackage::from_raw(next) });
+            }
+        }
+    }
+
+    impl Drop for PushOnly {
+        fn drop(&mut self) {
+            let head = *self.head.get_mut();
+            if !head.is_null() {
+                // SAFETY: The stack held the reference to its head
+                drop(unsafe { MlspPackage::from_raw(head) });
+            }
+        }
+    }
+
+    #[test]
+    fn package_from_published_pointer() {
+        use std::thread;
+
+        let stack = std::sync::Arc::new(PushOnly {
+            head: atomic::AtomicPtr::new(ptr::null_mut()),
+        });
+        let threads: Vec<_> = (0..4)
+            .map(|id| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    let mut seen = 0;
+                    for i in 0..100 {
+                        stack.push(id * 100 + i);
+                        // Readers take references while other threads keep pushing
+                        let values = stack.snapshot();
+                        assert!(values.len() > seen);
+                        assert!(values.contains(&(id * 100 + i)));
+                        seen = values.len();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut values = stack.snapshot();
+        values.sort_unstable();
+        assert_eq!((0..400).collect::<Vec<_>>(), values);
+
+        // A package outlives the stack that published it, and keeps the nodes below it alive
+        let head = stack.head.load(Ordering::Acquire);
+        let top = unsafe { Mlsp::package_from_ptr(head) };
+        drop(std::sync::Arc::try_unwrap(stack).ok().unwrap());
+        assert_eq!(400, top.unpackage().as_ref().values().len());
+    }
+
+    #[test]
+    fn freeze_vec_into_slice() {
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Tracked(u32);
+        impl Clone for Tracked {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Tracked(self.0)
+            }
+        }
+
+        let values = |slice: &[Tracked]| slice.iter().map(|t| t.0).collect::<Vec<_>>();
+
+        // The only handle gives up its elements without cloning any
+        let a = Mlsp::new((0..4).map(Tracked).collect::<Vec<_>>());
+        let slice = a.into_boxed_slice_shared();
+        assert_eq!(vec![0, 1, 2, 3], values(slice.as_ref()));
+        assert_eq!(0, CLONES.with(Cell::get));
+
+        // A shared vector is cloned, and stays with its other handles
+        let a = Mlsp::new((0..4).map(Tracked).collect::<Vec<_>>());
+        let package = a.package();
+        let slice = a.into_boxed_slice_shared();
+        assert_eq!(vec![0, 1, 2, 3], values(slice.as_ref()));
+        assert_eq!(4, CLONES.with(Cell::get));
+        assert_eq!(4, package.unpackage().len());
+    }
+
+    #[test]
+    fn with_contents() {
+        let mut a = Mlsp::new(vec![1u32, 2, 3]);
+        let sum = a.with(|v| v.iter().sum::<u32>());
+        assert_eq!(6, sum);
+
+        // Nothing outlives the call, so the handle can still be mutated and shared
+        a.get_mut().unwrap().push(4);
+        let b = a.clone();
+        assert_eq!(4, b.with(Vec::len));
+    }
+
+    #[test]
+    fn accessors_agree() {
+        /// Every way of reaching the contents must produce the same reference, metadata included
+        fn assert_agree<T: ?Sized>(a: &Mlsp<T>) {
+            let as_ref: *const T = a.as_ref();
+            let borrow: *const T = Borrow::<T>::borrow(a);
+            let with: *const T = a.with(|data| data as *const T);
+            assert!(ptr::eq(as_ref, borrow));
+            assert!(ptr::eq(as_ref, with));
+            assert!(ptr::eq(as_ref, a.as_ptr()));
+            assert!(ptr::eq(as_ref, a.package().as_ptr()));
+        }
+
+        let a = Mlsp::new(String::from("a"));
+        assert_agree(&a);
+        assert_agree(&a.clone());
+        assert_agree(&a.package().unpackage());
+        assert_agree(&a.downgrade().upgrade().unwrap());
+        assert_agree(&Mlsp::<[u16]>::from(vec![1, 2, 3]));
+        assert_agree(&Mlsp::new(()));
+
+        // Handles without a local counter reach the contents the same way
+        let packages = a.package_n(HOT_THRESHOLD);
+        assert_agree(&packages.into_iter().next().unwrap().unpackage());
+    }
+
+    #[test]
+    fn task_migration() {
+        use std::sync::mpsc;
+        use std::thread;
+
+        // Two workers pass a task's state back and forth, as if it were stolen at each suspension
+        let (to_second, on_second) = mpsc::channel::<MlspPackage<Vec<u32>>>();
+        let (to_first, on_first) = mpsc::channel::<MlspPackage<Vec<u32>>>();
+
+        let second = thread::spawn(move || {
+            for suspended in on_second {
+                let mut state = suspended.bind();
+                state.make_mut().push(2);
+                let clone = state.clone();
+                assert!(clone.clone_is_free());
+                drop(clone);
+                to_first.send(state.rebind()).unwrap();
+            }
+        });
+
+        let mut state = Mlsp::new(vec![1]);
+        let allocation = state.as_ptr();
+        for _ in 0..3 {
+            to_second.send(state.rebind()).unwrap();
+            state = on_first.recv().unwrap().bind();
+            state.make_mut().push(1);
+        }
+        drop(to_second);
+        second.join().unwrap();
+
+        assert_eq!(&[1, 2, 1, 2, 1, 2, 1], &state.as_ref()[..]);
+        // The state stayed in one allocation and was never duplicated
+        assert_eq!(allocation, state.as_ptr());
+        assert!(state.is_unique());
+    }
+
+    #[test]
+    fn inline_sole_package() {
+        use std::thread;
+
+        let package = Mlsp::new(5u32).package();
+        let value = thread::spawn(move || package.try_inline().ok().unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(5, value);
+
+        // A package sharing its contents stays a package
+        let a = Mlsp::new(6u32);
+        let weak = a.downgrade();
+        let package = a.package();
+        let package = package.try_inline().err().unwrap();
+        drop(a);
+        assert_eq!(1, weak.strong_count());
+
+        // until it is the last reference, after which weak references no longer upgrade
+        assert_eq!(6, package.try_inline().ok().unwrap());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn clone_in_bulk() {
+        let a = Mlsp::new(1u8);
+        let b = a.clone();
+        let clones = a.clone_n(5);
+        assert_eq!(7, a.local_handles());
+        assert!(clones.iter().all(|c| c.local_count == a.local_count));
+
+        let weak = a.downgrade();
+        assert_eq!(1, weak.strong_count());
+        drop(clones);
+        assert_eq!(2, a.local_handles());
+        drop(b);
+        assert_eq!(1, a.local_handles());
+        assert!(a.clone_n(0).is_empty());
+
+        // Handles without a local counter take the clones from the atomic count
+        let packages = a.package_n(HOT_THRESHOLD);
+        let hot = packages.into_iter().next().unwrap().unpackage();
+        let before = weak.strong_count();
+        let clones = hot.clone_n(4);
+        assert_eq!(before + 4, weak.strong_count());
+        drop(clones);
+        assert_eq!(before, weak.strong_count());
+    }
+
+    #[test]
+    fn fan_out_comparison() {
+        use std::cmp::Ordering;
+
+        let wide = Mlsp::new(1u8);
+        let narrow = Mlsp::new(2u8);
+        assert_eq!(Ordering::Equal, wide.compare_counts(&narrow));
+
+        // Local clones don't fan out, packages and other threads' handles do
+        let clones = narrow.clone_n(8);
+        assert_eq!(Ordering::Equal, wide.compare_counts(&narrow));
+        let packages = wide.package_n(3);
+        let remote = narrow.package().unpackage();
+        assert_eq!(Ordering::Greater, wide.compare_counts(&narrow));
+        assert_eq!(Ordering::Less, narrow.compare_counts(&wide));
+        assert_eq!(Ordering::Equal, wide.compare_counts(&wide.clone()));
+
+        drop(packages);
+        assert_eq!(Ordering::Less, wide.compare_counts(&narrow));
+        drop((clones, remote));
+        assert_eq!(Ordering::Equal, wide.compare_counts(&narrow));
+    }
+
+    #[test]
+    fn conditional_unpackage() {
+        use std::thread;
+
+        let a = Mlsp::new(String::from("route me"));
+        let weak = a.downgrade();
+        let package = a.package();
+        assert_eq!(2, weak.strong_count());
+
+        // A rejected package is handed back with its share of the count intact
+        let package = package.unpackage_if(|s| s.is_empty()).err().unwrap();
+        assert_eq!(2, weak.strong_count());
+
+        let accepted = thread::spawn(move || {
+            let b = package
+                .unpackage_if(|s| s.starts_with("route"))
+                .ok()
+                .unwrap();
+            assert!(b.was_unpackaged());
+            b.len()
+        });
+        assert_eq!(8, accepted.join().unwrap());
+        assert_eq!(1, weak.strong_count());
+    }
+
+    #[test]
+    fn gather_handles() {
+        let handles: Vec<_> = [3u32, 1, 4, 1, 5].into_iter().map(Mlsp::new).collect();
+        let shared = handles[1].clone();
+        let weak = handles[0].downgrade();
+
+        let slice = Mlsp::<[u32]>::from_handles(&handles);
+        assert_eq!([3, 1, 4, 1, 5], slice.as_ref());
+        assert_eq!(14, slice.as_ref().iter().sum::<u32>());
+
+        // The originals keep their own allocations and counts
+        assert_eq!(1, weak.strong_count());
+        assert!(shared.as_ptr() == handles[1].as_ptr());
+        assert_eq!(
+            vec![3, 1, 4, 1, 5],
+            handles.iter().map(|a| *a.as_ref()).collect::<Vec<_>>()
+        );
+        assert!(Mlsp::<[u32]>::from_handles(&[]).as_ref().is_empty());
+    }
+
+    #[test]
+    fn locality_falls_with_sharing() {
+        use std::thread;
+
+        // Sharing within the thread keeps every reference local
+        let a = Mlsp::new(String::from("local"));
+        let clones = a.clone_n(7);
+        assert_eq!(1.0, a.locality_ratio());
+        drop(clones);
+        assert_eq!(1.0, a.locality_ratio());
+
+        // Each thread holding the value adds a reference this thread doesn't own
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(4));
+        let holders: Vec<_> = (0..3)
+            .map(|_| {
+                let package = a.package();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    let held = package.unpackage();
+                    barrier.wait();
+                    barrier.wait();
+                    drop(held);
+                })
+            })
+            .collect();
+        barrier.wait();
+        assert_eq!(0.25, a.locality_ratio());
+
+        barrier.wait();
+        for holder in holders {
+            holder.join().unwrap();
+        }
+        assert_eq!(1.0, a.locality_ratio());
+    }
+
+    #[test]
+    fn batches_into_one_buffer() {
+        let a = Mlsp::new(String::from("batched"));
+        let b = Mlsp::new(String::from("other"));
+        let mut batch = Vec::new();
+
+        // Batches append to what the buffer already holds
+        a.package_batch_into(&mut batch, 3);
+        b.package_batch_into(&mut batch, 2);
+        a.package_batch_into(&mut batch, 0);
+        assert_eq!(4, a.count_snapshot().atomic);
+        assert_eq!(3, b.count_snapshot().atomic);
+        let targets: Vec<_> = batch.iter().map(MlspPackage::as_ptr).collect();
+        assert_eq!(
+            vec![a.as_ptr(), a.as_ptr(), a.as_ptr(), b.as_ptr(), b.as_ptr()],
+            targets
+        );
+
+        // Draining the buffer releases the batch and keeps its allocation for the next one
+        let buffer = batch.as_ptr();
+        let capacity = batch.capacity();
+        for round in 1..=4 {
+            drop(batch.drain(..));
+            assert_eq!(1, a.count_snapshot().atomic);
+            a.package_batch_into(&mut batch, round);
+            assert_eq!(round, batch.len());
+            assert_eq!(1 + round, a.count_snapshot().atomic);
+            assert_eq!((buffer, capacity), (batch.as_ptr(), batch.capacity()));
+        }
+
+        drop(batch);
+        assert!(a.is_unique());
+        assert!(b.is_unique());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn confinement_is_checked() {
+        use std::thread;
+
+        /// A reference moved to another thread by unsafe code, as confinement guards against
+        struct Smuggled<'a>(&'a Mlsp<String>);
+        unsafe impl Send for Smuggled<'_> {}
+
+        let clone_elsewhere = |a: &Mlsp<String>| {
+            let smuggled = Smuggled(a);
+            thread::scope(|s| {
+                s.spawn(move || {
+                    let smuggled = smuggled;
+                    drop(smuggled.0.clone());
+                })
+                .join()
+                .is_ok()
+            })
+        };
+
+        let a = Mlsp::new(String::from("confined"));
+        let crossed = a.confined_scope(|a| {
+            // Handles are used freely on this thread, and nested scopes restore the outer one
+            let b = a.clone();
+            b.confined_scope(|b| drop(b.clone()));
+            drop(b);
+            clone_elsewhere(a)
+        });
+        assert!(!crossed);
+        // The clone panicked before touching the count
+        assert_eq!(1, a.local_handles());
+
+        // Once the scope has returned the counter is no longer confined
+        assert!(clone_elsewhere(&a));
+        assert!(a.is_unique());
+    }
+
+    #[test]
+    fn definitely_shared_within_the_thread() {
+        let a = Mlsp::new(1u8);
+        let clones = a.clone_n(3);
+
+        // Other handles on the counter answer without reading the atomic count
+        #[cfg(feature = "metrics")]
+        let guard = metrics::AtomicOpGuard::scope();
+        assert_eq!(4, a.local_handles());
+        assert!(a.is_definitely_shared());
+        assert!(clones.iter().all(Mlsp::is_definitely_shared));
+        #[cfg(feature = "metrics")]
+        assert_eq!(0, guard.atomic_loads());
+
+        // A lone handle falls back to reading the count, which a package has raised
+        drop(clones);
+        assert_eq!(1, a.local_handles());
+        assert!(!a.is_definitely_shared());
+        #[cfg(feature = "metrics")]
+        assert_eq!(1, guard.atomic_loads());
+        let package = a.package();
+        assert!(a.is_definitely_shared());
+        drop(package);
+        assert!(!a.is_definitely_shared());
+    }
+
+    #[test]
+    fn relations_between_handles() {
+        let a = Mlsp::new(vec![1, 2, 3]);
+
+        // Handles to one allocation are related by address, on any counter
+        let unpackaged = a.package().unpackage();
+        assert_eq!(Relation::SameAllocation, a.relation(&a.clone()));
+        assert_eq!(Relation::SameAllocation, unpackaged.relation(&a));
+
+        let copy = Mlsp::new(vec![1, 2, 3]);
+        assert_eq!(Relation::EqualContent, a.relation(&copy));
+        assert_eq!(Relation::EqualContent, copy.relation(&a));
+        assert_eq!(Relation::Different, a.relation(&Mlsp::new(vec![3, 2, 1])));
+
+        // The same allocation is recognized without comparing, even for contents unequal to themselves
+        let nan = Mlsp::new(f64::NAN);
+        assert_eq!(Relation::SameAllocation, nan.relation(&nan.clone()));
+        assert_eq!(Relation::Different, nan.relation(&Mlsp::new(f64::NAN)));
+    }
+
+    #[test]
+    fn snapshot_matches_accessors() {
+        use std::thread;
+
+        let a = Mlsp::new(vec![1u8]);
+        let weak = a.downgrade();
+        let clones = a.clone_n(3);
+        let packages = a.package_n(2);
+        let remote = packages[0].clone();
+        let held = thread::spawn(move || remote.unpackage().len());
+        assert_eq!(1, held.join().unwrap());
+
+        let snapshot = a.count_snapshot();
+        assert_eq!(
+            CountSnapshot {
+                local: 4,
+                atomic: 3,
+                weak: 1,
+            },
+            snapshot
+        );
+        assert_eq!(weak.strong_count(), snapshot.atomic);
+        assert_eq!(
+            Mlsp::<u8>::MAX_REFCOUNT - a.count_headroom(),
+            snapshot.atomic
+        );
+        assert_eq!(
+            "Mlsp { data: [1], local_count: 4, atomic_count: 3 }",
+            format!("{:?}", a)
+        );
+
+        // A handle unpackaged on its own counter sees the same allocation counts
+        drop(clones);
+        let b = packages.into_iter().next().unwrap().unpackage();
+        let weaker = weak.clone();
+        let snapshot = b.count_snapshot();
+        assert_eq!((1, 2, 2), (snapshot.local, snapshot.atomic, snapshot.weak));
+        assert_eq!(snapshot.atomic, a.count_snapshot().atomic);
+        drop(weaker);
+    }
+
+    #[test]
+    fn from_arrays() {
+        use std::cell::Cell;
+
+        let a = Mlsp::from([1u32, 2, 3]);
+        assert_eq!([1, 2, 3], a.as_ref());
+        let b = a.clone();
+        assert_eq!(6, b.as_ref().iter().sum::<u32>());
+        assert!(Mlsp::<[u8]>::from([]).as_ref().is_empty());
+
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+        struct Counted(String);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        // Elements are moved rather than cloned, and dropped once with the slice
+        let strings = Mlsp::from([Counted(String::from("a")), Counted(String::from("b"))]);
+        assert_eq!(0, DROPS.with(Cell::get));
+        assert_eq!(
+            vec!["a", "b"],
+            strings
+                .as_ref()
+                .iter()
+                .map(|c| c.0.as_str())
+                .collect::<Vec<_>>()
+        );
+        let package = strings.package();
+        drop(strings);
+        assert_eq!(0, DROPS.with(Cell::get));
+        drop(package);
+        assert_eq!(2, DROPS.with(Cell::get));
+    }
+
+    #[test]
+    fn free_clones() {
+        let a = Mlsp::new(1u8);
+        assert!(a.clone_is_free());
+        assert!(a.clone().clone_is_free());
+        assert!(a.package().unpackage().clone_is_free());
+
+        let packages = a.package_n(HOT_THRESHOLD);
+        assert!(a.is_hot());
+        assert!(a.clone_is_free());
+
+        // Handles of a hot allocation made after the transition clone atomically
+        let b = packages.into_iter().next().unwrap().unpackage();
+        assert!(!b.clone_is_free());
+        assert!(!b.clone().clone_is_free());
+        assert!(!a.downgrade().upgrade().unwrap().clone_is_free());
+    }
+
+    #[test]
+    fn hot_allocation() {
+        use std::thread;
+
+        let a = Mlsp::new(String::from("hot"));
+        let weak = a.downgrade();
+        let packages = a.package_n(HOT_THRESHOLD - 2);
+        assert!(!a.is_hot());
+
+        let package = a.package();
+        assert!(a.is_hot());
+        assert_eq!(HOT_THRESHOLD, weak.strong_count());
+
+        // Handles created after the transition count every clone atomically
+        let b = package.unpackage();
+        assert!(b.is_atomic_only());
+        let c = b.clone();
+        assert_eq!(HOT_THRESHOLD + 1, weak.strong_count());
+        drop(c);
+
+        // while existing handles keep their local counter
+        let a2 = a.clone();
+        assert_eq!(HOT_THRESHOLD, weak.strong_count());
+
+        let workers: Vec<_> = packages
+            .into_iter()
+            .map(|package| {
+                thread::spawn(move || {
+                    let handle = package.unpackage();
+                    let clones = vec![handle.clone(); 10];
+                    clones.iter().map(|c| c.len()).sum::<usize>()
+                })
+            })
+            .collect();
+        for worker in workers {
+            assert_eq!(30, worker.join().unwrap());
+        }
+        assert_eq!(2, weak.strong_count());
+
+        drop((a, a2));
+        assert_eq!(1, weak.strong_count());
+        drop(weak);
+        assert!(b.is_unique());
+        assert_eq!(Ok(String::from("hot")), b.try_unwrap().map_err(|_| ()));
+    }
+
+    #[test]
+    fn mutation_helpers_agree() {
+        use std::any::Any;
+
+        // Each case leaves `a` shared in a different way, or not at all
+        for name in ["unique", "local clone", "package", "weak"] {
+            // The testing feature sets the shared counts directly, without the references
+            // they stand for, and releases them at the end of the case
+            let (mut a, other): (_, Box<dyn Any>) = match name {
+                #[cfg(feature = "testing")]
+                "local clone" => (Mlsp::with_counts(1u32, 2, 1), Box::new(())),
+                #[cfg(feature = "testing")]
+                "package" => (Mlsp::with_counts(1u32, 1, 2), Box::new(())),
+                _ => {
+                    let a = Mlsp::new(1u32);
+                    let other: Box<dyn Any> = match name {
+                        "local clone" => Box::new(a.clone()),
+                        "package" => Box::new(a.package()),
+                        "weak" => Box::new(a.downgrade()),
+                        _ => Box::new(()),
+                    };
+                    (a, other)
+                }
+            };
+            let unique = a.is_unique();
+            assert_eq!(name == "unique", unique, "{}", name);
+
+            assert_eq!(unique, a.get_mut().is_some(), "{}", name);
+            assert_eq!(unique, a.try_get_mut().is_ok(), "{}", name);
+
+            // Keeps the seeded allocation reachable once `a` moves off it
+            #[cfg(feature = "testing")]
+            let seeded = matches!(name, "local clone" | "package").then(|| a.clone());
+
+            // Only a shared handle moves to a new allocation
+            let before = a.as_ptr();
+            *a.get_mut_or_clone() += 1;
+            assert_eq!(unique, a.as_ptr() == before, "{}", name);
+            *a.make_mut() += 1;
+            assert!(a.is_unique(), "{}", name);
+            assert_eq!(3, *a.as_ref());
+            #[cfg(feature = "testing")]
+            if let Some(seeded) = seeded {
+                testing::release(seeded);
+            }
+            drop(other);
+        }
+    }
+
+    #[test]
+    fn container_sizes() {
+        let shared_vec = Mlsp::new(Vec::<u8>::with_capacity(16));
+        assert_eq!(0, shared_vec.len());
+        assert!(shared_vec.is_empty());
+        assert!(shared_vec.capacity() >= 16);
+
+        let mut shared_vec = Mlsp::new(vec![1u8, 2, 3]);
+        let other = shared_vec.clone();
+        assert_eq!(3, other.len());
+        drop(other);
+        shared_vec.get_mut().unwrap().push(4);
+        assert_eq!(4, shared_vec.len());
+
+        let shared_string = Mlsp::new(String::from("héllo"));
+        assert_eq!(6, shared_string.len());
+        assert!(!shared_string.is_empty());
+        assert!(shared_string.capacity() >= 6);
+        assert!(Mlsp::new(String::new()).is_empty());
+    }
+
+    #[test]
+    fn shrink_when_unique() {
+        let mut vec = Vec::with_capacity(64);
+        vec.extend([1u32, 2, 3]);
+        let mut a = Mlsp::new(vec);
+
+        // A shared vector keeps its capacity
+        let b = a.clone();
+        assert!(!a.shrink_to_fit());
+        assert_eq!(64, b.capacity());
+        drop(b);
+
+        assert!(a.shrink_to_fit());
+        assert_eq!(3, a.capacity());
+        assert_eq!([1, 2, 3], a[..]);
+
+        let mut string = String::with_capacity(64);
+        string.push_str("mlsp");
+        let mut a = Mlsp::new(string);
+        let package = a.package();
+        assert!(!a.shrink_to_fit());
+        assert_eq!(64, a.capacity());
+        drop(package);
+        assert!(a.shrink_to_fit());
+        assert_eq!(4, a.capacity());
+    }
+
+    #[test]
+    fn shared_indexing() {
+        use std::thread;
+
+        let a = Mlsp::new((0..8u32).map(|i| i * i).collect::<Vec<_>>());
+        assert_eq!(9, a[3]);
+        assert_eq!([0, 1, 4], a[..3]);
+
+        // Every worker reads the same vector through its own handle
+        let sums: Vec<u32> = a
+            .package_n(2)
+            .into_iter()
+            .enumerate()
+            .map(|(i, package)| {
+                thread::spawn(move || {
+                    let b = package.unpackage();
+                    (i..b.as_ref().len()).step_by(2).map(|j| b[j]).sum()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        assert_eq!(vec![56, 84], sums);
+
+        // Mutable indexing goes through a uniqueness check first
+        let mut b = a.clone();
+        assert!(b.get_mut().is_none());
+        b.make_mut()[0] = 100;
+        assert_eq!(100, b[0]);
+        assert_eq!(0, a[0]);
+    }
+
+    #[test]
+    fn path_forwarding() {
+        use std::fs::{self, File};
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("mlsp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = Mlsp::new(dir.join("shared.txt"));
+        fs::write(&path, "shared path").unwrap();
+
+        // Every handle to the path can be opened directly
+        let other = path.clone();
+        let mut contents = String::new();
+        File::open(&other)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("shared path", contents);
+
+        let name = Mlsp::new(OsString::from("shared.txt"));
+        assert_eq!(Some(OsStr::new("shared.txt")), Path::new(&name).file_name());
+        assert!(dir.join(Path::new(&name)).is_file());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn str_forwarding() {
+        fn shout(text: impl AsRef<str>) -> String {
+            text.as_ref().to_uppercase()
+        }
+
+        let a = Mlsp::new(String::from("shared"));
+        assert_eq!("SHARED", shout(&a));
+        assert_eq!("SHARED", shout(a.clone()));
+        // The `String` itself is still reachable with the type spelled out
+        let string: &String = a.as_ref();
+        assert_eq!("shared", string);
+    }
+
+    #[test]
+    fn map_into_new_allocation() {
+        let a = Mlsp::new(42u32);
+        let weak = a.downgrade();
+        let b = a.clone();
+
+        let mapped = b.map_value(|n| n.to_string());
+        assert_eq!("42", AsRef::<str>::as_ref(&mapped));
+        assert!(mapped.is_unique());
+        assert_eq!(1, weak.strong_count());
+
+        // The original is released with its last handle
+        let mapped = a.map_value(|n| format!("{}!", n));
+        assert_eq!("42!", AsRef::<str>::as_ref(&mapped));
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn drop_order() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        /// Records the live local counters on this thread when dropped,
+        /// and uses handles from inside its drop
+        struct Hook {
+            child: Option<Mlsp<Hook>>,
+            seen: Rc<Cell<usize>>,
+            panic: bool,
+        }
+
+        impl Drop for Hook {
+            fn drop(&mut self) {
+                self.seen.set(canary::outstanding());
+                drop(Mlsp::new(0u8).clone());
+                drop(self.child.take());
+                if self.panic {
+                    panic!("hook panicked");
+                }
+            }
+        }
+
+        let before = canary::outstanding();
+        let leaf_seen = Rc::new(Cell::new(0));
+        let root_seen = Rc::new(Cell::new(0));
+        let leaf = Mlsp::new(Hook {
+            child: None,
+            seen: leaf_seen.clone(),
+            panic: false,
+        });
+        let root = Mlsp::new(Hook {
+            child: Some(leaf),
+            seen: root_seen.clone(),
+            panic: false,
+        });
+        drop(root.clone());
+        drop(root);
+
+        // Each counter was released before the contents it counted were dropped
+        assert_eq!(before + 1, root_seen.get());
+        assert_eq!(before, leaf_seen.get());
+        assert_eq!(before, canary::outstanding());
+
+        // A panicking drop leaves no counter behind
+        let panicking = Mlsp::new(Hook {
+            child: None,
+            seen: Rc::new(Cell::new(0)),
+            panic: true,
+        });
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(panicking))).is_err());
+        assert_eq!(before, canary::outstanding());
+    }
+
+    #[test]
+    fn independent_allocations() {
+        let mut handles = Mlsp::new_many(vec![0u8], 3);
+        assert_eq!(3, handles.len());
+        assert!(handles[0].as_ptr() != handles[1].as_ptr());
+        assert!(handles[1].as_ptr() != handles[2].as_ptr());
+
+        // Each handle is the only one for its allocation
+        let shared = handles[0].clone();
+        assert!(!handles[0].is_unique());
+        assert!(handles[1].is_unique() && handles[2].is_unique());
+
+        handles[1].get_mut().unwrap().push(1);
+        assert_eq!(&[0], &shared.as_ref()[..]);
+        assert_eq!(&[0, 1], &handles[1].as_ref()[..]);
+        assert_eq!(&[0], &handles[2].as_ref()[..]);
+
+        assert!(Mlsp::new_many(0u8, 0).is_empty());
+    }
+
+    #[test]
+    fn replace_unique() {
+        let mut a = Mlsp::new(String::from("old"));
+        assert_eq!(Ok(String::from("old")), a.replace(String::from("new")));
+        assert_eq!("new", AsRef::<str>::as_ref(&a));
+
+        let b = a.clone();
+        // The caller keeps the new value when the contents are shared
+        assert_eq!(Err(String::from("newer")), a.replace(String::from("newer")));
+        assert_eq!("new", AsRef::<str>::as_ref(&b));
+    }
+
+    #[test]
+    fn zero_sized() {
+        use std::thread;
+
+        // The data takes no space after the counters, so only the header is allocated
+        assert_eq!(
+            MlspInner::<()>::header_layout().pad_to_align(),
+            Layout::new::<MlspInner<()>>()
+        );
+
+        let a = Mlsp::new(());
+        let b = a.clone();
+        let package = b.package();
+        let weak = a.downgrade();
+        assert!(a.as_ptr() == package.as_ptr());
+        assert!(package.as_ptr().is_aligned());
+        drop(a);
+        drop(b);
+
+        let c = thread::spawn(move || package.unpackage().package())
+            .join()
+            .unwrap()
+            .unpackage();
+        assert_eq!((), *c.as_ref());
+        assert!(c.try_unwrap().is_ok());
+        assert!(weak.upgrade().is_none());
+
+        // Zero-sized values with drop glue are still dropped exactly once
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
         }
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+        let a = Mlsp::new(Counted);
+        drop(a.clone());
+        drop(a);
+        assert_eq!(1, DROPS.with(Cell::get));
+
+        let slice = Mlsp::<[()]>::from(vec![(); 3]);
+        assert_eq!(3, slice.clone().as_ref().len());
+        let empty = Mlsp::<[u8]>::from(Vec::new());
+        assert!(empty.as_ref().is_empty());
     }
 }
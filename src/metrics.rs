@@ -0,0 +1,231 @@
+//! Counters for the atomic operations performed by this crate.
+//!
+//! When the `metrics` feature is enabled every atomic read-modify-write
+//! on an allocation's counter is recorded in a per-thread counter,
+//! which lets benchmarks confirm how often the atomic path is taken.
+//! Loads of the atomic count by `Mlsp::is_definitely_shared` are counted separately,
+//! since that check exists to avoid them.
+//!
+//! Each allocation also records the highest atomic count it has reached, see `Mlsp::peak_count`.
+
+use std::cell::Cell;
+use std::sync::atomic::Ordering;
+
+use crate::Mlsp;
+
+thread_local! {
+    static ATOMIC_OPS: Cell<usize> = const { Cell::new(0) };
+    static ATOMIC_LOADS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Records one atomic read-modify-write on the current thread.
+pub(crate) fn record_atomic_op() {
+    ATOMIC_OPS.with(|ops| ops.set(ops.get() + 1));
+}
+
+/// The total number of atomic read-modify-writes performed on the current thread.
+pub fn atomic_ops() -> usize {
+    ATOMIC_OPS.with(Cell::get)
+}
+
+/// Records one atomic load of an allocation's count on the current thread.
+pub(crate) fn record_atomic_load() {
+    ATOMIC_LOADS.with(|loads| loads.set(loads.get() + 1));
+}
+
+/// The total number of recorded atomic loads of an allocation's count on the current thread.
+pub fn atomic_loads() -> usize {
+    ATOMIC_LOADS.with(Cell::get)
+}
+
+/// Counts the atomic read-modify-writes, and the recorded atomic loads,
+/// performed on the current thread while it is alive.
+/// ```
+/// # use mlsp::{metrics::AtomicOpGuard, Mlsp};
+/// let a = Mlsp::new(1u8);
+/// let guard = AtomicOpGuard::scope();
+/// let b = a.clone();
+/// assert_eq!(0, guard.atomic_ops());
+/// let package = a.package();
+/// assert_eq!(1, guard.atomic_ops());
+/// ```
+pub struct AtomicOpGuard {
+    start: usize,
+    start_loads: usize,
+}
+
+impl AtomicOpGuard {
+    /// Starts counting from the current number of atomic operations.
+    pub fn scope() -> Self {
+        AtomicOpGuard {
+            start: atomic_ops(),
+            start_loads: atomic_loads(),
+        }
+    }
+
+    /// The number of atomic read-modify-writes performed on this thread since the guard was created.
+    pub fn atomic_ops(&self) -> usize {
+        atomic_ops() - self.start
+    }
+
+    /// The number of recorded atomic loads performed on this thread since the guard was created.
+    pub fn atomic_loads(&self) -> usize {
+        atomic_loads() - self.start_loads
+    }
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// The highest the atomic count of this allocation has been,
+    /// counting one for each thread holding handles and each package, as with `package_n_bounded`.
+    ///
+    /// This is the peak fan-out of the value, which stays recorded after the handles are dropped.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// drop(a.package_n(3));
+    /// assert_eq!(4, a.peak_count());
+    /// ```
+    pub fn peak_count(&self) -> usize {
+        // SAFETY: The existence of this Mlsp keeps the inner alive
+        unsafe { self.inner_ptr.as_ref() }
+            .peak_count
+            .load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_clones_are_atomic_free() {
+        let a = Mlsp::new(1u8);
+
+        let guard = AtomicOpGuard::scope();
+        let clones: Vec<_> = (0..1000).map(|_| a.clone()).collect();
+        drop(clones);
+        assert_eq!(0, guard.atomic_ops());
+    }
+
+    #[test]
+    fn packages_are_atomic() {
+        let a = Mlsp::new(1u8);
+
+        let guard = AtomicOpGuard::scope();
+        let packages: Vec<_> = (0..1000).map(|_| a.package()).collect();
+        assert_eq!(1000, guard.atomic_ops());
+
+        // Each dropped package decrements the atomic counter
+        drop(packages);
+        assert_eq!(2000, guard.atomic_ops());
+    }
+
+    #[test]
+    fn package_clones() {
+        let a = Mlsp::new(1u8);
+        let package = a.package();
+
+        let guard = AtomicOpGuard::scope();
+        let clones: Vec<_> = (0..10).map(|_| package.clone_for_send()).collect();
+        assert_eq!(10, guard.atomic_ops());
+        drop(clones);
+
+        let guard = AtomicOpGuard::scope();
+        let clones = package.clone_n(10);
+        let packages = a.package_n(10);
+        assert_eq!(2, guard.atomic_ops());
+        assert_eq!(10, clones.len());
+        assert_eq!(10, packages.len());
+        assert!(a.package_n(0).is_empty());
+        assert_eq!(2, guard.atomic_ops());
+
+        let mut batch = packages;
+        a.package_batch_into(&mut batch, 10);
+        a.package_batch_into(&mut batch, 0);
+        assert_eq!(3, guard.atomic_ops());
+        assert_eq!(20, batch.len());
+    }
+
+    #[test]
+    fn read_and_forward_is_one_op() {
+        let a = Mlsp::new(String::from("forward"));
+
+        let guard = AtomicOpGuard::scope();
+        let (len, package) = a.read_and_forward(|s| s.len());
+        assert_eq!(7, len);
+        assert_eq!(1, guard.atomic_ops());
+        assert_eq!("forward", AsRef::<str>::as_ref(&package.unpackage()));
+    }
+
+    #[test]
+    fn batched_package_drops() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+
+        use crate::MlspPackage;
+
+        struct Dropped(Arc<AtomicUsize>);
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops: Vec<_> = (0..3).map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        let kept = Mlsp::new(Dropped(drops[0].clone()));
+        let mut mailbox = kept.package_n(5);
+        let b = Mlsp::new(Dropped(drops[1].clone()));
+        mailbox.extend(b.package_n(3));
+        drop(b);
+        mailbox.push(MlspPackage::from_value(Dropped(drops[2].clone())));
+        mailbox.extend(kept.package_n(2));
+
+        // One decrement for each allocation, and one more to free each allocation released
+        let guard = AtomicOpGuard::scope();
+        MlspPackage::drop_batch(mailbox);
+        assert_eq!(5, guard.atomic_ops());
+
+        let dropped: Vec<_> = drops.iter().map(|d| d.load(Ordering::Relaxed)).collect();
+        assert_eq!(vec![0, 1, 1], dropped);
+        assert!(kept.is_unique());
+        drop(kept);
+        assert_eq!(1, drops[0].load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn reservations() {
+        let a = Mlsp::new(1u8);
+
+        for n in [1, 10, 1000] {
+            let guard = AtomicOpGuard::scope();
+            let mut reservation = a.reserve(n);
+            let packages: Vec<_> = (0..n / 2).map(|_| reservation.take().unwrap()).collect();
+            assert_eq!(1, guard.atomic_ops());
+
+            // Returning the rest of the reservation is a single operation as well
+            let guard = AtomicOpGuard::scope();
+            drop(reservation);
+            assert_eq!(usize::from(n / 2 < n), guard.atomic_ops());
+            drop(packages);
+        }
+    }
+
+    #[test]
+    fn peak_is_retained() {
+        let a = Mlsp::new(1u8);
+        let _local: Vec<_> = (0..10).map(|_| a.clone()).collect();
+        assert_eq!(1, a.peak_count());
+
+        let packages = a.package_n(5);
+        let weak = a.downgrade();
+        let upgraded = weak.upgrade_to_package().unwrap();
+        assert_eq!(7, a.peak_count());
+
+        drop(packages);
+        drop(upgraded);
+        assert_eq!(7, a.peak_count());
+
+        // Falling back below the peak and rising again leaves it unchanged
+        drop(a.package_n(2));
+        assert_eq!(7, a.peak_count());
+    }
+}
@@ -0,0 +1,150 @@
+use std::borrow::Borrow;
+use std::sync::{LockResult, Mutex, MutexGuard, TryLockResult};
+
+use crate::{Mlsp, MlspPackage};
+
+/// An Mlsp whose contents are protected by a `Mutex`,
+/// allowing any holder to temporarily gain exclusive access.
+///
+/// Like `Mlsp` it cannot be sent between threads,
+/// use `package()` to share it with another thread.
+/// ```
+/// # use std::thread;
+/// let counter = mlsp::MlspMutex::new(0u32);
+/// let counter_pkg = counter.package();
+/// thread::spawn(move || {
+///     *counter_pkg.unpackage().lock().unwrap() += 1;
+/// })
+/// .join()
+/// .unwrap();
+/// assert_eq!(1, *counter.lock().unwrap());
+/// ```
+pub struct MlspMutex<T> {
+    inner: Mlsp<Mutex<T>>,
+}
+
+impl<T> MlspMutex<T> {
+    /// Creates a new MlspMutex wrapping the given value.
+    pub fn new(data: T) -> Self {
+        MlspMutex {
+            inner: Mlsp::new(Mutex::new(data)),
+        }
+    }
+
+    /// Blocks until the lock is acquired.
+    ///
+    /// Poisoning behaves exactly as it does for `Mutex::lock`.
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        let mutex: &Mutex<T> = self.inner.borrow();
+        mutex.lock()
+    }
+
+    /// Attempts to acquire the lock without blocking.
+    ///
+    /// Poisoning behaves exactly as it does for `Mutex::try_lock`.
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+        let mutex: &Mutex<T> = self.inner.borrow();
+        mutex.try_lock()
+    }
+
+    /// Returns true if a holder of the lock panicked while holding it.
+    pub fn is_poisoned(&self) -> bool {
+        let mutex: &Mutex<T> = self.inner.borrow();
+        mutex.is_poisoned()
+    }
+
+    /// Create a Send-able package from the MlspMutex
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> MlspMutexPackage<T> {
+        MlspMutexPackage {
+            inner: self.inner.package(),
+        }
+    }
+}
+
+impl<T> Clone for MlspMutex<T> {
+    fn clone(&self) -> Self {
+        MlspMutex {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A reference to the contents of an MlspMutex
+/// that can be sent across threads.
+pub struct MlspMutexPackage<T> {
+    inner: MlspPackage<Mutex<T>>,
+}
+
+impl<T> MlspMutexPackage<T> {
+    /// Turns this package into a normal MlspMutex that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> MlspMutex<T> {
+        MlspMutex {
+            inner: self.inner.unpackage(),
+        }
+    }
+}
+
+impl<T> Clone for MlspMutexPackage<T> {
+    fn clone(&self) -> Self {
+        MlspMutexPackage {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_thread_counter() {
+        use std::thread;
+
+        let counter = MlspMutex::new(0u32);
+
+        let mut children = vec![];
+
+        for _ in 0..10 {
+            let package = counter.package();
+
+            children.push(thread::spawn(move || {
+                let counter = package.unpackage();
+                for _ in 0..100 {
+                    *counter.lock().unwrap() += 1;
+                }
+            }));
+        }
+
+        for child in children {
+            child.join().unwrap();
+        }
+
+        assert_eq!(1000, *counter.lock().unwrap());
+    }
+
+    #[test]
+    fn poisoning() {
+        use std::thread;
+
+        let counter = MlspMutex::new(0u32);
+        let package = counter.package();
+
+        let result = thread::spawn(move || {
+            let counter = package.unpackage();
+            let _guard = counter.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        assert!(result.is_err());
+        assert!(counter.is_poisoned());
+        assert!(counter.lock().is_err());
+        assert!(counter.try_lock().is_err());
+
+        // The data is still reachable through the poison error
+        assert_eq!(0, *counter.lock().unwrap_err().into_inner());
+    }
+}
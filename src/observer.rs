@@ -0,0 +1,124 @@
+use std::cell::Cell;
+
+use crate::{Mlsp, MlspWeak};
+
+/// A weak reference that subscribers on other threads can hold to a published value,
+/// which lets them reach it while it exists without keeping it alive.
+///
+/// An observer remembers when the value was last found to be gone,
+/// so once the publisher has dropped it every later `peek` returns `None`
+/// without touching the shared counters again.
+/// ```
+/// let a = mlsp::Mlsp::new(String::from("published"));
+/// let observer = a.observer();
+///
+/// let subscriber = std::thread::spawn(move || {
+///     let current = observer.peek().map(|value| AsRef::<String>::as_ref(&value).clone());
+///     (current, observer)
+/// });
+/// let (current, observer) = subscriber.join().unwrap();
+/// assert_eq!(Some("published"), current.as_deref());
+///
+/// drop(a);
+/// assert!(observer.peek().is_none());
+/// ```
+pub struct MlspObserver<T: ?Sized> {
+    weak: MlspWeak<T>,
+    gone: Cell<bool>,
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Creates an observer of the contents, which can be sent to subscribers on other threads.
+    ///
+    /// This increments the weak_count
+    pub fn observer(&self) -> MlspObserver<T> {
+        MlspObserver {
+            weak: self.downgrade(),
+            gone: Cell::new(false),
+        }
+    }
+}
+
+impl<T: ?Sized> MlspObserver<T> {
+    /// Returns a handle to the value if it still exists.
+    ///
+    /// This upgrades the weak reference, unless the value is already known to be gone.
+    pub fn peek(&self) -> Option<Mlsp<T>> {
+        if self.gone.get() {
+            return None;
+        }
+
+        let value = self.weak.upgrade();
+        self.gone.set(value.is_none());
+        value
+    }
+
+    /// Whether the value still exists, without taking a handle to it.
+    ///
+    /// The value may be dropped right after this returns `true`,
+    /// but once it returns `false` it always will.
+    pub fn is_alive(&self) -> bool {
+        if !self.gone.get() && self.weak.strong_count() == 0 {
+            self.gone.set(true);
+        }
+        !self.gone.get()
+    }
+}
+
+impl<T: ?Sized> Clone for MlspObserver<T> {
+    fn clone(&self) -> Self {
+        MlspObserver {
+            weak: self.weak.clone(),
+            gone: self.gone.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn peeks_after_the_value_is_dropped() {
+        let a = Mlsp::new(vec![1, 2, 3]);
+        let (send, recv) = mpsc::channel();
+        let (done, finished) = mpsc::channel();
+
+        let subscriber = thread::spawn(move || {
+            let observer: MlspObserver<Vec<i32>> = recv.recv().unwrap();
+            assert_eq!(3, observer.peek().unwrap().as_ref().len());
+            done.send(()).unwrap();
+
+            // Wait for the publisher to drop its value
+            assert!(recv.recv().is_err());
+            assert!(!observer.is_alive());
+            assert!(observer.peek().is_none());
+            assert!(observer.peek().is_none());
+        });
+
+        send.send(a.observer()).unwrap();
+        finished.recv().unwrap();
+
+        // An observer doesn't keep the value alive
+        let weak = a.downgrade();
+        drop(a);
+        assert_eq!(0, weak.strong_count());
+        drop(send);
+        subscriber.join().unwrap();
+    }
+
+    #[test]
+    fn clones_peek_independently() {
+        let a = Mlsp::new(1u8);
+        let observer = a.observer();
+        let clone = observer.clone();
+        assert!(observer.is_alive());
+
+        drop(a);
+        assert!(clone.peek().is_none());
+        assert!(!observer.is_alive());
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use crate::Mlsp;
+
+impl<T: ?Sized + Send + Sync> Mlsp<T> {
+    /// Spawns one task per thread of the current rayon pool into `scope`,
+    /// each calling `f` with its own handle to the contents.
+    ///
+    /// The number of tasks is `rayon::current_num_threads()` as seen by the calling thread,
+    /// which is the global pool's size when called from outside any pool.
+    ///
+    /// The packages for every task are created with a single atomic increment,
+    /// and each task unpackages its own so that clones within `f` stay thread-local.
+    /// The tasks share `f` through an `Arc`, as with `Mlsp::scatter`,
+    /// which costs one more atomic increment for each task.
+    ///
+    /// Since an Mlsp cannot be moved to another thread, the scope must be created on this thread
+    /// with `rayon::in_place_scope` or `ThreadPool::in_place_scope`.
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// let data = mlsp::Mlsp::new(vec![1u32, 2, 3]);
+    /// let tasks = AtomicUsize::new(0);
+    /// rayon::in_place_scope(|s| {
+    ///     data.par_share(s, |data| {
+    ///         assert_eq!(6, data.as_ref().iter().sum::<u32>());
+    ///         tasks.fetch_add(1, Ordering::Relaxed);
+    ///     });
+    /// });
+    /// assert_eq!(rayon::current_num_threads(), tasks.into_inner());
+    /// ```
+    pub fn par_share<'scope, F>(&self, scope: &rayon::Scope<'scope>, f: F)
+    where
+        T: 'scope,
+        F: Fn(Mlsp<T>) + Send + Sync + 'scope,
+    {
+        // The tasks share one closure, which may outlive this call but not the scope
+        let f = Arc::new(f);
+
+        for package in self.package_n(rayon::current_num_threads()) {
+            let f = f.clone();
+            scope.spawn(move |_| f(package.unpackage()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn parallel_sum() {
+        let data = Mlsp::new((1..=100).collect::<Vec<u32>>());
+        let weak = data.downgrade();
+        let total = AtomicU32::new(0);
+
+        rayon::in_place_scope(|s| {
+            data.par_share(s, |data| {
+                let sum: u32 = data.as_ref().iter().sum();
+                total.fetch_add(sum, Ordering::Relaxed);
+            });
+        });
+
+        let tasks = rayon::current_num_threads() as u32;
+        assert_eq!(tasks * 5050, total.into_inner());
+
+        // Every task's package was released when the scope ended
+        assert_eq!(1, weak.strong_count());
+        drop(weak);
+        assert!(data.is_unique());
+    }
+}
@@ -0,0 +1,105 @@
+use std::mem::{self, ManuallyDrop};
+
+use bytemuck::Pod;
+
+use crate::{Mlsp, MlspInner};
+
+impl Mlsp<[u8]> {
+    /// Reinterprets the bytes as a `T` in place, keeping the same allocation and counts,
+    /// enabled by the `bytemuck` feature.
+    ///
+    /// This suits buffers received as bytes and shared across threads once decoded,
+    /// since no copy is made and every handle to the bytes keeps the `T` alive.
+    /// Returns the handle back if the length of the slice is not the size of `T`,
+    /// or if the alignment of `T` would place it at another offset in the allocation than
+    /// the bytes, which start right after the counters rather than at an aligned position.
+    /// Types with an alignment of one, such as byte arrays and structs of them
+    /// in the style of wire-format headers, always match.
+    /// ```
+    /// let bytes = mlsp::Mlsp::<[u8]>::from(vec![1, 0, 2]);
+    /// let header = bytes.try_cast::<[u8; 3]>().ok().unwrap();
+    /// assert_eq!(2, header.as_ref()[2]);
+    /// ```
+    pub fn try_cast<T: Pod>(self) -> Result<Mlsp<T>, Mlsp<[u8]>> {
+        let align = mem::align_of::<T>();
+        if self.as_ref().len() != mem::size_of::<T>()
+            || MlspInner::<T>::data_offset(align) != MlspInner::<[u8]>::data_offset(1)
+            || align > MlspInner::<[u8]>::header_layout().align()
+        {
+            return Err(self);
+        }
+
+        // With the data at the same offset and no greater alignment than the header,
+        // the inner of a `T` has the same layout as the inner of the slice,
+        // so the allocation is freed in the same way. Any bit pattern is a valid `T`.
+        let this = ManuallyDrop::new(self);
+        Ok(Mlsp {
+            local_count: this.local_count,
+            inner_ptr: this.inner_ptr.cast::<MlspInner<T>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    /// A record of bytes, as a wire-format header would be
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Record {
+        kind: u8,
+        id: [u8; 4],
+        flags: [u8; 3],
+    }
+
+    // SAFETY: Byte fields with no padding
+    unsafe impl bytemuck::Zeroable for Record {}
+    unsafe impl Pod for Record {}
+
+    /// A value needing more alignment than the counters of an inner
+    #[derive(Clone, Copy)]
+    #[repr(C, align(64))]
+    struct Aligned([u8; 64]);
+
+    // SAFETY: A byte array with no padding, aligned further
+    unsafe impl bytemuck::Zeroable for Aligned {}
+    unsafe impl Pod for Aligned {}
+
+    #[test]
+    fn matching_sizes() {
+        let bytes = Mlsp::<[u8]>::from(vec![7, 1, 2, 3, 4, 0, 0, 9]);
+        let other = bytes.clone();
+        let weak = bytes.downgrade();
+
+        let record = bytes.try_cast::<Record>().ok().unwrap();
+        assert_eq!(7, record.as_ref().kind);
+        assert_eq!([1, 2, 3, 4], record.as_ref().id);
+        assert_eq!([0, 0, 9], record.as_ref().flags);
+        assert_eq!(2, record.local_handles());
+        assert!(std::ptr::addr_eq(other.as_ptr(), record.as_ptr()));
+
+        // The reinterpreted handle is shared like any other
+        let package = record.package();
+        thread::spawn(move || assert_eq!(9, package.unpackage().as_ref().flags[2]))
+            .join()
+            .unwrap();
+
+        drop(other);
+        drop(record);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn mismatches_are_refused() {
+        let bytes = Mlsp::<[u8]>::from(vec![0; 3]);
+        let bytes = bytes.try_cast::<Record>().err().unwrap();
+        assert_eq!(3, bytes.as_ref().len());
+
+        // The size matches, but the allocation is not aligned for it
+        let bytes = Mlsp::<[u8]>::from(vec![0; 64]);
+        assert!(bytes.try_cast::<Aligned>().is_err());
+    }
+}
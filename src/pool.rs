@@ -0,0 +1,452 @@
+//! Thread-local free lists of inner allocations, used by `Mlsp::new_pooled`.
+//!
+//! A pool for a layout is created on a thread by the first `new_pooled` of that layout there.
+//! From then on every inner of that layout freed on the thread is kept for reuse,
+//! up to `MAX_SLOTS`, instead of being returned to the global allocator.
+//! The slots of a pool are freed when its thread exits.
+//!
+//! `stats` reports how well the pools of the current thread are serving its `new_pooled` calls.
+//!
+//! `prewarm` fills a separate pool of local counters, which every handle created on the thread
+//! can take its counter from, so that a latency-sensitive thread can do its allocation up front.
+
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::ptr::{self, NonNull};
+#[cfg(debug_assertions)]
+use std::sync::atomic::AtomicU64;
+use std::thread::LocalKey;
+
+#[cfg(debug_assertions)]
+use crate::checks;
+use crate::{LocalCounter, Mlsp, MlspInner};
+
+/// The most free slots kept for each layout on each thread
+const MAX_SLOTS: usize = 64;
+
+thread_local! {
+    static POOLS: Pools = const { Pools(RefCell::new(Vec::new())) };
+    /// The hits and misses of `take` on this thread since the last `reset_stats`
+    static TAKES: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    /// The hits and misses of `take_counter` on this thread since the last `reset_stats`
+    static COUNTER_TAKES: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    static COUNTERS: Counters = const { Counters(RefCell::new(None)) };
+}
+
+/// How the pools of one thread have served its calls to `Mlsp::new_pooled`,
+/// and the local counters of the handles it created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Calls that reused a free slot.
+    pub hits: usize,
+    /// Calls that allocated from the global allocator because no slot was free.
+    pub misses: usize,
+    /// The free slots currently kept by the thread, across all layouts.
+    pub current_size: usize,
+    /// Handles that took their local counter from the pool filled by `prewarm`.
+    pub counter_hits: usize,
+    /// Handles that allocated their local counter because the pool had none free,
+    /// or the thread never called `prewarm`.
+    pub counter_misses: usize,
+    /// The free local counters currently kept by the thread.
+    pub counter_size: usize,
+}
+
+impl PoolStats {
+    /// The fraction of calls that reused a slot, or zero if there were none.
+    pub fn hit_ratio(&self) -> f64 {
+        ratio(self.hits, self.misses)
+    }
+
+    /// The fraction of local counters taken from the pool, or zero if none were needed.
+    pub fn counter_hit_ratio(&self) -> f64 {
+        ratio(self.counter_hits, self.counter_misses)
+    }
+}
+
+fn ratio(hits: usize, misses: usize) -> f64 {
+    match hits + misses {
+        0 => 0.0,
+        total => hits as f64 / total as f64,
+    }
+}
+
+/// Reports the pool hits and misses of the current thread since its last `reset_stats`,
+/// along with the number of slots and local counters its pools hold now.
+/// ```
+/// let a = mlsp::Mlsp::new_pooled(1u8);
+/// drop(a);
+/// let b = mlsp::Mlsp::new_pooled(2u8);
+///
+/// let stats = mlsp::pool::stats();
+/// assert_eq!((1, 1), (stats.hits, stats.misses));
+/// // Without `prewarm` every handle allocates its counter
+/// assert_eq!((0, 2), (stats.counter_hits, stats.counter_misses));
+/// ```
+pub fn stats() -> PoolStats {
+    let (hits, misses) = TAKES.with(Cell::get);
+    let (counter_hits, counter_misses) = COUNTER_TAKES.with(Cell::get);
+    let current_size = POOLS
+        .try_with(|pools| pools.0.borrow().iter().map(|(_, slots)| slots.len()).sum())
+        .unwrap_or(0);
+    let counter_size = COUNTERS
+        .try_with(|counters| counters.0.borrow().as_ref().map_or(0, Vec::len))
+        .unwrap_or(0);
+    PoolStats {
+        hits,
+        misses,
+        current_size,
+        counter_hits,
+        counter_misses,
+        counter_size,
+    }
+}
+
+/// Resets the hits and misses reported by `stats` for the current thread.
+pub fn reset_stats() {
+    TAKES.with(|takes| takes.set((0, 0)));
+    COUNTER_TAKES.with(|takes| takes.set((0, 0)));
+}
+
+/// Counts a hit or miss of a pool in `takes`, unless the thread is exiting
+fn record(takes: &'static LocalKey<Cell<(usize, usize)>>, hit: bool) {
+    let _ = takes.try_with(|takes| {
+        let (hits, misses) = takes.get();
+        takes.set(if hit {
+            (hits + 1, misses)
+        } else {
+            (hits, misses + 1)
+        });
+    });
+}
+
+/// Free slots by layout, each allocated from the global allocator with that layout.
+///
+/// A thread only uses a handful of layouts, so a linear search beats hashing them.
+struct Pools(RefCell<Vec<(Layout, Vec<NonNull<u8>>)>>);
+
+impl Pools {
+    fn slots(&self, layout: Layout) -> Option<usize> {
+        self.0.borrow().iter().position(|(l, _)| *l == layout)
+    }
+}
+
+impl Drop for Pools {
+    fn drop(&mut self) {
+        for (layout, slots) in self.0.get_mut().drain(..) {
+            for slot in slots {
+                // SAFETY: Every slot was allocated with the layout it is stored under
+                unsafe { alloc::dealloc(slot.as_ptr(), layout) };
+            }
+        }
+    }
+}
+
+/// Free local counters, each allocated as a `Box<LocalCounter>`,
+/// or `None` until the thread calls `prewarm`.
+struct Counters(RefCell<Option<Vec<NonNull<LocalCounter>>>>);
+
+impl Drop for Counters {
+    fn drop(&mut self) {
+        for counter in self.0.get_mut().take().into_iter().flatten() {
+            // SAFETY: Every kept counter was allocated as a box and nothing references it
+            drop(unsafe { Box::from_raw(counter.as_ptr()) });
+        }
+    }
+}
+
+/// Allocates `n` local counters into the current thread's pool of them, up to `MAX_SLOTS`,
+/// so that the next `n` handles created on the thread, by `Mlsp::new`, `unpackage`
+/// or any other way, take a counter without allocating.
+///
+/// From then on the thread also keeps the counters its handles free, up to the same limit,
+/// so steady-state creation and dropping of handles on it doesn't allocate counters at all.
+/// ```
+/// mlsp::pool::prewarm(16);
+/// let a = mlsp::Mlsp::new(1u8);
+/// let package = a.package();
+/// // Both handles took their counters from the pool
+/// let b = package.unpackage();
+/// ```
+pub fn prewarm(n: usize) {
+    let _ = COUNTERS.try_with(|counters| {
+        let mut counters = counters.0.borrow_mut();
+        let free = counters.get_or_insert_with(|| Vec::with_capacity(MAX_SLOTS));
+        let target = free.len().saturating_add(n).min(MAX_SLOTS);
+        while free.len() < target {
+            let counter = Box::new(LocalCounter {
+                count: Cell::new(0),
+                unpackaged: false,
+                #[cfg(debug_assertions)]
+                sentinel: 0,
+                #[cfg(debug_assertions)]
+                confined: AtomicU64::new(checks::UNCONFINED),
+            });
+            // SAFETY: Box::into_raw returns a non-null pointer
+            free.push(unsafe { NonNull::new_unchecked(Box::into_raw(counter)) });
+        }
+    });
+}
+
+/// Takes a free local counter, if this thread keeps any.
+pub(crate) fn take_counter() -> Option<NonNull<LocalCounter>> {
+    let counter = COUNTERS
+        .try_with(|counters| counters.0.borrow_mut().as_mut()?.pop())
+        .ok()
+        .flatten();
+
+    record(&COUNTER_TAKES, counter.is_some());
+    counter
+}
+
+/// Offers a freed local counter to this thread's pool of them.
+///
+/// Returns false if it was not kept, in which case the caller must free it.
+///
+/// # Safety
+/// The counter must have been allocated as a `Box<LocalCounter>`,
+/// and must not be used again by the caller if this returns true.
+pub(crate) unsafe fn give_counter(counter: NonNull<LocalCounter>) -> bool {
+    COUNTERS
+        .try_with(|counters| match counters.0.borrow_mut().as_mut() {
+            Some(free) if free.len() < MAX_SLOTS => {
+                free.push(counter);
+                true
+            }
+            _ => false,
+        })
+        .unwrap_or(false)
+}
+
+/// Takes a free slot for `layout`, creating the pool for it if there is none yet.
+fn take(layout: Layout) -> Option<NonNull<u8>> {
+    let slot = POOLS
+        .try_with(|pools| match pools.slots(layout) {
+            Some(i) => pools.0.borrow_mut()[i].1.pop(),
+            None => {
+                pools
+                    .0
+                    .borrow_mut()
+                    .push((layout, Vec::with_capacity(MAX_SLOTS)));
+                None
+            }
+        })
+        .ok()
+        .flatten();
+
+    record(&TAKES, slot.is_some());
+    slot
+}
+
+/// Offers a freed allocation to this thread's pool for its layout.
+///
+/// Returns false if it was not kept, in which case the caller must deallocate it.
+///
+/// # Safety
+/// The allocation must have been made by the global allocator with `layout`
+/// and must not be used again by the caller if this returns true.
+pub(crate) unsafe fn give(slot: NonNull<u8>, layout: Layout) -> bool {
+    POOLS
+        .try_with(|pools| {
+            let Some(i) = pools.slots(layout) else {
+                return false;
+            };
+            let slots = &mut pools.0.borrow_mut()[i].1;
+            if slots.len() < MAX_SLOTS {
+                slots.push(slot);
+                true
+            } else {
+                false
+            }
+        })
+        .unwrap_or(false)
+}
+
+impl<T> Mlsp<T> {
+    /// Creates a new Mlsp, reusing an allocation freed earlier on this thread if one is available.
+    ///
+    /// After the first call for a given `T` on a thread, the inners of that size and alignment
+    /// freed on the thread are kept for later calls instead of being returned to the allocator.
+    /// This avoids the global allocator for workloads that repeatedly create and drop
+    /// short-lived values, at the cost of holding on to a few slots until the thread exits.
+    /// ```
+    /// let a = mlsp::Mlsp::new_pooled(1u64);
+    /// let slot = a.as_ptr();
+    /// drop(a);
+    ///
+    /// let b = mlsp::Mlsp::new_pooled(2u64);
+    /// assert_eq!(slot, b.as_ptr());
+    /// assert_eq!(2, *b.as_ref());
+    /// ```
+    pub fn new_pooled(data: T) -> Mlsp<T> {
+        let inner_ptr = match take(Layout::new::<MlspInner<T>>()) {
+            Some(slot) => {
+                let inner_ptr = slot.cast::<MlspInner<T>>();
+                // SAFETY: The slot is a free allocation with the layout of an MlspInner<T>
+                unsafe { ptr::write(inner_ptr.as_ptr(), MlspInner::new(data)) };
+                inner_ptr
+            }
+            None => {
+                let inner = Box::new(MlspInner::new(data));
+                // SAFETY: Box::into_raw returns a non-null pointer
+                unsafe { NonNull::new_unchecked(Box::into_raw(inner)) }
+            }
+        };
+
+        Mlsp::from_inner(inner_ptr, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::thread;
+
+    fn free_counters() -> usize {
+        COUNTERS.with(|counters| counters.0.borrow().as_ref().map_or(0, Vec::len))
+    }
+
+    fn free_slots<T>() -> usize {
+        POOLS.with(|pools| {
+            pools
+                .slots(Layout::new::<MlspInner<T>>())
+                .map_or(0, |i| pools.0.borrow()[i].1.len())
+        })
+    }
+
+    /// Counts how many times values have been dropped
+    struct Dropped(Rc<Cell<usize>>, &'static str);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn slots_are_reused() {
+        let drops = Rc::new(Cell::new(0));
+
+        let a = Mlsp::new_pooled(Dropped(drops.clone(), "first"));
+        let slot = a.as_ptr() as *const ();
+        let b = a.clone();
+        drop(a);
+        drop(b);
+        assert_eq!(1, drops.get());
+        assert_eq!(1, free_slots::<Dropped>());
+
+        // The slot holds the new value, and the old one is not dropped again
+        let c = Mlsp::new_pooled(Dropped(drops.clone(), "second"));
+        assert_eq!(slot, c.as_ptr() as *const ());
+        assert_eq!("second", c.as_ref().1);
+        assert_eq!(0, free_slots::<Dropped>());
+        drop(c);
+        assert_eq!(2, drops.get());
+    }
+
+    #[test]
+    fn pools_are_bounded() {
+        // Plain allocations are only recycled once a pool exists for their layout
+        drop(Mlsp::new(1u32));
+        assert_eq!(0, free_slots::<u32>());
+
+        drop(Mlsp::new_pooled(1u32));
+        let handles: Vec<_> = (0..2 * MAX_SLOTS as u32).map(Mlsp::new).collect();
+        drop(handles);
+        assert_eq!(MAX_SLOTS, free_slots::<u32>());
+    }
+
+    #[test]
+    fn churn_hits_the_pool() {
+        reset_stats();
+        for i in 0..1000u64 {
+            let a = Mlsp::new_pooled(i);
+            let b = a.clone();
+            drop(a);
+            drop(b);
+        }
+
+        // Only the first call allocates, every later one reuses the slot it freed
+        let stats = stats();
+        assert_eq!(999, stats.hits);
+        assert_eq!(1, stats.misses);
+        assert!(stats.hit_ratio() > 0.99);
+        assert_eq!(1, stats.current_size);
+        // The thread never prewarmed, so every handle allocated its counter
+        assert_eq!((0, 1000), (stats.counter_hits, stats.counter_misses));
+
+        reset_stats();
+        assert_eq!(
+            PoolStats {
+                current_size: 1,
+                ..PoolStats::default()
+            },
+            super::stats()
+        );
+        assert_eq!(0.0, super::stats().hit_ratio());
+
+        // After prewarming, only the unpackaged handle of the first round allocates its counter
+        prewarm(1);
+        reset_stats();
+        for i in 0..1000u64 {
+            let a = Mlsp::new(i);
+            let b = a.package().unpackage();
+            drop(a);
+            drop(b);
+        }
+        let stats = super::stats();
+        assert_eq!(1999, stats.counter_hits);
+        assert_eq!(1, stats.counter_misses);
+        assert!(stats.counter_hit_ratio() > 0.99);
+        assert_eq!(2, stats.counter_size);
+        assert_eq!((0, 0), (stats.hits, stats.misses));
+    }
+
+    #[test]
+    fn released_on_another_thread() {
+        let a = Mlsp::new_pooled(1u64);
+        let package = a.package();
+        drop(a);
+
+        // The slot goes to the pool of the thread that frees it, which has none for it
+        thread::spawn(move || drop(package.unpackage()))
+            .join()
+            .unwrap();
+        assert_eq!(0, free_slots::<u64>());
+    }
+
+    #[test]
+    fn prewarmed_counters() {
+        // Counters are only kept on threads that prewarm
+        drop(Mlsp::new(1u8));
+        assert_eq!(0, free_counters());
+
+        prewarm(3);
+        assert_eq!(3, free_counters());
+        let a = Mlsp::new(1u8);
+        let b = a.package().unpackage();
+        assert_eq!(1, free_counters());
+
+        // Each freed counter is kept, and fills the same allocation again
+        let counter = b.local_count;
+        drop(b);
+        assert_eq!(2, free_counters());
+        let c = a.package().unpackage();
+        assert_eq!(counter, c.local_count);
+        assert_eq!(1, c.local_handles());
+        assert!(c.was_unpackaged());
+        drop((a, c));
+        assert_eq!(3, free_counters());
+
+        // The pool is bounded like those of inners, however many are asked for
+        prewarm(usize::MAX);
+        assert_eq!(MAX_SLOTS, free_counters());
+        let handles: Vec<_> = (0..2 * MAX_SLOTS).map(Mlsp::new).collect();
+        assert_eq!(0, free_counters());
+        drop(handles);
+        assert_eq!(MAX_SLOTS, free_counters());
+    }
+}
@@ -0,0 +1,140 @@
+use std::ptr::NonNull;
+
+#[cfg(feature = "debug")]
+use crate::debug;
+#[cfg(feature = "tracing")]
+use crate::trace;
+use crate::{Mlsp, MlspInner, MlspPackage};
+
+/// Packages counted ahead of time by `Mlsp::reserve`,
+/// which are handed out by `take` without further atomic operations.
+///
+/// Dropping the reservation returns the count of the packages not taken
+/// with a single atomic operation.
+/// The reservation keeps the contents alive until then, like the packages it holds.
+pub struct PackageReservation<T: ?Sized> {
+    inner_ptr: NonNull<MlspInner<T>>,
+    remaining: usize,
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Reserves `additional` packages with a single increment of the atomic_count,
+    /// to be taken later from the reservation without any atomic operations.
+    ///
+    /// This moves the atomic cost of a burst of packaging ahead of the burst itself.
+    /// ```
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let mut reservation = a.reserve(2);
+    /// let package = reservation.take().unwrap();
+    /// drop(reservation);
+    ///
+    /// std::thread::spawn(move || assert_eq!(1, *package.unpackage().as_ref()))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    pub fn reserve(&self, additional: usize) -> PackageReservation<T> {
+        if additional > 0 {
+            // SAFETY: The reservation decrements the counter once for each package it does
+            // not hand out, and each package it does hand out decrements it once
+            unsafe { self.inner_ptr.as_ref().increment_by(additional) };
+
+            #[cfg(feature = "tracing")]
+            trace::package(self.inner_ptr.as_ptr(), additional);
+        }
+
+        PackageReservation {
+            inner_ptr: self.inner_ptr,
+            remaining: additional,
+        }
+    }
+}
+
+impl<T: ?Sized> PackageReservation<T> {
+    /// Hands out one of the reserved packages, or `None` if all have been taken.
+    pub fn take(&mut self) -> Option<MlspPackage<T>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        #[cfg(feature = "debug")]
+        debug::package_created(self.inner_ptr);
+
+        Some(MlspPackage::from_inner(self.inner_ptr))
+    }
+
+    /// The number of packages that can still be taken.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T: ?Sized> Drop for PackageReservation<T> {
+    fn drop(&mut self) {
+        if self.remaining > 0 {
+            // SAFETY: The counter was incremented for each package that was not taken
+            unsafe { MlspInner::decrement_by(self.inner_ptr, self.remaining) };
+        }
+    }
+}
+
+// SAFETY: A reservation holds packages' worth of references, and only hands out packages
+unsafe impl<T: ?Sized + Sync + Send> Send for PackageReservation<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for PackageReservation<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn unused_packages_are_refunded() {
+        let a = Mlsp::new(1u8);
+        let weak = a.downgrade();
+
+        let mut reservation = a.reserve(4);
+        assert_eq!(5, weak.strong_count());
+        let first = reservation.take().unwrap();
+        let second = reservation.take().unwrap();
+        assert_eq!(2, reservation.remaining());
+        assert_eq!(5, weak.strong_count());
+
+        drop(reservation);
+        assert_eq!(3, weak.strong_count());
+        drop(first);
+        drop(second);
+        assert_eq!(1, weak.strong_count());
+
+        // Taking everything leaves nothing to refund
+        let mut reservation = a.reserve(3);
+        let packages: Vec<_> = std::iter::from_fn(|| reservation.take()).collect();
+        assert_eq!(3, packages.len());
+        assert_eq!(4, weak.strong_count());
+        drop(packages);
+        drop(reservation);
+        assert!(a.reserve(0).take().is_none());
+        assert_eq!(1, weak.strong_count());
+    }
+
+    #[test]
+    fn reservation_keeps_contents_alive() {
+        struct Dropped(Rc<Cell<bool>>);
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = Rc::new(Cell::new(false));
+        let a = Mlsp::new(Dropped(dropped.clone()));
+        let reservation = a.reserve(2);
+        drop(a);
+        assert!(!dropped.get());
+
+        // The refund releases the last references
+        drop(reservation);
+        assert!(dropped.get());
+    }
+}
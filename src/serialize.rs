@@ -0,0 +1,167 @@
+//! `serde` support, enabled by the `serde` feature.
+//!
+//! An `Mlsp` serializes as its contents and deserializes into a new allocation,
+//! so handles that shared contents before serializing do not share them afterwards.
+//!
+//! An `MlspWeak` serializes as an optional value: `Some` with the contents if it can be upgraded
+//! and `None` if they have already been dropped.
+//! Deserializing one needs an `MlspWeakSeed`, see its documentation for why.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{DeserializeSeed, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::{Mlsp, MlspWeak};
+
+impl<T: ?Sized + Serialize> Serialize for Mlsp<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_ref().serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Mlsp<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Mlsp::new)
+    }
+}
+
+/// Serializes the contents if they are still alive, as `Some(contents)`, and `None` otherwise.
+///
+/// The contents are kept alive by a temporary upgrade while they are serialized.
+impl<T: ?Sized + Serialize> Serialize for MlspWeak<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.upgrade() {
+            Some(strong) => serializer.serialize_some(strong.as_ref()),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Deserializes an `MlspWeak` that was serialized as an optional value.
+///
+/// A weak reference cannot keep what it refers to alive, so a deserialized `Some(contents)`
+/// needs a strong reference to be held somewhere else, or the contents would be dropped
+/// as soon as they were deserialized and the weak reference would never upgrade.
+/// This is why `MlspWeak` does not implement `Deserialize` on its own.
+///
+/// The seed creates a new allocation for each `Some(contents)` and pushes its strong reference
+/// onto `keep_alive`, the caller decides how long the contents live by holding on to it.
+/// A `None` produces a weak reference created by `MlspWeak::new`, which never upgrades.
+/// ```
+/// use serde::de::DeserializeSeed;
+///
+/// let weak = mlsp::Mlsp::new(5u32).downgrade();
+/// let json = serde_json::to_string(&weak).unwrap();
+/// assert_eq!("null", json);
+///
+/// let mut keep_alive = Vec::new();
+/// let mut json = serde_json::Deserializer::from_str("7");
+/// let weak = mlsp::MlspWeakSeed::new(&mut keep_alive).deserialize(&mut json).unwrap();
+/// assert_eq!(Some(7), weak.upgrade().map(|a| *a.as_ref()));
+///
+/// drop(keep_alive);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct MlspWeakSeed<'a, T> {
+    keep_alive: &'a mut Vec<Mlsp<T>>,
+}
+
+impl<'a, T> MlspWeakSeed<'a, T> {
+    /// Creates a seed that stores the strong references it creates in `keep_alive`.
+    pub fn new(keep_alive: &'a mut Vec<Mlsp<T>>) -> Self {
+        MlspWeakSeed { keep_alive }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for MlspWeakSeed<'_, T> {
+    type Value = MlspWeak<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_option(WeakVisitor {
+            keep_alive: self.keep_alive,
+            marker: PhantomData,
+        })
+    }
+}
+
+struct WeakVisitor<'a, T> {
+    keep_alive: &'a mut Vec<Mlsp<T>>,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for WeakVisitor<'_, T> {
+    type Value = MlspWeak<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an optional value")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(MlspWeak::new())
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(MlspWeak::new())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let strong = Mlsp::new(T::deserialize(deserializer)?);
+        let weak = strong.downgrade();
+        self.keep_alive.push(strong);
+        Ok(weak)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mlsp_round_trip() {
+        let a = Mlsp::new(vec![String::from("a"), String::from("b")]);
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(r#"["a","b"]"#, json);
+
+        let b: Mlsp<Vec<String>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(a.as_ref(), b.as_ref());
+        assert!(b.is_unique());
+    }
+
+    #[test]
+    fn live_weak_round_trip() {
+        let a = Mlsp::new(String::from("live"));
+        let json = serde_json::to_string(&a.downgrade()).unwrap();
+        assert_eq!(r#""live""#, json);
+
+        let mut keep_alive = Vec::<Mlsp<String>>::new();
+        let weak = MlspWeakSeed::new(&mut keep_alive)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+        assert_eq!(
+            Some(String::from("live")),
+            weak.upgrade().map(|b| AsRef::<String>::as_ref(&b).clone())
+        );
+
+        // The deserialized contents are a new allocation, kept alive only by the seed's output
+        assert_eq!(1, keep_alive.len());
+        assert!(weak != a.downgrade());
+        keep_alive.clear();
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn dead_weak_round_trip() {
+        let weak = Mlsp::new(String::from("dead")).downgrade();
+        let json = serde_json::to_string(&weak).unwrap();
+        assert_eq!("null", json);
+
+        let mut keep_alive = Vec::<Mlsp<String>>::new();
+        let weak = MlspWeakSeed::new(&mut keep_alive)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+        assert!(weak.upgrade().is_none());
+        assert!(keep_alive.is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+use std::thread::{self, Scope, ScopedJoinHandle, ThreadId};
+
+use crate::{Mlsp, MlspPackage};
+
+/// Where a reference made by `Mlsp::share_to` is going.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destination {
+    /// The recipient runs on the current thread.
+    Local,
+    /// The recipient runs on another thread.
+    Remote,
+}
+
+impl Destination {
+    /// The destination for a recipient running on `thread`, as seen from the current thread.
+    pub fn of(thread: ThreadId) -> Self {
+        if thread == thread::current().id() {
+            Destination::Local
+        } else {
+            Destination::Remote
+        }
+    }
+}
+
+/// A reference made by `Mlsp::share_to`, in the form its destination can use.
+pub enum Handle<T: ?Sized> {
+    /// A clone for a recipient on the current thread.
+    Local(Mlsp<T>),
+    /// A package for a recipient on another thread.
+    Remote(MlspPackage<T>),
+}
+
+impl<T: ?Sized> Handle<T> {
+    /// Whether this is a clone for the current thread.
+    pub fn is_local(&self) -> bool {
+        matches!(self, Handle::Local(_))
+    }
+
+    /// Converts the reference into an `Mlsp`, unpackaging it if it is a package.
+    ///
+    /// Call this on the destination thread, where an `Mlsp` can be used.
+    pub fn into_mlsp(self) -> Mlsp<T> {
+        match self {
+            Handle::Local(a) => a,
+            Handle::Remote(package) => package.unpackage(),
+        }
+    }
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Makes the cheapest reference that can be used at `dest`:
+    /// a clone for the current thread, which needs no atomic operation unless the allocation is hot,
+    /// or a package for another thread, which needs one.
+    /// ```
+    /// use mlsp::{Destination, Handle};
+    ///
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// let worker = std::thread::spawn(|| ());
+    /// match a.share_to(Destination::of(worker.thread().id())) {
+    ///     Handle::Remote(package) => drop(package),
+    ///     Handle::Local(_) => unreachable!(),
+    /// }
+    /// worker.join().unwrap();
+    /// ```
+    pub fn share_to(&self, dest: Destination) -> Handle<T> {
+        match dest {
+            Destination::Local => Handle::Local(self.clone()),
+            Destination::Remote => Handle::Remote(self.package()),
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Sync> Mlsp<T> {
+    /// Spawns a thread in `scope` that runs `f` with its own handle to the contents,
+    /// packaging the value here and unpackaging it on the new thread.
+    ///
+    /// The thread is joined by the end of the scope at the latest, and its handle
+    /// can't be returned from it, since an `Mlsp` can't leave the thread it was made on.
+    /// ```
+    /// let a = mlsp::Mlsp::new(vec![1, 2, 3]);
+    /// let sum = std::thread::scope(|s| {
+    ///     let worker = a.lend_scoped(s, |b| b.as_ref().iter().sum::<i32>());
+    ///     worker.join().unwrap()
+    /// });
+    /// assert_eq!(6, sum);
+    /// ```
+    /// ```compile_fail
+    /// let a = mlsp::Mlsp::new(1u8);
+    /// std::thread::scope(|s| {
+    ///     // The handle made for the thread can't escape it
+    ///     let b = a.lend_scoped(s, |b| b).join().unwrap();
+    /// });
+    /// ```
+    pub fn lend_scoped<'scope, 'env, F, R>(
+        &self,
+        scope: &'scope Scope<'scope, 'env>,
+        f: F,
+    ) -> ScopedJoinHandle<'scope, R>
+    where
+        T: 'scope,
+        F: FnOnce(Mlsp<T>) -> R + Send + 'scope,
+        R: Send + 'scope,
+    {
+        let package = self.package();
+        scope.spawn(move || f(package.unpackage()))
+    }
+}
+
+impl<T: Send + Sync> Mlsp<T> {
+    /// Allocates `data` and spawns `n` threads in `scope` that each run `f`
+    /// with their index and their own handle to it, broadcasting one immutable input
+    /// to a fixed set of workers.
+    ///
+    /// The packages for the workers are made with a single atomic increment, see `package_n`.
+    /// Returns the handle of the calling thread, which is the only one left once the scope ends.
+    /// ```
+    /// let table = std::thread::scope(|s| {
+    ///     mlsp::Mlsp::scatter(vec![10, 20, 30], s, 3, |i, table| {
+    ///         assert_eq!(10 * (i + 1), table.as_ref()[i]);
+    ///     })
+    /// });
+    /// assert!(table.is_unique());
+    /// ```
+    pub fn scatter<'scope, F>(data: T, scope: &'scope Scope<'scope, '_>, n: usize, f: F) -> Self
+    where
+        T: 'scope,
+        F: Fn(usize, Mlsp<T>) + Send + Sync + 'scope,
+    {
+        let a = Mlsp::new(data);
+        // The workers share one closure, which may outlive this call but not the scope
+        let f = Arc::new(f);
+        for (index, package) in a.package_n(n).into_iter().enumerate() {
+            let f = f.clone();
+            scope.spawn(move || f(index, package.unpackage()));
+        }
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_shares_are_clones() {
+        let a = Mlsp::new(String::from("local"));
+        let weak = a.downgrade();
+
+        let shared = a.share_to(Destination::of(thread::current().id()));
+        assert!(shared.is_local());
+        assert_eq!(1, weak.strong_count());
+
+        let b = shared.into_mlsp();
+        assert!(!b.was_unpackaged());
+        assert_eq!("local", AsRef::<str>::as_ref(&b));
+        drop(b);
+        assert_eq!(1, weak.strong_count());
+    }
+
+    #[test]
+    fn remote_shares_are_packages() {
+        let a = Mlsp::new(String::from("remote"));
+        let weak = a.downgrade();
+
+        let shared = a.share_to(Destination::Remote);
+        assert!(!shared.is_local());
+        assert_eq!(2, weak.strong_count());
+
+        let Handle::Remote(package) = shared else {
+            panic!("a remote share should be a package");
+        };
+        let worker = thread::spawn(move || {
+            let b = Handle::Remote(package).into_mlsp();
+            assert!(b.was_unpackaged());
+            b.len()
+        });
+        assert_ne!(Destination::Local, Destination::of(worker.thread().id()));
+        assert_eq!(6, worker.join().unwrap());
+        assert_eq!(1, weak.strong_count());
+    }
+
+    #[test]
+    fn lent_to_scoped_threads() {
+        let a = Mlsp::new(String::from("lent"));
+        let weak = a.downgrade();
+        let borrowed = String::from(" and borrowed");
+
+        let lengths: Vec<_> = thread::scope(|s| {
+            let workers: Vec<_> = (0..3)
+                .map(|_| {
+                    // The closure may borrow from outside the scope, as any scoped thread can
+                    a.lend_scoped(s, |b| {
+                        assert!(b.was_unpackaged());
+                        b.len() + borrowed.len()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        assert_eq!(vec![17; 3], lengths);
+        assert_eq!(1, weak.strong_count());
+    }
+
+    #[test]
+    fn scattered_to_workers() {
+        let squares: Vec<u64> = (0..8).map(|i| i * i).collect();
+        let (send, recv) = std::sync::mpsc::channel();
+
+        let table = thread::scope(|s| {
+            Mlsp::scatter(squares, s, 8, |i, table| {
+                assert!(table.was_unpackaged());
+                send.send((i, table.as_ref()[i])).unwrap();
+            })
+        });
+
+        drop(send);
+        let mut read: Vec<_> = recv.iter().collect();
+        read.sort();
+        assert_eq!(
+            (0..8).map(|i| (i, (i * i) as u64)).collect::<Vec<_>>(),
+            read
+        );
+
+        // The workers' shares were all released by the end of the scope
+        assert!(table.is_unique());
+        assert_eq!(8, table.try_unwrap().ok().unwrap().len());
+    }
+}
@@ -0,0 +1,196 @@
+use std::borrow::Borrow;
+use std::ops::Range;
+
+use crate::{Mlsp, MlspPackage};
+
+/// A handle to part of a shared slice, which keeps the whole allocation alive.
+///
+/// An `Mlsp<[T]>` always refers to every element of its allocation,
+/// so the halves made by `split_at` are views that pair a handle with the range they cover.
+/// Splitting moves or clones a local handle, so it performs no atomic operations,
+/// and the halves can be packaged for other threads for divide-and-conquer over shared data.
+/// ```
+/// let a = mlsp::Mlsp::<[u32]>::from(vec![1, 2, 3, 4]);
+/// let (left, right) = a.split_at(1);
+/// assert_eq!([1], left.as_ref());
+///
+/// let package = right.package();
+/// let sum = std::thread::spawn(move || {
+///     let (b, c) = package.unpackage().split_at(1);
+///     b.as_ref()[0] + c.as_ref().iter().sum::<u32>()
+/// });
+/// assert_eq!(9, sum.join().unwrap());
+/// ```
+pub struct MlspSlice<T> {
+    inner: Mlsp<[T]>,
+    range: Range<usize>,
+}
+
+impl<T> Mlsp<[T]> {
+    /// Splits the handle into views of the elements before and after `mid`,
+    /// which share this allocation.
+    ///
+    /// # Panics
+    /// Panics if `mid` is greater than the length of the slice.
+    pub fn split_at(self, mid: usize) -> (MlspSlice<T>, MlspSlice<T>) {
+        MlspSlice::from(self).split_at(mid)
+    }
+}
+
+impl<T> MlspSlice<T> {
+    /// Splits the view into views of its elements before and after `mid`,
+    /// which share its allocation.
+    ///
+    /// # Panics
+    /// Panics if `mid` is greater than the length of this view.
+    pub fn split_at(self, mid: usize) -> (MlspSlice<T>, MlspSlice<T>) {
+        assert!(mid <= self.range.len(), "mid > len");
+
+        let mid = self.range.start + mid;
+        let head = MlspSlice {
+            inner: self.inner.clone(),
+            range: self.range.start..mid,
+        };
+        let tail = MlspSlice {
+            inner: self.inner,
+            range: mid..self.range.end,
+        };
+        (head, tail)
+    }
+
+    /// The position of this view's elements in the whole slice.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// A handle to the whole slice this view is part of.
+    pub fn whole(&self) -> &Mlsp<[T]> {
+        &self.inner
+    }
+
+    /// Create a Send-able package from the view
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> MlspSlicePackage<T> {
+        MlspSlicePackage {
+            inner: self.inner.package(),
+            range: self.range.clone(),
+        }
+    }
+}
+
+/// A view of the whole slice.
+impl<T> From<Mlsp<[T]>> for MlspSlice<T> {
+    fn from(inner: Mlsp<[T]>) -> Self {
+        let range = 0..inner.as_ref().len();
+        MlspSlice { inner, range }
+    }
+}
+
+impl<T> AsRef<[T]> for MlspSlice<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.inner.as_ref()[self.range.clone()]
+    }
+}
+
+impl<T> Borrow<[T]> for MlspSlice<T> {
+    fn borrow(&self) -> &[T] {
+        self.as_ref()
+    }
+}
+
+impl<T> Clone for MlspSlice<T> {
+    fn clone(&self) -> Self {
+        MlspSlice {
+            inner: self.inner.clone(),
+            range: self.range.clone(),
+        }
+    }
+}
+
+/// A reference to part of a shared slice that can be sent across threads.
+pub struct MlspSlicePackage<T> {
+    inner: MlspPackage<[T]>,
+    range: Range<usize>,
+}
+
+impl<T> MlspSlicePackage<T> {
+    /// Turns this package into a view that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> MlspSlice<T> {
+        MlspSlice {
+            inner: self.inner.unpackage(),
+            range: self.range,
+        }
+    }
+}
+
+impl<T> Clone for MlspSlicePackage<T> {
+    fn clone(&self) -> Self {
+        MlspSlicePackage {
+            inner: self.inner.clone(),
+            range: self.range.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn halves_share_the_allocation() {
+        let a = Mlsp::<[String]>::from(vec![String::from("a"), String::from("b")]);
+        let weak = a.downgrade();
+        let (left, right) = a.split_at(1);
+        assert_eq!(["a"], left.as_ref());
+        assert_eq!(["b"], right.as_ref());
+        assert!(std::ptr::addr_eq(
+            left.whole().as_ptr(),
+            right.whole().as_ptr()
+        ));
+
+        // Either half keeps the whole slice alive
+        drop(left);
+        assert_eq!(1, weak.strong_count());
+        assert_eq!(["b"], right.as_ref());
+        assert_eq!(1..2, right.range());
+        drop(right);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn divide_and_conquer() {
+        fn sum(slice: MlspSlice<u64>) -> u64 {
+            if slice.as_ref().len() <= 4 {
+                return slice.as_ref().iter().sum();
+            }
+            let mid = slice.as_ref().len() / 2;
+            let (left, right) = slice.split_at(mid);
+            let right = right.package();
+            let right = thread::spawn(move || sum(right.unpackage()));
+            sum(left) + right.join().unwrap()
+        }
+
+        let a = Mlsp::<[u64]>::from((1..=100).collect::<Vec<_>>());
+        let weak = a.downgrade();
+        assert_eq!(5050, sum(a.into()));
+        assert_eq!(0, weak.strong_count());
+
+        // Empty halves at either end
+        let (empty, all) = Mlsp::<[u64]>::from(vec![1, 2]).split_at(0);
+        assert!(empty.as_ref().is_empty());
+        let (all, empty) = all.split_at(2);
+        assert_eq!([1, 2], all.as_ref());
+        assert!(empty.as_ref().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "mid > len")]
+    fn split_past_the_end() {
+        let (_, right) = Mlsp::<[u8]>::from(vec![1, 2, 3]).split_at(1);
+        let _ = right.split_at(3);
+    }
+}
@@ -0,0 +1,286 @@
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+use crate::atomic::{AtomicPtr, AtomicUsize};
+use crate::{Mlsp, MlspPackage};
+
+/// A lock-free stack of `Mlsp`s that can be shared between threads.
+///
+/// Values are stored as packages, so pushing the last handle on a thread
+/// and popping it on another performs no atomic operations on the value's counter.
+/// The contents of a popped value stay alive through its counter like any other `Mlsp`,
+/// however long the stack itself lives.
+/// ```
+/// let stack = mlsp::MlspStack::new();
+/// stack.push(mlsp::Mlsp::new(1u8));
+/// stack.push(mlsp::Mlsp::new(2u8));
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert_eq!(Some(2), stack.pop().map(|a| *a.as_ref())));
+/// });
+/// assert_eq!(Some(1), stack.pop().map(|a| *a.as_ref()));
+/// assert!(stack.pop().is_none());
+/// ```
+pub struct MlspStack<T: ?Sized> {
+    head: AtomicPtr<Node<T>>,
+    /// The number of threads currently inside `pop`
+    poppers: AtomicUsize,
+    /// Popped nodes that another popper may still be reading, linked through `next`
+    pending: AtomicPtr<Node<T>>,
+}
+
+struct Node<T: ?Sized> {
+    /// Moved out by the thread that pops the node, after which only `next` is read
+    value: ManuallyDrop<MlspPackage<T>>,
+    /// Atomic since a popper that lost the race for this node may still be reading it
+    /// while it is relinked into the pending list
+    next: AtomicPtr<Node<T>>,
+}
+
+// Popped nodes are not freed while any other thread is inside `pop`,
+// since it may have loaded the node as the head and be about to read its `next`.
+// They are put on the pending list instead, which is freed by the next popper
+// to find itself alone. A node's address can't be reused while a popper might hold it,
+// which also rules out ABA on the head.
+impl<T: ?Sized> MlspStack<T> {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        MlspStack {
+            head: AtomicPtr::new(ptr::null_mut()),
+            poppers: AtomicUsize::new(0),
+            pending: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Pushes a value onto the top of the stack.
+    ///
+    /// An `Mlsp` that is the last handle on its thread is moved in without an atomic increment,
+    /// see `Mlsp::into_package`.
+    pub fn push(&self, value: impl Into<MlspPackage<T>>) {
+        let node = Box::into_raw(Box::new(Node {
+            value: ManuallyDrop::new(value.into()),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }));
+
+        // Every access to the head is SeqCst, since the reclamation in `pop`
+        // relies on a single order between them and the changes to `poppers`
+        let mut head = self.head.load(Ordering::SeqCst);
+        loop {
+            // SAFETY: The node is not shared until the exchange succeeds
+            unsafe { (*node).next.store(head, Ordering::Relaxed) };
+            match self
+                .head
+                .compare_exchange_weak(head, node, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Removes the value on top of the stack and returns it as an Mlsp for this thread.
+    pub fn pop(&self) -> Option<Mlsp<T>> {
+        self.poppers.fetch_add(1, Ordering::SeqCst);
+
+        let mut head = self.head.load(Ordering::SeqCst);
+        loop {
+            if head.is_null() {
+                self.poppers.fetch_sub(1, Ordering::SeqCst);
+                return None;
+            }
+
+            // SAFETY: Nodes are not freed while this thread is counted in `poppers`
+            let next = unsafe { (*head).next.load(Ordering::SeqCst) };
+            match self
+                .head
+                .compare_exchange_weak(head, next, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+
+        // SAFETY: Winning the exchange gives this thread sole ownership of the value,
+        // other poppers only read `next`
+        let package = unsafe { ManuallyDrop::take(&mut (*ptr::addr_of_mut!((*head).value))) };
+        unsafe { self.reclaim(head) };
+
+        Some(package.unpackage())
+    }
+
+    /// Returns true if the stack held no values when it was checked.
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::SeqCst).is_null()
+    }
+
+    /// Frees a popped node or defers it, and leaves `pop`.
+    ///
+    /// # Safety
+    /// The node must have just been unlinked by this thread and its value moved out.
+    unsafe fn reclaim(&self, node: *mut Node<T>) {
+        if self.poppers.load(Ordering::SeqCst) != 1 {
+            // Another popper may have loaded this node, leave it for a later one
+            self.defer(node, node);
+            self.poppers.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+
+        // No other popper can have loaded this node, it was unlinked before any that started since
+        let pending = self.pending.swap(ptr::null_mut(), Ordering::SeqCst);
+        if self.poppers.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Still alone after claiming the pending list, so nothing references its nodes either
+            free_chain(pending);
+        } else if !pending.is_null() {
+            // A new popper may have loaded nodes of the claimed list before it was claimed
+            let mut last = pending;
+            while !(*last).next.load(Ordering::Relaxed).is_null() {
+                last = (*last).next.load(Ordering::Relaxed);
+            }
+            self.defer(pending, last);
+        }
+        drop(Box::from_raw(node));
+    }
+
+    /// Adds the chain of nodes from `first` to `last` to the pending list.
+    ///
+    /// # Safety
+    /// The nodes must be unlinked from the stack and their values moved out.
+    unsafe fn defer(&self, first: *mut Node<T>, last: *mut Node<T>) {
+        let mut pending = self.pending.load(Ordering::SeqCst);
+        loop {
+            (*last).next.store(pending, Ordering::SeqCst);
+            match self.pending.compare_exchange_weak(
+                pending,
+                first,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return,
+                Err(current) => pending = current,
+            }
+        }
+    }
+}
+
+/// Frees a chain of nodes whose values have been moved out.
+///
+/// # Safety
+/// No other thread may reference any node of the chain.
+unsafe fn free_chain<T: ?Sized>(mut node: *mut Node<T>) {
+    while !node.is_null() {
+        let next = (*node).next.load(Ordering::Relaxed);
+        drop(Box::from_raw(node));
+        node = next;
+    }
+}
+
+impl<T: ?Sized> Default for MlspStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> Drop for MlspStack<T> {
+    fn drop(&mut self) {
+        // SAFETY: No thread can be inside `pop` while the stack is borrowed mutably
+        unsafe {
+            let mut node = self.head.load(Ordering::Acquire);
+            while !node.is_null() {
+                let mut boxed = Box::from_raw(node);
+                ManuallyDrop::drop(&mut boxed.value);
+                node = boxed.next.load(Ordering::Relaxed);
+            }
+            free_chain(self.pending.load(Ordering::Acquire));
+        }
+    }
+}
+
+// SAFETY: The stack only hands out the packages it holds, which are Send and Sync in this case
+unsafe impl<T: ?Sized + Send + Sync> Send for MlspStack<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for MlspStack<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Fails if its contents are read after being dropped
+    struct Canary(AtomicBool);
+
+    impl Canary {
+        fn check(&self) {
+            assert!(!self.0.load(Ordering::Acquire), "read after drop");
+        }
+    }
+
+    impl Drop for Canary {
+        fn drop(&mut self) {
+            assert!(!self.0.swap(true, Ordering::AcqRel), "dropped twice");
+        }
+    }
+
+    #[test]
+    fn last_in_first_out() {
+        let stack = MlspStack::new();
+        let a = Mlsp::new(1u8);
+        stack.push(a.package());
+        stack.push(a.clone());
+        stack.push(Mlsp::new(2u8));
+
+        assert_eq!(Some(2), stack.pop().map(|a| *a.as_ref()));
+        assert!(stack.pop().unwrap().as_ptr() == a.as_ptr());
+        assert!(!stack.is_empty());
+
+        // Values left on the stack are released with it
+        let weak = a.downgrade();
+        drop(a);
+        drop(stack);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn stress() {
+        const THREADS: usize = 8;
+        const ROUNDS: usize = if cfg!(miri) { 50 } else { 2000 };
+
+        let stack = Arc::new(MlspStack::new());
+        let shared = Mlsp::new(Canary(AtomicBool::new(false)));
+        let weak = shared.downgrade();
+
+        let workers: Vec<_> = (0..THREADS)
+            .map(|i| {
+                let stack = stack.clone();
+                let package = shared.package();
+                thread::spawn(move || {
+                    let shared = package.unpackage();
+                    for round in 0..ROUNDS {
+                        // Alternate between a value every thread shares and one only this thread made
+                        if (round + i) % 2 == 0 {
+                            stack.push(shared.package());
+                        } else {
+                            stack.push(Mlsp::new(Canary(AtomicBool::new(false))));
+                        }
+                        if let Some(value) = stack.pop() {
+                            value.as_ref().check();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        while let Some(value) = stack.pop() {
+            value.as_ref().check();
+        }
+
+        // Every package of the shared value pushed was released
+        drop(shared);
+        assert_eq!(0, weak.strong_count());
+    }
+}
@@ -0,0 +1,96 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::{Mlsp, MlspPackage};
+
+/// A `Stream` of `Mlsp`s made by unpackaging the packages of another stream,
+/// such as the receiver of an async channel, enabled by the `futures` feature.
+///
+/// Each package is unpackaged when the stream is polled, on the thread polling it,
+/// so the local counter of every value received belongs to the consuming task's thread.
+/// ```
+/// use futures::channel::mpsc;
+/// use futures::StreamExt;
+///
+/// let (send, recv) = mpsc::unbounded();
+/// send.unbounded_send(mlsp::Mlsp::new(1u8).package()).unwrap();
+/// drop(send);
+///
+/// let mut stream = mlsp::MlspStream::new(recv);
+/// futures::executor::block_on(async {
+///     assert_eq!(Some(1), stream.next().await.map(|a| *a.as_ref()));
+///     assert!(stream.next().await.is_none());
+/// });
+/// ```
+pub struct MlspStream<S> {
+    packages: S,
+}
+
+impl<S> MlspStream<S> {
+    /// Wraps a stream of packages.
+    pub fn new(packages: S) -> Self {
+        MlspStream { packages }
+    }
+
+    /// Returns the wrapped stream of packages.
+    pub fn into_inner(self) -> S {
+        self.packages
+    }
+}
+
+impl<T: ?Sized, S: Stream<Item = MlspPackage<T>>> Stream for MlspStream<S> {
+    type Item = Mlsp<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Mlsp<T>>> {
+        // SAFETY: The wrapped stream is pinned along with the wrapper and never moved out of it
+        // while pinned, `into_inner` takes the wrapper by value which requires it to be unpinned
+        let packages = unsafe { self.map_unchecked_mut(|stream| &mut stream.packages) };
+        packages
+            .poll_next(cx)
+            .map(|package| package.map(MlspPackage::unpackage))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.packages.size_hint()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    use futures::channel::mpsc;
+    use futures::{SinkExt, StreamExt};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn unpackaged_by_the_receiving_task() {
+        let shared = Mlsp::new(String::from("shared"));
+        let weak = shared.downgrade();
+        let (mut send, recv) = mpsc::channel(4);
+
+        let packages = shared.package_n(8);
+        drop(shared);
+        let sender = tokio::spawn(async move {
+            for package in packages {
+                send.send(package).await.unwrap();
+            }
+        });
+
+        let receiver = tokio::spawn(async move {
+            let mut stream = MlspStream::new(recv);
+            let mut received = 0;
+            while let Some(a) = stream.next().await {
+                assert_eq!("shared", AsRef::<str>::as_ref(&a));
+                assert!(a.was_unpackaged());
+                received += 1;
+            }
+            received
+        });
+
+        sender.await.unwrap();
+        assert_eq!(8, receiver.await.unwrap());
+        assert!(weak.upgrade().is_none());
+    }
+}
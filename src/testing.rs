@@ -0,0 +1,122 @@
+//! Constructors for putting an allocation into a chosen count state, enabled by the `testing` feature.
+//!
+//! These exist so tests of the two-level counting can start from a specific state
+//! instead of building it up from real handles and packages.
+//! They are not meant for use outside of tests.
+
+use std::sync::atomic::Ordering;
+
+use crate::Mlsp;
+
+impl<T> Mlsp<T> {
+    /// Creates a handle to `data` whose local count is `local` and whose atomic count is `atomic`,
+    /// as though `local - 1` other handles shared its local counter
+    /// and `atomic - 1` other packages or threads referenced the allocation.
+    ///
+    /// # Warning
+    /// The counts beyond the returned handle stand for references that do not exist,
+    /// so nothing will ever release them and the contents are leaked unless both counts are 1.
+    /// The state is otherwise consistent: every operation on the handle behaves as it would
+    /// with those references alive, which is what makes it useful in tests and nowhere else.
+    ///
+    /// The allocation is not marked hot even if `atomic` is at or above `HOT_THRESHOLD`.
+    ///
+    /// # Panics
+    /// Panics if either count is zero, since the returned handle is counted in both.
+    /// ```
+    /// let a = mlsp::Mlsp::with_counts(1u8, 1, 2);
+    /// assert!(!a.is_unique());
+    /// assert!(a.try_unwrap().is_err());
+    /// ```
+    pub fn with_counts(data: T, local: usize, atomic: usize) -> Self {
+        assert!(
+            local > 0 && atomic > 0,
+            "counts must include the new handle"
+        );
+        assert!(
+            atomic <= Self::MAX_REFCOUNT,
+            "atomic count above MAX_REFCOUNT"
+        );
+
+        let a = Mlsp::new(data);
+        // SAFETY: Nothing else references the new allocation or its local counter yet
+        unsafe {
+            a.local_count.as_ref().count.set(local);
+            a.inner_ptr
+                .as_ref()
+                .atomic_count
+                .store(atomic, Ordering::Relaxed);
+        }
+        a
+    }
+}
+
+/// Sets the counts back to the handle's own and drops it,
+/// so a test that started from `with_counts` releases the allocation and its local counter
+/// instead of leaking them.
+///
+/// The handle must be the only real reference left to the allocation.
+#[cfg(test)]
+pub(crate) fn release<T>(a: Mlsp<T>) {
+    // SAFETY: The references the counts stood for never existed
+    unsafe {
+        a.local_count.as_ref().count.set(1);
+        a.inner_ptr
+            .as_ref()
+            .atomic_count
+            .store(1, Ordering::Relaxed);
+    }
+    drop(a);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::BackpressureError;
+
+    #[test]
+    fn uniqueness_at_each_state() {
+        for (local, atomic) in [(1, 1), (2, 1), (1, 2), (3, 4)] {
+            let state = format!("local {} atomic {}", local, atomic);
+            let unique = local == 1 && atomic == 1;
+
+            let mut a = Mlsp::with_counts(5u32, local, atomic);
+            assert_eq!(unique, a.is_unique(), "{}", state);
+            assert_eq!(unique, a.get_mut().is_some(), "{}", state);
+            assert_eq!(atomic, a.downgrade().strong_count(), "{}", state);
+            match a.try_unwrap() {
+                Ok(_) => assert!(unique, "{}", state),
+                Err(a) => {
+                    assert!(!unique, "{}", state);
+                    release(a);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_near_the_limit() {
+        let max = Mlsp::<u8>::MAX_REFCOUNT;
+        let a = Mlsp::with_counts(1u8, 1, max - 2);
+        let weak = a.downgrade();
+
+        assert_eq!(Some(BackpressureError), a.package_n_bounded(3, max).err());
+        assert_eq!(max - 2, weak.strong_count());
+
+        let packages = a.package_n_bounded(2, max).unwrap();
+        assert_eq!(max, weak.strong_count());
+        assert_eq!(Some(BackpressureError), a.package_n_bounded(1, max).err());
+        drop(packages);
+        assert_eq!(max - 2, weak.strong_count());
+
+        release(a);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    #[should_panic(expected = "counts must include the new handle")]
+    fn zero_counts() {
+        let _ = Mlsp::with_counts(1u8, 0, 1);
+    }
+}
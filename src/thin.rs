@@ -0,0 +1,268 @@
+//! A single-pointer handle, enabled by the `thin` feature.
+//!
+//! An `Mlsp` holds a pointer to its local counter next to the pointer to its inner.
+//! A `ThinMlsp` holds only the inner pointer and keeps its thread's local counts in a
+//! thread-local map keyed by the inner's address, so it is half the size
+//! at the cost of a map lookup on every clone and drop.
+//! The map also means every `ThinMlsp` for one allocation on a thread shares one count,
+//! however many times the allocation was unpackaged there.
+//!
+//! `benches/thin.rs` compares the two representations.
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+use std::ptr::{self, NonNull};
+
+#[cfg(feature = "debug")]
+use crate::debug;
+#[cfg(feature = "tracing")]
+use crate::trace;
+use crate::{MlspInner, MlspPackage};
+
+thread_local! {
+    /// The number of `ThinMlsp`s on this thread for each inner they refer to
+    static LOCAL_COUNTS: RefCell<HashMap<usize, usize>> = RefCell::new(HashMap::new());
+}
+
+/// The key of an inner in `LOCAL_COUNTS`
+fn key<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) -> usize {
+    inner_ptr.as_ptr() as *const () as usize
+}
+
+/// Counts one more handle to the inner on this thread,
+/// returning whether the thread already held a share of its atomic count.
+fn adopt<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) -> bool {
+    LOCAL_COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        let count = counts.entry(key(inner_ptr)).or_insert(0);
+        *count += 1;
+        *count > 1
+    })
+}
+
+/// Counts one fewer handle to the inner on this thread,
+/// returning whether that was the last one and the thread's share of the atomic count is released.
+fn release<T: ?Sized>(inner_ptr: NonNull<MlspInner<T>>) -> bool {
+    LOCAL_COUNTS
+        .try_with(|counts| {
+            let mut counts = counts.borrow_mut();
+            let key = key(inner_ptr);
+            let count = counts
+                .get_mut(&key)
+                .expect("thin handle without a local count");
+            *count -= 1;
+            let last = *count == 0;
+            if last {
+                counts.remove(&key);
+            }
+            last
+        })
+        // Once the map is gone its counts can't be trusted, so the share is leaked
+        .unwrap_or(false)
+}
+
+/// A hybrid between Rc and Arc like `Mlsp`, one pointer wide.
+///
+/// It packages and unpackages like an `Mlsp`, trading the pointer to a local counter
+/// for a lookup in a thread-local map on clone and drop.
+/// A `ThinMlsp` still alive when its thread's map is torn down, such as one stored
+/// in another thread-local, leaks its contents instead of dropping them.
+/// ```
+/// let a = mlsp::ThinMlsp::new(String::from("thin"));
+/// assert_eq!(std::mem::size_of::<usize>(), std::mem::size_of_val(&a));
+///
+/// let package = a.package();
+/// std::thread::spawn(move || assert_eq!("thin", package.unpackage_thin().as_ref()))
+///     .join()
+///     .unwrap();
+/// ```
+pub struct ThinMlsp<T: ?Sized> {
+    inner_ptr: NonNull<MlspInner<T>>,
+}
+
+impl<T> ThinMlsp<T> {
+    /// Creates a new ThinMlsp wrapping the given value.
+    pub fn new(data: T) -> Self {
+        let inner_ptr = NonNull::from(Box::leak(Box::new(MlspInner::new(data))));
+        ThinMlsp::from_inner(inner_ptr)
+    }
+}
+
+impl<T: ?Sized> ThinMlsp<T> {
+    /// Creates a handle for this thread from an inner whose atomic count
+    /// already accounts for it, merging that share into the thread's own if it holds one.
+    fn from_inner(inner_ptr: NonNull<MlspInner<T>>) -> Self {
+        #[cfg(feature = "debug")]
+        debug::handle_created(inner_ptr);
+
+        if adopt(inner_ptr) {
+            // SAFETY: The thread's other handles keep the contents alive,
+            // so this releases the surplus share without dropping them
+            unsafe { MlspInner::decrement(inner_ptr) };
+        } else {
+            #[cfg(feature = "debug")]
+            debug::counter_created(inner_ptr);
+        }
+        ThinMlsp { inner_ptr }
+    }
+
+    /// Create a Send-able package from the handle
+    ///
+    /// This increments the atomic_count
+    pub fn package(&self) -> MlspPackage<T> {
+        // SAFETY: The package decrements the counter once when it is dropped
+        unsafe { self.inner_ptr.as_ref().increment() };
+
+        #[cfg(feature = "debug")]
+        debug::package_created(self.inner_ptr);
+
+        #[cfg(feature = "tracing")]
+        trace::package(self.inner_ptr.as_ptr(), 1);
+
+        MlspPackage::from_inner(self.inner_ptr)
+    }
+
+    /// A pointer to the contents, which stays valid as long as any reference to them exists.
+    pub fn as_ptr(&self) -> *const T {
+        unsafe { ptr::addr_of!((*self.inner_ptr.as_ptr()).data) as *const T }
+    }
+}
+
+impl<T: ?Sized> MlspPackage<T> {
+    /// Turns this package into a `ThinMlsp` for the current thread.
+    ///
+    /// If the thread already has `ThinMlsp`s for the same contents the new handle
+    /// joins their count and the package's share of the atomic count is released.
+    pub fn unpackage_thin(self) -> ThinMlsp<T> {
+        #[cfg(feature = "debug")]
+        debug::check_unpackage(self.source);
+
+        // The package's reference is handed over to the new handle
+        let package = ManuallyDrop::new(self);
+
+        #[cfg(feature = "debug")]
+        debug::package_dropped(package.inner_ptr);
+
+        #[cfg(feature = "tracing")]
+        trace::unpackage(package.inner_ptr.as_ptr());
+
+        ThinMlsp::from_inner(package.inner_ptr)
+    }
+}
+
+impl<T: ?Sized> Clone for ThinMlsp<T> {
+    fn clone(&self) -> Self {
+        // The thread holds a share for this handle, so it only needs another local count
+        adopt(self.inner_ptr);
+
+        #[cfg(feature = "debug")]
+        debug::handle_created(self.inner_ptr);
+
+        ThinMlsp {
+            inner_ptr: self.inner_ptr,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for ThinMlsp<T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "debug")]
+        debug::handle_dropped(self.inner_ptr);
+
+        // The map is no longer borrowed here, so the contents' drop can use other thin handles
+        if release(self.inner_ptr) {
+            #[cfg(feature = "debug")]
+            debug::counter_released(self.inner_ptr);
+
+            // SAFETY: This was the thread's last handle, holding its share of the atomic count
+            unsafe { MlspInner::decrement(self.inner_ptr) };
+        }
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for ThinMlsp<T> {
+    fn as_ref(&self) -> &T {
+        unsafe { &self.inner_ptr.as_ref().data }
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for ThinMlsp<T> {
+    fn borrow(&self) -> &T {
+        self.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn one_pointer_wide() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<ThinMlsp<u64>>());
+        assert_eq!(
+            mem::size_of::<usize>(),
+            mem::size_of::<Option<ThinMlsp<u64>>>()
+        );
+        assert_eq!(
+            2 * mem::size_of::<ThinMlsp<u64>>(),
+            mem::size_of::<crate::Mlsp<u64>>()
+        );
+    }
+
+    #[test]
+    fn counts_shared_per_thread() {
+        let a = ThinMlsp::new(String::from("counted"));
+        let weak = {
+            let package = a.package();
+            package.unpackage().downgrade()
+        };
+        assert_eq!(1, weak.strong_count());
+
+        // Clones and packages count as they do for `Mlsp`
+        let b = a.clone();
+        assert_eq!(1, weak.strong_count());
+        let packages = [a.package(), a.package()];
+        assert_eq!(3, weak.strong_count());
+
+        // Unpackaging on a thread that holds a share folds the package's share into it
+        let [first, second] = packages;
+        let c = first.unpackage_thin();
+        assert_eq!(2, weak.strong_count());
+        let remote = thread::spawn(move || {
+            let d = second.unpackage_thin();
+            let e = d.clone();
+            e.as_ref().len()
+        });
+        assert_eq!(7, remote.join().unwrap());
+        assert_eq!(1, weak.strong_count());
+
+        drop((a, b));
+        assert_eq!("counted", c.as_ref());
+        assert_eq!(1, weak.strong_count());
+        drop(c);
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn drop_uses_other_handles() {
+        struct Owner(ThinMlsp<u8>);
+        impl Drop for Owner {
+            fn drop(&mut self) {
+                assert_eq!(1, *self.0.as_ref());
+            }
+        }
+
+        let shared = ThinMlsp::new(1u8);
+        let owner = ThinMlsp::new(Owner(shared.clone()));
+        drop(shared);
+
+        // The owner's drop releases the last handle to `shared` while the map is in use
+        drop(owner);
+        LOCAL_COUNTS.with(|counts| assert!(counts.borrow().is_empty()));
+    }
+}
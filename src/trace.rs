@@ -0,0 +1,130 @@
+//! `tracing` events for the atomic counters of each allocation.
+//!
+//! When the `tracing` feature is enabled every change to an allocation's atomic count
+//! emits a TRACE event with the `mlsp` target, carrying the address of the contents
+//! (as returned by `Mlsp::as_ptr`) and the count after the change.
+//! Packaging and unpackaging emit their own events as well.
+
+use std::ptr;
+
+use crate::MlspInner;
+
+/// The address an allocation is reported under, the address of its data.
+fn addr<T: ?Sized>(inner: *const MlspInner<T>) -> *const () {
+    // SAFETY: Only computes an address within the allocation, nothing is read.
+    unsafe { ptr::addr_of!((*inner).data) as *const () }
+}
+
+pub(crate) fn increment<T: ?Sized>(inner: *const MlspInner<T>, count: usize) {
+    tracing::trace!(target: "mlsp", addr = ?addr(inner), count, "atomic increment");
+}
+
+pub(crate) fn decrement<T: ?Sized>(inner: *const MlspInner<T>, count: usize) {
+    tracing::trace!(target: "mlsp", addr = ?addr(inner), count, "atomic decrement");
+}
+
+pub(crate) fn package<T: ?Sized>(inner: *const MlspInner<T>, packages: usize) {
+    tracing::trace!(target: "mlsp", addr = ?addr(inner), packages, "package");
+}
+
+pub(crate) fn unpackage<T: ?Sized>(inner: *const MlspInner<T>) {
+    tracing::trace!(target: "mlsp", addr = ?addr(inner), "unpackage");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Mlsp;
+
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// The message of an event along with its address and count, if any
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Captured {
+        message: String,
+        addr: String,
+        count: Option<u64>,
+    }
+
+    impl Visit for Captured {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "count" {
+                self.count = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            match field.name() {
+                "message" => self.message = format!("{:?}", value),
+                "addr" => self.addr = format!("{:?}", value),
+                _ => {}
+            }
+        }
+    }
+
+    /// Records every event from this crate, ignoring spans
+    #[derive(Clone, Default)]
+    struct Capture {
+        events: Arc<Mutex<Vec<Captured>>>,
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "mlsp"
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut captured = Captured::default();
+            event.record(&mut captured);
+            self.events.lock().unwrap().push(captured);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn clone_package_drop() {
+        let capture = Capture::default();
+
+        let addr = tracing::subscriber::with_default(capture.clone(), || {
+            let a = Mlsp::new(1u8);
+            let addr = format!("{:?}", a.as_ptr() as *const ());
+
+            // Local clones do not touch the atomic count
+            drop(a.clone());
+
+            let b = a.package().unpackage();
+            drop(b);
+            drop(a);
+            addr
+        });
+
+        let event = |message: &str, count| Captured {
+            message: message.to_string(),
+            addr: addr.clone(),
+            count,
+        };
+        let expected = vec![
+            event("atomic increment", Some(2)),
+            event("package", None),
+            event("unpackage", None),
+            event("atomic decrement", Some(1)),
+            event("atomic decrement", Some(0)),
+        ];
+        assert_eq!(expected, *capture.events.lock().unwrap());
+    }
+}
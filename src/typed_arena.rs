@@ -0,0 +1,225 @@
+use std::borrow::Borrow;
+use std::cell::{Cell, RefCell};
+use std::sync::OnceLock;
+
+use crate::{Mlsp, MlspPackage};
+
+/// Hands out handles to values stored together in shared blocks,
+/// so that many small shared values cost one allocation per block rather than one each.
+///
+/// Each block holds `block_len` values, and a new block is started once the current one is full.
+/// Every handle into a block shares the block's counters, so cloning a handle is a local
+/// increment as with `Mlsp`, and a block with all of its values is dropped
+/// once the arena has moved past it and the last handle into it is gone.
+/// ```
+/// let arena = mlsp::MlspArena::with_block_len(2);
+/// let a = arena.alloc(String::from("a"));
+/// let b = arena.alloc(String::from("b"));
+/// assert_eq!("b", b.as_ref());
+///
+/// let package = a.package();
+/// std::thread::spawn(move || assert_eq!("a", package.unpackage().as_ref()))
+///     .join()
+///     .unwrap();
+/// ```
+pub struct MlspArena<T> {
+    /// The current block, whose slots are `OnceLock`s so that handles on other threads
+    /// can read the values stored so far while the arena fills the rest
+    block: RefCell<Option<Mlsp<[OnceLock<T>]>>>,
+    next: Cell<usize>,
+    block_len: usize,
+}
+
+impl<T> MlspArena<T> {
+    /// The number of values in each block of an arena made with `new`
+    pub const DEFAULT_BLOCK_LEN: usize = 64;
+
+    /// Creates an arena with blocks of `DEFAULT_BLOCK_LEN` values.
+    pub fn new() -> Self {
+        Self::with_block_len(Self::DEFAULT_BLOCK_LEN)
+    }
+
+    /// Creates an arena whose blocks hold `block_len` values each.
+    ///
+    /// # Panics
+    /// Panics if `block_len` is zero.
+    pub fn with_block_len(block_len: usize) -> Self {
+        assert!(block_len > 0, "blocks must hold at least one value");
+        MlspArena {
+            block: RefCell::new(None),
+            next: Cell::new(block_len),
+            block_len,
+        }
+    }
+
+    /// Stores `value` in the current block and returns a handle to it,
+    /// starting a new block first if the current one is full.
+    pub fn alloc(&self, value: T) -> MlspArenaHandle<T> {
+        if self.next.get() == self.block_len {
+            let slots: Vec<_> = (0..self.block_len).map(|_| OnceLock::new()).collect();
+            let full = self.block.borrow_mut().replace(Mlsp::from(slots));
+            self.next.set(0);
+            // Dropped outside the borrow, since it may drop values whose own drop uses the arena
+            drop(full);
+        }
+
+        let index = self.next.get();
+        self.next.set(index + 1);
+
+        let block = self.block.borrow().clone().unwrap();
+        block.as_ref()[index]
+            .set(value)
+            .ok()
+            .expect("only the arena sets slots, each once as `next` passes it");
+        MlspArenaHandle { block, index }
+    }
+}
+
+impl<T> Default for MlspArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A handle to one value stored in an `MlspArena`, which keeps the value's whole block alive.
+pub struct MlspArenaHandle<T> {
+    block: Mlsp<[OnceLock<T>]>,
+    index: usize,
+}
+
+impl<T> MlspArenaHandle<T> {
+    /// Create a Send-able package from the handle
+    ///
+    /// This increments the atomic_count of the block
+    pub fn package(&self) -> MlspArenaPackage<T> {
+        MlspArenaPackage {
+            block: self.block.package(),
+            index: self.index,
+        }
+    }
+
+    /// The values stored so far in the block this value is stored in, in the order stored.
+    ///
+    /// The view is read-only, the slots the arena has not reached yet are left out.
+    pub fn block_values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.block.as_ref().iter().filter_map(OnceLock::get)
+    }
+}
+
+impl<T> AsRef<T> for MlspArenaHandle<T> {
+    fn as_ref(&self) -> &T {
+        // A handle is only made for a slot after its value is set
+        self.block.as_ref()[self.index].get().unwrap()
+    }
+}
+
+impl<T> Borrow<T> for MlspArenaHandle<T> {
+    fn borrow(&self) -> &T {
+        self.as_ref()
+    }
+}
+
+impl<T> Clone for MlspArenaHandle<T> {
+    fn clone(&self) -> Self {
+        MlspArenaHandle {
+            block: self.block.clone(),
+            index: self.index,
+        }
+    }
+}
+
+/// A reference to a value stored in an `MlspArena` that can be sent across threads.
+pub struct MlspArenaPackage<T> {
+    block: MlspPackage<[OnceLock<T>]>,
+    index: usize,
+}
+
+impl<T> MlspArenaPackage<T> {
+    /// Turns this package into a handle that can
+    /// be shared within this thread without atomic operations.
+    pub fn unpackage(self) -> MlspArenaHandle<T> {
+        MlspArenaHandle {
+            block: self.block.unpackage(),
+            index: self.index,
+        }
+    }
+}
+
+impl<T> Clone for MlspArenaPackage<T> {
+    fn clone(&self) -> Self {
+        MlspArenaPackage {
+            block: self.block.clone(),
+            index: self.index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Counts how many values have been dropped
+    struct Dropped(Arc<AtomicUsize>, usize);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn blocks_outlive_the_arena() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let arena = MlspArena::with_block_len(3);
+        let handles: Vec<_> = (0..4)
+            .map(|i| arena.alloc(Dropped(drops.clone(), i)))
+            .collect();
+
+        // The first three values share a block, the fourth starts another
+        let block = |i: usize| handles[i].block.as_ptr() as *const ();
+        assert!(block(0) == block(2));
+        assert!(block(2) != block(3));
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            handles.iter().map(|h| h.as_ref().1).collect::<Vec<_>>()
+        );
+        let stored = |i: usize| handles[i].block_values().map(|d| d.1).collect::<Vec<_>>();
+        assert_eq!(vec![0, 1, 2], stored(1));
+        assert_eq!(vec![3], stored(3));
+
+        // Dropping the arena only releases its own handle to the current block
+        drop(arena);
+        let mut handles = handles.into_iter();
+        let first = handles.next().unwrap();
+        drop(handles);
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+
+        // The first block goes, with all its values, once its last handle does
+        let weak = first.block.downgrade();
+        let package = first.clone().package();
+        drop(first);
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+        thread::spawn(move || assert_eq!(0, package.unpackage().as_ref().1))
+            .join()
+            .unwrap();
+        assert_eq!(4, drops.load(Ordering::Relaxed));
+        assert_eq!(0, weak.strong_count());
+    }
+
+    #[test]
+    fn partial_blocks_drop_only_stored_values() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let arena = MlspArena::new();
+        let a = arena.alloc(Dropped(drops.clone(), 0));
+        let b = a.clone();
+        drop(arena);
+        drop(a);
+        assert_eq!(0, drops.load(Ordering::Relaxed));
+        drop(b);
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,255 @@
+//! Trait objects behind a shared allocation, enabled by the `unsize` feature.
+//!
+//! Stable Rust cannot express "`T` coerces to `Dyn`" as a bound, so a package is turned into
+//! a trait object by the `into_dyn!` macro, which performs the coercion on a raw pointer where
+//! the compiler checks it and hands the result to `MlspPackage::into_dyn_unchecked`.
+//!
+//! A package of `dyn Any + Send + Sync`, such as a message in a type-erased mailbox,
+//! is turned back into a handle to its concrete type with `MlspPackage::unpackage_downcast`.
+
+use std::any::Any;
+use std::borrow::Borrow;
+use std::mem;
+
+use crate::{Mlsp, MlspPackage};
+
+/// Turns an `MlspPackage<T>` into an `MlspDyn<Dyn>` for a trait object type `Dyn`
+/// that `T` coerces to, such as `dyn Fn()` for a closure.
+///
+/// The coercion is checked by the compiler, a type that `T` does not coerce to is rejected.
+/// ```
+/// let package = mlsp::Mlsp::new(|| 5).package();
+/// let f = mlsp::into_dyn!(package, dyn Fn() -> i32);
+/// assert_eq!(5, (f.as_ref())());
+/// ```
+#[macro_export]
+macro_rules! into_dyn {
+    ($package:expr, $dyn:ty) => {{
+        let package: $crate::MlspPackage<_> = $package;
+        // SAFETY: The closure only performs an unsizing coercion of its argument
+        unsafe { package.into_dyn_unchecked(|ptr| -> *const $dyn { ptr }) }
+    }};
+}
+
+/// A thread-mobile handle to contents of an unsized type, usually a trait object.
+///
+/// Like an `Arc`, every clone and drop updates the atomic counter directly,
+/// so it can be stored and shared wherever an `Arc<dyn Trait>` would be.
+/// A thread that clones it often can use `unpackage` to get an `Mlsp` with a local counter.
+/// ```
+/// let packages = vec![
+///     mlsp::into_dyn!(mlsp::Mlsp::new(1u8).package(), dyn std::fmt::Debug),
+///     mlsp::into_dyn!(mlsp::Mlsp::new("two").package(), dyn std::fmt::Debug),
+/// ];
+/// let printed: Vec<String> = packages.iter().map(|d| format!("{:?}", d.as_ref())).collect();
+/// assert_eq!(vec!["1", "\"two\""], printed);
+/// ```
+pub struct MlspDyn<Dyn: ?Sized> {
+    package: MlspPackage<Dyn>,
+}
+
+impl<T> MlspPackage<T> {
+    /// Turns this package into an `MlspDyn<Dyn>` using `coerce` to unsize the pointer
+    /// to its contents, prefer the `into_dyn!` macro which supplies the coercion.
+    ///
+    /// # Safety
+    /// `coerce` must return the pointer it is given, converted by an unsizing coercion.
+    /// Any other pointer, even one to the same address, may free the allocation with
+    /// the wrong layout or drop the contents as the wrong type.
+    pub unsafe fn into_dyn_unchecked<Dyn: ?Sized>(
+        self,
+        coerce: impl FnOnce(*const T) -> *const Dyn,
+    ) -> MlspDyn<Dyn> {
+        let package = self.cast(|ptr| {
+            let ptr = coerce(ptr);
+            debug_assert_eq!(mem::size_of::<T>(), mem::size_of_val(&*ptr));
+            ptr
+        });
+        MlspDyn { package }
+    }
+}
+
+impl<T: ?Sized> MlspPackage<T> {
+    /// Turns this package into a package of the pointer returned by `cast`,
+    /// keeping where the package came from.
+    ///
+    /// # Safety
+    /// `cast` must return a pointer to the same contents that `from_raw` accepts for `U`.
+    unsafe fn cast<U: ?Sized>(self, cast: impl FnOnce(*const T) -> *const U) -> MlspPackage<U> {
+        #[cfg(feature = "debug")]
+        let source = self.source;
+        let package = MlspPackage::from_raw(cast(self.into_raw()));
+        // The pointer round trip loses where the package came from
+        #[cfg(feature = "debug")]
+        let package = {
+            let mut package = package;
+            package.source = source;
+            package
+        };
+        package
+    }
+}
+
+impl MlspPackage<dyn Any + Send + Sync> {
+    /// Unpackages the contents as a `T` if that is their concrete type,
+    /// otherwise returns the package unchanged.
+    ///
+    /// This is the receiving side of a mailbox of type-erased packages,
+    /// a handler tries each type it accepts until one matches.
+    /// ```
+    /// use std::any::Any;
+    ///
+    /// let package = mlsp::into_dyn!(mlsp::Mlsp::new(7u32).package(), dyn Any + Send + Sync)
+    ///     .into_package();
+    /// let package = package.unpackage_downcast::<String>().err().unwrap();
+    /// assert_eq!(7, *package.unpackage_downcast::<u32>().ok().unwrap().as_ref());
+    /// ```
+    pub fn unpackage_downcast<T: Any + Send + Sync>(self) -> Result<Mlsp<T>, Self> {
+        // SAFETY: The package keeps the contents alive
+        if !unsafe { &*self.as_ptr() }.is::<T>() {
+            return Err(self);
+        }
+
+        // SAFETY: The contents are a `T`, so dropping the vtable gives the pointer
+        // that `into_raw` returns for a package of `T`
+        let package = unsafe { self.cast(|ptr| ptr as *const T) };
+        Ok(package.unpackage())
+    }
+}
+
+impl<Dyn: ?Sized> MlspDyn<Dyn> {
+    /// Converts this handle into an Mlsp for the current thread, without an atomic operation.
+    pub fn unpackage(self) -> Mlsp<Dyn> {
+        self.package.unpackage()
+    }
+
+    /// Converts this handle back into the package it was made from.
+    pub fn into_package(self) -> MlspPackage<Dyn> {
+        self.package
+    }
+
+    /// A pointer to the contents, which stays valid as long as any reference to them exists.
+    pub fn as_ptr(&self) -> *const Dyn {
+        self.package.as_ptr()
+    }
+}
+
+impl<Dyn: ?Sized> Clone for MlspDyn<Dyn> {
+    /// Clones the handle with a single atomic increment.
+    fn clone(&self) -> Self {
+        MlspDyn {
+            package: self.package.clone(),
+        }
+    }
+}
+
+impl<Dyn: ?Sized> AsRef<Dyn> for MlspDyn<Dyn> {
+    fn as_ref(&self) -> &Dyn {
+        // SAFETY: The package keeps the contents alive, and they are only shared immutably
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<Dyn: ?Sized> Borrow<Dyn> for MlspDyn<Dyn> {
+    fn borrow(&self) -> &Dyn {
+        self.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::thread;
+
+    fn named() -> String {
+        String::from("fn item")
+    }
+
+    #[test]
+    fn common_trait_object() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = {
+            let calls = calls.clone();
+            Mlsp::new(move || {
+                calls.set(calls.get() + 1);
+            })
+        };
+        let weak = counted.downgrade();
+
+        let greeting = String::from("captured");
+        let fns: Vec<MlspDyn<dyn Fn()>> = vec![
+            into_dyn!(counted.package(), dyn Fn()),
+            into_dyn!(
+                Mlsp::new(move || assert_eq!("captured", greeting)).package(),
+                dyn Fn()
+            ),
+            into_dyn!(Mlsp::new(|| drop(named())).package(), dyn Fn()),
+        ];
+        drop(counted);
+
+        let copies = fns.clone();
+        for f in fns.iter().chain(&copies) {
+            (f.as_ref())();
+        }
+        assert_eq!(2, calls.get());
+
+        // Every handle points at the original allocation, which is freed with the last of them
+        assert_eq!(2, weak.strong_count());
+        drop(fns);
+        let local = copies.into_iter().next().unwrap().unpackage();
+        (local.as_ref())();
+        assert_eq!(3, calls.get());
+        drop(local);
+        assert_eq!(0, weak.strong_count());
+        assert_eq!(1, Rc::strong_count(&calls));
+    }
+
+    #[test]
+    fn downcast_messages() {
+        type Message = MlspPackage<dyn Any + Send + Sync>;
+
+        let text = Mlsp::new(String::from("text"));
+        let weak = text.downgrade();
+        let mailbox: Vec<Message> = vec![
+            into_dyn!(text.package(), dyn Any + Send + Sync).into_package(),
+            into_dyn!(Mlsp::new(3u64).package(), dyn Any + Send + Sync).into_package(),
+        ];
+        drop(text);
+
+        let handled: Vec<String> = thread::spawn(move || {
+            mailbox
+                .into_iter()
+                .map(|message| match message.unpackage_downcast::<String>() {
+                    Ok(text) => AsRef::<String>::as_ref(&text).clone(),
+                    // The wrong type hands the package back intact, still counted
+                    Err(message) => {
+                        let number = message.unpackage_downcast::<u64>().ok().unwrap();
+                        number.as_ref().to_string()
+                    }
+                })
+                .collect()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(vec!["text", "3"], handled);
+        assert_eq!(0, weak.strong_count());
+
+        let message: Message =
+            into_dyn!(Mlsp::new(1u8).package(), dyn Any + Send + Sync).into_package();
+        let message = message.unpackage_downcast::<u16>().err().unwrap();
+        let typed = message.unpackage_downcast::<u8>().ok().unwrap();
+        assert!(typed.was_unpackaged());
+        assert_eq!(1, *typed.as_ref());
+    }
+
+    #[test]
+    fn sent_between_threads() {
+        let f = into_dyn!(Mlsp::new(named).package(), dyn Fn() -> String + Send + Sync);
+        let copy = f.clone();
+        let result = thread::spawn(move || (copy.as_ref())()).join().unwrap();
+        assert_eq!(result, (f.as_ref())());
+    }
+}
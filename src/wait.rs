@@ -0,0 +1,154 @@
+//! Blocking until a value is uniquely owned, enabled by the `wait` feature.
+//!
+//! Every inner carries a mutex and condition variable, and every atomic decrement
+//! takes the mutex so that a thread in `Mlsp::wait_unique` can't miss the decrement it waits for.
+//! Local clones and drops are unaffected, but handoffs through packages pay for the lock.
+
+use std::sync::atomic::Ordering;
+use std::sync::{Condvar, Mutex};
+
+use crate::atomic::AtomicUsize;
+use crate::Mlsp;
+
+/// The threads waiting for an allocation's atomic count to drop.
+pub(crate) struct Waiters {
+    lock: Mutex<()>,
+    released: Condvar,
+}
+
+impl Waiters {
+    pub(crate) fn new() -> Self {
+        Waiters {
+            lock: Mutex::new(()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Decrements `count` by `n`, which must be the atomic count of the inner holding
+    /// these waiters, and wakes any waiting threads. Returns the count before the decrement.
+    ///
+    /// The decrement happens under the lock, so the last reference can't be released
+    /// and the inner freed by another thread until the waiters have been notified.
+    pub(crate) fn decrement(&self, count: &AtomicUsize, n: usize) -> usize {
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let old = count.fetch_sub(n, Ordering::Release);
+        self.released.notify_all();
+        old
+    }
+}
+
+impl<T> Mlsp<T> {
+    /// Blocks until this is the only `Mlsp` or `MlspPackage` referencing the contents,
+    /// then returns them.
+    ///
+    /// This lets a coordinator reclaim a value it handed out once every worker is done with it.
+    /// Outstanding `MlspWeak`s do not prevent this, as with `try_unwrap`.
+    ///
+    /// # Panics
+    /// Panics if other handles to the contents exist on this thread,
+    /// since they could not be dropped while it waits.
+    /// Handles made after the allocation became hot have no local counter to tell
+    /// whether the thread holds others, so this also panics for them, see `Mlsp::is_hot`.
+    /// ```
+    /// let a = mlsp::Mlsp::new(vec![1, 2]);
+    /// let package = a.package();
+    /// let worker = std::thread::spawn(move || package.unpackage().as_ref().len());
+    ///
+    /// assert_eq!(vec![1, 2], a.wait_unique());
+    /// assert_eq!(2, worker.join().unwrap());
+    /// ```
+    pub fn wait_unique(mut self) -> T {
+        assert!(
+            !self.is_atomic_only(),
+            "wait_unique called on a handle to a hot allocation"
+        );
+        assert!(
+            self.local_handles() == 1,
+            "wait_unique called with other handles on the same thread"
+        );
+
+        loop {
+            // SAFETY: The existence of this Mlsp keeps the inner alive
+            let inner = unsafe { self.inner_ptr.as_ref() };
+            let waiters = &inner.waiters;
+            let mut guard = waiters
+                .lock
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            while inner.atomic_count.load(Ordering::Acquire) != 1 {
+                guard = waiters
+                    .released
+                    .wait(guard)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            drop(guard);
+
+            // A weak reference may upgrade before the contents are claimed, wait for it as well
+            match self.try_unwrap() {
+                Ok(data) => return data,
+                Err(this) => self = this,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::MlspPackage;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn reclaim_after_workers() {
+        const WORKERS: usize = 4;
+
+        let a = Mlsp::new(vec![0u32; 16]);
+        let workers: Vec<_> = a
+            .package_n(WORKERS)
+            .into_iter()
+            .enumerate()
+            .map(|(i, package)| {
+                thread::spawn(move || {
+                    let b = package.unpackage();
+                    let c = b.clone();
+                    thread::sleep(Duration::from_millis(5 * i as u64));
+                    c.as_ref().iter().sum::<u32>()
+                })
+            })
+            .collect();
+
+        let weak = a.downgrade();
+        let data = a.wait_unique();
+        assert_eq!(16, data.len());
+        assert!(weak.upgrade().is_none());
+        for worker in workers {
+            assert_eq!(0, worker.join().unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "other handles on the same thread")]
+    fn local_handles_would_deadlock() {
+        let a = Mlsp::new(1u8);
+        let _b = a.clone();
+        a.wait_unique();
+    }
+
+    #[test]
+    #[should_panic(expected = "hot allocation")]
+    fn hot_handles_would_deadlock() {
+        let a = Mlsp::new(1u8);
+        let packages = a.package_n(Mlsp::<u8>::HOT_THRESHOLD);
+        drop(a);
+
+        // Each handle holds its own share, which the other can't release while this one waits
+        let mut handles = packages.into_iter().map(MlspPackage::unpackage);
+        let (b, _c) = (handles.next().unwrap(), handles.next().unwrap());
+        b.wait_unique();
+    }
+}
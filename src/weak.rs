@@ -0,0 +1,295 @@
+use std::hash::{Hash, Hasher};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "debug")]
+use crate::debug;
+#[cfg(feature = "tracing")]
+use crate::trace;
+use crate::{Mlsp, MlspInner, MlspPackage};
+
+/// A reference to the contents of an Mlsp that does not keep them alive.
+///
+/// Weak references only use the atomic counters, so unlike `Mlsp`
+/// they can be sent between threads directly.
+/// ```
+/// let a = mlsp::Mlsp::new(1u8);
+/// let weak = a.downgrade();
+/// assert_eq!(Some(1u8), weak.upgrade().map(|a| *a.as_ref()));
+///
+/// drop(a);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct MlspWeak<T: ?Sized> {
+    inner_ptr: NonNull<MlspInner<T>>,
+}
+
+impl<T: ?Sized> Mlsp<T> {
+    /// Creates a weak reference to the contents of this Mlsp.
+    ///
+    /// This increments the weak_count
+    pub fn downgrade(&self) -> MlspWeak<T> {
+        unsafe {
+            self.inner_ptr.as_ref().increment_weak();
+        }
+
+        MlspWeak {
+            inner_ptr: self.inner_ptr,
+        }
+    }
+
+    /// Creates a weak reference to the contents of this Mlsp,
+    /// returning `None` if the atomic_count has already reached zero.
+    ///
+    /// The count cannot reach zero while a correctly obtained Mlsp exists,
+    /// so this only fails for handles rebuilt through unsafe code after the contents were dropped,
+    /// such as from a stale raw pointer or token.
+    pub fn try_downgrade(&self) -> Option<MlspWeak<T>> {
+        // SAFETY: The existence of this Mlsp keeps the allocation alive, if not the contents
+        if unsafe { self.inner_ptr.as_ref().atomic_count.load(Ordering::Acquire) } == 0 {
+            return None;
+        }
+
+        Some(self.downgrade())
+    }
+}
+
+impl<T> MlspWeak<T> {
+    /// Creates a weak reference that refers to nothing and never upgrades,
+    /// without allocating.
+    pub fn new() -> Self {
+        MlspWeak {
+            // SAFETY: usize::MAX is not null
+            inner_ptr: unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(DANGLING)) },
+        }
+    }
+}
+
+/// The address of weak references created by `MlspWeak::new`,
+/// which can never be the address of an allocation since it is not aligned for the counters.
+const DANGLING: usize = usize::MAX;
+
+impl<T: ?Sized> MlspWeak<T> {
+    /// The allocation, unless this weak reference was created by `new`
+    fn inner(&self) -> Option<&MlspInner<T>> {
+        if self.inner_ptr.as_ptr() as *const () as usize == DANGLING {
+            None
+        } else {
+            // SAFETY: The weak reference keeps the allocation alive
+            Some(unsafe { self.inner_ptr.as_ref() })
+        }
+    }
+
+    /// Attempts to create a new Mlsp for this thread,
+    /// returning `None` if the contents have already been dropped.
+    ///
+    /// On success this increments the atomic_count.
+    /// The increment is a compare-and-swap that never moves the count up from zero,
+    /// so an upgrade racing with the drop of the last strong reference on another thread
+    /// either takes effect first and keeps the contents alive, or fails.
+    /// It never returns a handle to contents that are being dropped.
+    pub fn upgrade(&self) -> Option<Mlsp<T>> {
+        // SAFETY: The new Mlsp decrements the counter when it is dropped
+        if unsafe { self.inner()?.try_increment() } {
+            Some(Mlsp::from_inner(self.inner_ptr, false))
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to create a Send-able package,
+    /// returning `None` if the contents have already been dropped.
+    ///
+    /// This is the same compare-and-swap as `upgrade`, but the result can be handed to
+    /// another thread without creating a local counter on this one,
+    /// as `upgrade` followed by `into_package` would.
+    pub fn upgrade_to_package(&self) -> Option<MlspPackage<T>> {
+        // SAFETY: The package decrements the counter once when dropped
+        if !unsafe { self.inner()?.try_increment() } {
+            return None;
+        }
+
+        #[cfg(feature = "debug")]
+        debug::package_created(self.inner_ptr);
+
+        #[cfg(feature = "tracing")]
+        trace::package(self.inner_ptr.as_ptr(), 1);
+
+        Some(MlspPackage::from_inner(self.inner_ptr))
+    }
+
+    /// The atomic count of the allocation, which is zero once the contents have been dropped.
+    ///
+    /// Each thread holding `Mlsp`s contributes one to this count,
+    /// as does each `MlspPackage`.
+    ///
+    /// Weak references created by `new` always report zero.
+    pub fn strong_count(&self) -> usize {
+        self.inner()
+            .map_or(0, |inner| inner.atomic_count.load(Ordering::Acquire))
+    }
+}
+
+impl<T: ?Sized> Clone for MlspWeak<T> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = self.inner() {
+            unsafe { inner.increment_weak() };
+        }
+
+        MlspWeak {
+            inner_ptr: self.inner_ptr,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MlspWeak<T> {
+    fn drop(&mut self) {
+        if self.inner().is_none() {
+            return;
+        }
+
+        unsafe {
+            // Decrement the weak counter on the MlspInner and free it if necessary
+            MlspInner::decrement_weak(self.inner_ptr);
+        }
+    }
+}
+
+impl<T> Default for MlspWeak<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Weak references are equal if they reference the same allocation,
+/// whether or not its contents have been dropped.
+/// All weak references created by `new` are equal to each other.
+///
+/// Comparing contents would be unsound since they may already be gone,
+/// and the allocation cannot be reused while a weak reference keeps it alive.
+impl<T: ?Sized> PartialEq for MlspWeak<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner_ptr.as_ptr() as *const () == other.inner_ptr.as_ptr() as *const ()
+    }
+}
+
+impl<T: ?Sized> Eq for MlspWeak<T> {}
+
+/// Hashes the address of the allocation, consistent with `PartialEq`.
+impl<T: ?Sized> Hash for MlspWeak<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.inner_ptr.as_ptr() as *const ()).hash(state);
+    }
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for MlspWeak<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for MlspWeak<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_while_alive() {
+        let a = Mlsp::new(String::from("weak"));
+        let weak = a.downgrade();
+        let weak_clone = weak.clone();
+
+        let b = weak.upgrade().unwrap();
+        assert_eq!("weak", AsRef::<str>::as_ref(&b));
+        assert_eq!(2, weak.strong_count());
+
+        drop(b);
+        assert_eq!(1, weak_clone.strong_count());
+    }
+
+    #[test]
+    fn upgrade_after_drop() {
+        let a = Mlsp::new(String::from("weak"));
+        let package = a.package();
+        let weak = a.downgrade();
+
+        drop(a);
+        assert!(weak.upgrade().is_some());
+
+        drop(package);
+        assert_eq!(0, weak.strong_count());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn checked_downgrade() {
+        let a = Mlsp::new(1u8);
+        let weak = a.try_downgrade().unwrap();
+        assert_eq!(1, weak.strong_count());
+        assert!(a.try_downgrade().unwrap() == weak);
+    }
+
+    #[test]
+    fn upgrade_to_package() {
+        use std::thread;
+
+        let a = Mlsp::new(String::from("weak"));
+        let weak = a.downgrade();
+
+        let package = weak.upgrade_to_package().unwrap();
+        assert_eq!(2, weak.strong_count());
+        let len = thread::spawn(move || package.unpackage().len())
+            .join()
+            .unwrap();
+        assert_eq!(4, len);
+        assert_eq!(1, weak.strong_count());
+
+        drop(a);
+        assert!(weak.upgrade_to_package().is_none());
+    }
+
+    #[test]
+    fn dangling() {
+        let weak = MlspWeak::<String>::new();
+        assert!(weak.upgrade().is_none());
+        assert!(weak.upgrade_to_package().is_none());
+        assert_eq!(0, weak.strong_count());
+        assert!(weak.clone() == MlspWeak::default());
+
+        let a = Mlsp::new(String::new());
+        assert!(weak != a.downgrade());
+    }
+
+    #[test]
+    fn cross_thread_upgrade() {
+        use std::thread;
+
+        let a = Mlsp::new(1u8);
+        let weak = a.downgrade();
+
+        let upgraded = thread::spawn(move || weak.upgrade().map(|b| *b.as_ref()))
+            .join()
+            .unwrap();
+        assert_eq!(Some(1u8), upgraded);
+    }
+
+    #[test]
+    fn identity_keys() {
+        use std::collections::HashMap;
+
+        let a = Mlsp::new(1u8);
+        let b = Mlsp::new(1u8);
+        let a_weak = a.downgrade();
+
+        // Equal contents do not make equal weaks
+        assert!(a_weak == a.downgrade());
+        assert!(a_weak != b.downgrade());
+
+        let mut observers = HashMap::new();
+        observers.insert(a.downgrade(), "a");
+        observers.insert(b.downgrade(), "b");
+        observers.insert(a_weak.clone(), "a again");
+        assert_eq!(2, observers.len());
+        assert_eq!(Some(&"a again"), observers.get(&a.downgrade()));
+
+        // The identity is kept after the contents are dropped
+        drop(a);
+        assert_eq!(Some(&"a again"), observers.get(&a_weak));
+    }
+}
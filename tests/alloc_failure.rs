@@ -0,0 +1,146 @@
+/// Tests for the fallible allocation paths, using a global allocator
+/// that can be told to fail and that tracks the bytes each thread has live
+/// and the allocations it has made.
+use mlsp::{AllocError, Mlsp, MlspPackage};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::panic;
+
+struct TestAllocator;
+
+thread_local! {
+    static FAIL: Cell<bool> = const { Cell::new(false) };
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for TestAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if FAIL.with(Cell::get) {
+            return std::ptr::null_mut();
+        }
+        LIVE_BYTES.with(|live| live.set(live.get() + layout.size() as isize));
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.with(|live| live.set(live.get() - layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TestAllocator = TestAllocator;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(Cell::get)
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn try_from_slice_success() {
+    let items = vec!["a".to_string(), "b".to_string()];
+
+    let shared = Mlsp::try_from_slice(&items).unwrap();
+    assert_eq!(items.as_slice(), shared.as_ref());
+
+    let shared: Mlsp<[String]> = items.as_slice().try_into().unwrap();
+    assert_eq!(items.as_slice(), shared.as_ref());
+}
+
+#[test]
+fn try_from_slice_allocation_failure() {
+    let items = [1u32, 2, 3];
+    let before = live_bytes();
+
+    FAIL.with(|fail| fail.set(true));
+    let result = Mlsp::try_from_slice(&items);
+    FAIL.with(|fail| fail.set(false));
+
+    assert_eq!(Some(AllocError), result.err());
+    assert_eq!(before, live_bytes());
+}
+
+#[test]
+fn try_from_slice_clone_panic() {
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+        static DROPS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct PanicsOnThirdClone;
+
+    impl Clone for PanicsOnThirdClone {
+        fn clone(&self) -> Self {
+            let clones = CLONES.with(|clones| {
+                clones.set(clones.get() + 1);
+                clones.get()
+            });
+            if clones == 3 {
+                panic!("third clone");
+            }
+            PanicsOnThirdClone
+        }
+    }
+
+    impl Drop for PanicsOnThirdClone {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+
+    let items = vec![PanicsOnThirdClone, PanicsOnThirdClone, PanicsOnThirdClone];
+
+    // Keep the default hook from allocating while the panic is reported
+    panic::set_hook(Box::new(|_| {}));
+    let before = live_bytes();
+    let result = panic::catch_unwind(|| Mlsp::try_from_slice(&items));
+    let payload = result.err();
+    drop(payload);
+    let after = live_bytes();
+    let _ = panic::take_hook();
+
+    // The two successful clones were dropped and the allocation was freed
+    assert_eq!(2, DROPS.with(Cell::get));
+    assert_eq!(before, after);
+}
+
+#[test]
+fn package_from_value_skips_the_local_counter() {
+    let before = allocations();
+    let direct = MlspPackage::from_value(1u64);
+    let direct_allocations = allocations() - before;
+
+    let before = allocations();
+    let through_handle = Mlsp::new(2u64).into_package();
+    let handle_allocations = allocations() - before;
+
+    assert!(direct_allocations < handle_allocations);
+    assert_eq!(1, *direct.unpackage().as_ref());
+    assert_eq!(2, *through_handle.unpackage().as_ref());
+}
+
+// The debug registry allocates for new handles by itself, CI runs this with only `pool`
+#[cfg(all(feature = "pool", not(feature = "debug")))]
+#[test]
+fn prewarmed_counters_skip_the_allocator() {
+    let a = Mlsp::new(String::from("warm"));
+    let packages = a.package_n(8);
+    let mut handles = Vec::with_capacity(9);
+
+    mlsp::pool::prewarm(8);
+    let before = allocations();
+    for package in packages {
+        handles.push(package.unpackage());
+    }
+    assert_eq!(before, allocations());
+
+    // The pool is empty again, so the next counter comes from the allocator
+    handles.push(a.package().unpackage());
+    assert_eq!(before + 1, allocations());
+}
@@ -0,0 +1,107 @@
+//! Checks that the formatting, error and comparison impls of each handle forward to the contents
+//! where they should, and that the counts-showing `Debug` of a handle is the one used
+//! even for handles that deref to their contents.
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use mlsp::{FrozenMlsp, Mlsp};
+
+#[derive(Debug)]
+struct Failure {
+    cause: std::io::Error,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed")
+    }
+}
+
+impl Error for Failure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+#[test]
+fn display_forwards() {
+    assert_eq!("text", Mlsp::new(String::from("text")).to_string());
+    assert_eq!("1.5", Mlsp::new(1.5f64).to_string());
+    assert_eq!("  7", format!("{:>3}", Mlsp::new(7u8)));
+    assert_eq!("frozen", Mlsp::new("frozen").freeze().to_string());
+}
+
+#[test]
+fn debug_shows_counts() {
+    let a = Mlsp::new(vec![1u8]);
+    let b = a.clone();
+    let package = a.package();
+    assert_eq!(
+        "Mlsp { data: [1], local_count: 2, atomic_count: 2 }",
+        format!("{:?}", b)
+    );
+    assert_eq!(
+        "MlspPackage { data: [1], atomic_count: 2 }",
+        format!("{:?}", package)
+    );
+    assert_eq!(
+        "Mlsp { data: [1, 2], local_count: 1, atomic_count: 1 }",
+        format!("{:?}", Mlsp::<[u8]>::from(vec![1, 2]))
+    );
+
+    // A frozen handle derefs to its contents, but is shown as the handle
+    let frozen: FrozenMlsp<Vec<u8>> = b.freeze();
+    assert_eq!("[1]", format!("{:?}", frozen.deref()));
+    assert_eq!(
+        "FrozenMlsp(Mlsp { data: [1], local_count: 2, atomic_count: 2 })",
+        format!("{:?}", frozen)
+    );
+}
+
+#[test]
+fn pointer_is_the_contents() {
+    let a = Mlsp::new(5u32);
+    let address = format!("{:p}", a.as_ptr());
+    assert_eq!(address, format!("{:p}", a));
+    assert_eq!(address, format!("{:p}", a.clone()));
+    assert_eq!(address, format!("{:p}", a.package()));
+    assert_eq!(address, format!("{:p}", a.clone().freeze()));
+    assert_ne!(address, format!("{:p}", Mlsp::new(5u32)));
+}
+
+#[test]
+fn errors_forward_their_source() {
+    let failure = Mlsp::new(Failure {
+        cause: std::io::Error::other("disk"),
+    });
+    assert_eq!("failed", failure.to_string());
+    assert_eq!("disk", failure.source().unwrap().to_string());
+
+    // Usable wherever a boxed error is expected, with the chain intact
+    let boxed: Box<dyn Error> = Box::new(failure.clone());
+    assert_eq!("disk", boxed.source().unwrap().to_string());
+    let package = failure.package();
+    let sent = std::thread::spawn(move || package.unpackage().source().map(|e| e.to_string()));
+    assert_eq!(Some(String::from("disk")), sent.join().unwrap());
+}
+
+#[test]
+fn compares_with_arc_and_rc() {
+    let a = Mlsp::new(String::from("same"));
+    assert!(a == Arc::new(String::from("same")));
+    assert!(Arc::new(String::from("same")) == a);
+    assert!(a != Arc::new(String::from("other")));
+    assert!(Arc::new(String::from("other")) != a);
+
+    assert!(a == Rc::new(String::from("same")));
+    assert!(Rc::new(String::from("same")) == a);
+    assert!(a != Rc::new(String::from("other")));
+
+    // Unsized contents compare the same way
+    let bytes = Mlsp::<[u8]>::from(vec![1, 2]);
+    assert!(bytes == Arc::<[u8]>::from(vec![1, 2]));
+    assert!(bytes != Rc::<[u8]>::from(vec![2, 1]));
+}
@@ -0,0 +1,244 @@
+//! Model checks of the races between threads sharing an allocation,
+//! such as weak upgrades racing the drop of the last strong reference.
+//!
+//! These only run when the crate is built against loom's atomics:
+//! `RUSTFLAGS="--cfg loom" cargo test --release --test loom`
+#![cfg(loom)]
+
+use loom::cell::UnsafeCell;
+use loom::sync::atomic::{AtomicBool, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+use mlsp::Mlsp;
+
+/// Sets its flag when dropped, so a handle can check that its contents are still alive
+struct Canary(Arc<AtomicBool>);
+
+impl Canary {
+    fn assert_alive(&self) {
+        assert!(
+            !self.0.load(Ordering::Acquire),
+            "observed contents after they were dropped"
+        );
+    }
+}
+
+impl Drop for Canary {
+    fn drop(&mut self) {
+        assert!(
+            !self.0.swap(true, Ordering::AcqRel),
+            "contents dropped twice"
+        );
+    }
+}
+
+#[test]
+fn upgrade_races_last_drop() {
+    loom::model(|| {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let a = Mlsp::new(Canary(dropped.clone()));
+        let weak = a.downgrade();
+
+        let upgrader = thread::spawn(move || {
+            if let Some(b) = weak.upgrade() {
+                b.as_ref().assert_alive();
+            }
+        });
+
+        drop(a);
+        upgrader.join().unwrap();
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
+
+#[test]
+fn upgrade_to_package_races_last_package_drop() {
+    loom::model(|| {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let a = Mlsp::new(Canary(dropped.clone()));
+        let weak = a.downgrade();
+        let package = a.package();
+        drop(a);
+
+        let dropper = thread::spawn(move || drop(package));
+
+        if let Some(package) = weak.upgrade_to_package() {
+            package.unpackage().as_ref().assert_alive();
+        }
+        assert!(weak
+            .upgrade()
+            .is_none_or(|b| !b.as_ref().0.load(Ordering::Acquire)));
+
+        dropper.join().unwrap();
+        drop(weak);
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
+
+/// Two slots written by different threads, each read by the drop of the pair.
+/// loom reports a race if either write does not happen before the drop.
+struct Pair([UnsafeCell<usize>; 2]);
+
+// SAFETY: Each slot is written by one thread, and read only when the pair is dropped
+unsafe impl Sync for Pair {}
+
+impl Drop for Pair {
+    fn drop(&mut self) {
+        for slot in &self.0 {
+            slot.with(|value| assert_eq!(1, unsafe { *value }));
+        }
+    }
+}
+
+#[test]
+fn last_drop_sees_every_write() {
+    loom::model(|| {
+        let a = Mlsp::new(Pair([UnsafeCell::new(0), UnsafeCell::new(0)]));
+        let package = a.package();
+
+        let writer = thread::spawn(move || {
+            let b = package.unpackage();
+            b.as_ref().0[1].with_mut(|value| unsafe { *value = 1 });
+        });
+
+        a.as_ref().0[0].with_mut(|value| unsafe { *value = 1 });
+        drop(a);
+        writer.join().unwrap();
+    });
+}
+
+#[test]
+fn stack_pops_race() {
+    use mlsp::MlspStack;
+
+    loom::model(|| {
+        let stack = Arc::new(MlspStack::new());
+        let dropped = Arc::new(AtomicBool::new(false));
+        stack.push(Mlsp::new(Canary(dropped.clone())));
+        stack.push(Mlsp::new(Canary(Arc::new(AtomicBool::new(false)))));
+
+        let popper = {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                if let Some(value) = stack.pop() {
+                    value.as_ref().assert_alive();
+                }
+            })
+        };
+
+        if let Some(value) = stack.pop() {
+            value.as_ref().assert_alive();
+        }
+        popper.join().unwrap();
+
+        assert!(stack.is_empty());
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
+
+#[test]
+fn load_races_store() {
+    use mlsp::AtomicMlsp;
+
+    loom::model(|| {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(Canary(dropped.clone()))));
+
+        let loader = {
+            let slot = slot.clone();
+            thread::spawn(move || slot.load().as_ref().assert_alive())
+        };
+
+        slot.store(Mlsp::new(Canary(Arc::new(AtomicBool::new(false)))));
+        loader.join().unwrap();
+
+        // The replaced value goes once the loader's handle does
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
+
+#[test]
+fn load_races_two_stores() {
+    use mlsp::AtomicMlsp;
+
+    // The second writer spins while the first waits for loads,
+    // which only finishes exploring with few preemptions
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound = Some(2);
+    builder.check(|| {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let slot = Arc::new(AtomicMlsp::new(Mlsp::new(Canary(dropped.clone()))));
+
+        let loader = {
+            let slot = slot.clone();
+            thread::spawn(move || slot.load().as_ref().assert_alive())
+        };
+        let writer = {
+            let slot = slot.clone();
+            thread::spawn(move || slot.store(Mlsp::new(Canary(Arc::new(AtomicBool::new(false))))))
+        };
+
+        slot.store(Mlsp::new(Canary(Arc::new(AtomicBool::new(false)))));
+        writer.join().unwrap();
+        loader.join().unwrap();
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
+
+#[test]
+fn package_from_ptr_races_push() {
+    use loom::sync::atomic::AtomicPtr;
+    use mlsp::MlspPackage;
+    use std::ptr;
+
+    /// A node of a push-only stack, holding the reference to the node below it
+    struct Node {
+        canary: Canary,
+        next: *const Node,
+    }
+
+    unsafe impl Send for Node {}
+    unsafe impl Sync for Node {}
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            if !self.next.is_null() {
+                drop(unsafe { MlspPackage::from_raw(self.next) });
+            }
+        }
+    }
+
+    loom::model(|| {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let bottom = MlspPackage::from_value(Node {
+            canary: Canary(dropped.clone()),
+            next: ptr::null(),
+        });
+        let head = Arc::new(AtomicPtr::new(bottom.into_raw() as *mut Node));
+
+        let pusher = {
+            let head = head.clone();
+            thread::spawn(move || {
+                // The new node takes over the stack's reference to the old head
+                let old = head.load(Ordering::Acquire);
+                let top = MlspPackage::from_value(Node {
+                    canary: Canary(Arc::new(AtomicBool::new(false))),
+                    next: old,
+                });
+                let top = top.into_raw() as *mut Node;
+                assert!(head
+                    .compare_exchange(old, top, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok());
+            })
+        };
+
+        // The stack never releases a node it has published, so the count can't be zero here
+        let current = head.load(Ordering::Acquire);
+        let package = unsafe { Mlsp::package_from_ptr(current) };
+        package.unpackage().as_ref().canary.assert_alive();
+
+        pusher.join().unwrap();
+        drop(unsafe { MlspPackage::from_raw(head.load(Ordering::Acquire)) });
+        assert!(dropped.load(Ordering::Acquire));
+    });
+}
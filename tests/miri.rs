@@ -0,0 +1,135 @@
+//! Small checks of the unsafe core, sized to run under Miri:
+//! `cargo +nightly miri test --test miri`
+//!
+//! They also run as ordinary tests, but their purpose is to give Miri every pointer path
+//! of a handle's life: the local counter, the atomic count, the handoff between threads
+//! and the drop of the contents and the allocation.
+#![cfg(not(loom))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use mlsp::{Mlsp, MlspPackage};
+
+/// Counts its drops, so a test can check the contents are dropped exactly once
+struct Counted(Arc<AtomicUsize>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn clone_and_drop() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let a = Mlsp::new(Counted(drops.clone()));
+    let b = a.clone();
+    let c = b.clone();
+
+    // Dropping in any order leaves the contents to the last handle
+    drop(b);
+    drop(a);
+    assert_eq!(0, drops.load(Ordering::Relaxed));
+    assert!(c.is_unique());
+    drop(c);
+    assert_eq!(1, drops.load(Ordering::Relaxed));
+}
+
+#[test]
+fn package_round_trip() {
+    let a = Mlsp::new(vec![1u32, 2, 3]);
+    let package = a.package();
+    let owned = a.clone().into_package();
+
+    let b = package.unpackage();
+    let c = owned.unpackage();
+    assert_eq!(a.as_ref(), b.as_ref());
+    assert_eq!(a.as_ref(), c.as_ref());
+
+    // Both round trips hand back their references
+    drop((b, c));
+    assert_eq!(vec![1, 2, 3], a.try_unwrap().ok().unwrap());
+
+    // Raw pointers carry the reference across as well.
+    // Tokens are left out, as integers they can't carry provenance for Miri to check.
+    let a = Mlsp::new(String::from("raw"));
+    let ptr = a.package().into_raw();
+    let b = unsafe { MlspPackage::from_raw(ptr) }.unpackage();
+    drop(a);
+    assert_eq!("raw", b.try_unwrap().ok().unwrap());
+}
+
+#[test]
+fn scoped_handoff() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let a = Mlsp::new(Counted(drops.clone()));
+    let packages = a.package_n(2);
+
+    thread::scope(|s| {
+        for package in packages {
+            s.spawn(move || {
+                let b = package.unpackage();
+                let c = b.clone();
+                drop(b);
+                c.as_ref().0.load(Ordering::Relaxed)
+            });
+        }
+    });
+    assert_eq!(0, drops.load(Ordering::Relaxed));
+
+    // The last reference may be dropped on another thread
+    let last = a.into_package();
+    thread::scope(|s| {
+        s.spawn(move || drop(last.unpackage()));
+    });
+    assert_eq!(1, drops.load(Ordering::Relaxed));
+}
+
+#[test]
+fn nested_drop_order() {
+    /// Records its name when dropped
+    struct Named(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Drop for Named {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let inner = Mlsp::new(Named("inner", order.clone()));
+    let outer = Mlsp::new((Named("outer", order.clone()), inner.clone()));
+
+    // The outer contents hold a handle to the inner ones, which outlives them
+    drop(inner);
+    assert!(order.borrow().is_empty());
+    drop(outer);
+    assert_eq!(vec!["outer", "inner"], *order.borrow());
+}
+
+#[test]
+fn weak_references_outlive_contents() {
+    let a = Mlsp::new(String::from("weak"));
+    let weak = a.downgrade();
+    let package = weak.upgrade_to_package().unwrap();
+    drop(a);
+
+    assert_eq!("weak", AsRef::<str>::as_ref(&package.unpackage()));
+    assert!(weak.upgrade().is_none());
+    drop(weak);
+}
+
+#[test]
+fn unsized_slices() {
+    let a = Mlsp::<[String]>::from(vec![String::from("a"), String::from("b")]);
+    let package = a.package();
+    let b = thread::spawn(move || package.unpackage().as_ref().concat())
+        .join()
+        .unwrap();
+    assert_eq!("ab", b);
+    assert!(a.is_unique());
+}
@@ -0,0 +1,53 @@
+//! Compile-time checks of the auto traits each type implements.
+//!
+//! `Mlsp` shares a non-atomic local counter between its clones,
+//! so it is only sound as long as it can never leave its thread.
+//! Any change that makes it `Send` or `Sync` breaks the build here.
+use mlsp::{
+    AtomicMlsp, Mlsp, MlspCell, MlspCellPackage, MlspEnvelope, MlspMutex, MlspMutexPackage,
+    MlspObserver, MlspPackage, MlspReadGuard, MlspWeak,
+};
+use static_assertions::{assert_impl_all, assert_not_impl_any};
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::MutexGuard;
+
+// Thread-local handles never cross threads, whatever they contain
+assert_not_impl_any!(Mlsp<u8>: Send, Sync);
+assert_not_impl_any!(Mlsp<[u8]>: Send, Sync);
+assert_not_impl_any!(Mlsp<dyn Send + Sync>: Send, Sync);
+assert_not_impl_any!(MlspMutex<u8>: Send, Sync);
+assert_not_impl_any!(MlspCell<u8>: Send, Sync);
+
+// Packages and weak references cross threads when the contents can be shared
+assert_impl_all!(MlspPackage<u8>: Send, Sync);
+assert_impl_all!(MlspPackage<[u8]>: Send, Sync);
+assert_impl_all!(MlspPackage<dyn Send + Sync>: Send, Sync);
+assert_impl_all!(MlspWeak<u8>: Send, Sync);
+assert_impl_all!(MlspCellPackage<u8>: Send, Sync);
+assert_impl_all!(MlspEnvelope<u8, u8>: Send, Sync);
+assert_impl_all!(AtomicMlsp<u8>: Send, Sync);
+assert_impl_all!(MlspReadGuard<u8>: Send, Sync);
+
+// An observer caches what it has seen, so it can be sent to a subscriber but not shared
+assert_impl_all!(MlspObserver<u8>: Send);
+assert_not_impl_any!(MlspObserver<u8>: Sync);
+assert_not_impl_any!(MlspObserver<Cell<u8>>: Send, Sync);
+
+// A mutex only needs its contents to be Send
+assert_impl_all!(MlspMutexPackage<Cell<u8>>: Send, Sync);
+
+// Contents that are not both Send and Sync keep packages on their thread
+assert_not_impl_any!(MlspPackage<Rc<u8>>: Send, Sync);
+assert_not_impl_any!(MlspPackage<Cell<u8>>: Send, Sync);
+assert_not_impl_any!(MlspPackage<MutexGuard<'static, u8>>: Send, Sync);
+assert_not_impl_any!(MlspWeak<Cell<u8>>: Send, Sync);
+assert_not_impl_any!(MlspCellPackage<Cell<u8>>: Send, Sync);
+assert_not_impl_any!(MlspMutexPackage<Rc<u8>>: Send, Sync);
+assert_not_impl_any!(AtomicMlsp<Cell<u8>>: Send, Sync);
+assert_not_impl_any!(MlspReadGuard<Cell<u8>>: Send, Sync);
+
+// An envelope needs both its tag and its payload to be able to cross
+assert_not_impl_any!(MlspEnvelope<Rc<u8>, u8>: Send, Sync);
+assert_not_impl_any!(MlspEnvelope<u8, Cell<u8>>: Send, Sync);
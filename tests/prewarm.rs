@@ -0,0 +1,54 @@
+//! Checks that handles created after `mlsp::pool::prewarm` take their local counters
+//! without allocating, using a global allocator that counts each thread's allocations.
+//!
+//! The `debug` feature records every handle in a registry that allocates on its own,
+//! so these only run without it.
+#![cfg(all(feature = "pool", not(feature = "debug"), not(loom)))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use mlsp::Mlsp;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn unpackaging_after_prewarm_does_not_allocate() {
+    const N: usize = 16;
+
+    // Separate allocations, since handles unpackaged from a hot one have no counter
+    let packages: Vec<_> = (0..N).map(|i| Mlsp::new(i).into_package()).collect();
+    let mut handles = Vec::with_capacity(N);
+
+    mlsp::pool::prewarm(N);
+    let before = allocations();
+    for package in packages {
+        handles.push(package.unpackage());
+    }
+    assert_eq!(before, allocations());
+
+    // Each handle has a counter of its own, taken from the pool
+    assert!(handles.iter().all(|b| b.count_snapshot().local == 1));
+}